@@ -0,0 +1,149 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! `Alarm` implementation shared by the unit tests of the alarm and timer
+//! virtualizers.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Freq1KHz, Ticks, Ticks32, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+pub struct FakeAlarm<'a, T: Ticks = Ticks32> {
+    now: Cell<T>,
+    /// Whether every read of `now` advances the clock by one tick.
+    free_running: bool,
+    reference: Cell<T>,
+    dt: Cell<T>,
+    armed: Cell<bool>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl FakeAlarm<'_> {
+    /// An alarm whose clock starts at 1000 and is free running.
+    pub fn new() -> Self {
+        Self::with_clock(Ticks32::from(1_000), true)
+    }
+}
+
+impl<T: Ticks> FakeAlarm<'_, T> {
+    /// An alarm whose clock starts at 0 and only moves in `fire_at`.
+    pub fn stopped() -> Self {
+        Self::with_clock(T::from(0), false)
+    }
+
+    fn with_clock(now: T, free_running: bool) -> Self {
+        Self {
+            now: Cell::new(now),
+            free_running,
+            reference: Cell::new(T::from(0)),
+            dt: Cell::new(T::from(0)),
+            armed: Cell::new(false),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// The emulated delay from when hardware timer to when kernel loop will
+    /// run to check if alarms have fired or not.
+    pub fn hardware_delay(&self) -> T {
+        T::from(10)
+    }
+
+    /// Fast forwards time to the next time we would fire an alarm and call client. Returns if
+    /// alarm is still armed after triggering client
+    pub fn trigger_next_alarm(&self) -> bool {
+        if !self.is_armed() {
+            return false;
+        }
+        self.now.set(
+            self.reference
+                .get()
+                .wrapping_add(self.dt.get())
+                .wrapping_add(self.hardware_delay()),
+        );
+        self.client.map(|c| c.alarm());
+        self.is_armed()
+    }
+
+    /// Runs for the specified number of ticks as long as there are alarms armed.
+    pub fn run_for_ticks(&self, left: T) {
+        let final_now = self.now.get().wrapping_add(left);
+        let mut left = left.into_u64();
+
+        while self.is_armed() {
+            // Ensure that we have enough remaining ticks to handle the next alarm. Reference is
+            // always in the past, so we need to figure out the difference between the reference
+            // and now to discount the DT the alarm needs to wait by.
+            let ticks_from_reference = self.now.get().wrapping_sub(self.reference.get());
+            let dt = self
+                .dt
+                .get()
+                .into_u64()
+                .saturating_sub(ticks_from_reference.into_u64());
+            if dt <= left {
+                left -= dt;
+                self.trigger_next_alarm();
+            } else {
+                break;
+            }
+        }
+        // Ensure that we ate up all of the time we were suppose to run for
+        self.now.set(final_now);
+    }
+
+    /// Fire the armed alarm with the clock reading `now`.
+    pub fn fire_at(&self, now: T) {
+        assert!(self.armed.get());
+        self.now.set(now);
+        self.armed.set(false);
+        self.client.map(|c| c.alarm());
+    }
+}
+
+impl<T: Ticks> Time for FakeAlarm<'_, T> {
+    type Ticks = T;
+    type Frequency = Freq1KHz;
+
+    fn now(&self) -> T {
+        if self.free_running {
+            // Every time we get now, it needs to increment to represent a free running timer
+            self.now.set(self.now.get().wrapping_add(T::from(1)));
+        }
+        self.now.get()
+    }
+}
+
+impl<'a, T: Ticks> Alarm<'a> for FakeAlarm<'a, T> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        self.reference.set(reference);
+        self.dt.set(dt);
+        self.armed.set(true);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.reference.get().wrapping_add(self.dt.get())
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.armed.set(false);
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.armed.get()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        T::from(0)
+    }
+}
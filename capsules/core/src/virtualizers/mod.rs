@@ -12,3 +12,6 @@ pub mod virtual_rng;
 pub mod virtual_spi;
 pub mod virtual_timer;
 pub mod virtual_uart;
+
+#[cfg(test)]
+mod fake_alarm;
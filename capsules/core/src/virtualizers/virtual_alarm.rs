@@ -96,6 +96,12 @@ impl<'a, A: Alarm<'a>> Alarm<'a> for VirtualMuxAlarm<'a, A> {
         self.client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        // Leave the alarm armed: when it expires the mux disarms it as
+        // usual, but there is no client to call.
+        self.client.clear();
+    }
+
     fn disarm(&self) -> Result<(), ErrorCode> {
         if !self.armed.get() {
             return Ok(());
@@ -309,117 +315,9 @@ impl<'a, A: Alarm<'a>> time::AlarmClient for MuxAlarm<'a, A> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::virtualizers::fake_alarm::FakeAlarm;
     use time::*;
 
-    struct FakeAlarm<'a> {
-        now: Cell<Ticks32>,
-        reference: Cell<Ticks32>,
-        dt: Cell<Ticks32>,
-        armed: Cell<bool>,
-        client: OptionalCell<&'a dyn AlarmClient>,
-    }
-
-    impl FakeAlarm<'_> {
-        fn new() -> Self {
-            Self {
-                now: Cell::new(1_000u32.into()),
-                reference: Cell::new(0u32.into()),
-                dt: Cell::new(0u32.into()),
-                armed: Cell::new(false),
-                client: OptionalCell::empty(),
-            }
-        }
-
-        /// The emulated delay from when hardware timer to when kernel loop will
-        /// run to check if alarms have fired or not.
-        pub fn hardware_delay(&self) -> Ticks32 {
-            Ticks32::from(10)
-        }
-
-        /// Fast forwards time to the next time we would fire an alarm and call client. Returns if
-        /// alarm is still armed after triggering client
-        pub fn trigger_next_alarm(&self) -> bool {
-            if !self.is_armed() {
-                return false;
-            }
-            self.now.set(
-                self.reference
-                    .get()
-                    .wrapping_add(self.dt.get())
-                    .wrapping_add(self.hardware_delay()),
-            );
-            self.client.map(|c| c.alarm());
-            self.is_armed()
-        }
-
-        /// Runs for the specified number of ticks as long as there are alarms armed.
-        pub fn run_for_ticks(&self, left: Ticks32) {
-            let final_now = self.now.get().wrapping_add(left);
-            let mut left = left.into_u32();
-
-            while self.is_armed() {
-                // Ensure that we have enough remaining ticks to handle the next alarm. Reference is
-                // always in the past, so we need to figure out the difference between the reference
-                // and now to discount the DT the alarm needs to wait by.
-                let ticks_from_reference = self.now.get().wrapping_sub(self.reference.get());
-                let dt = self
-                    .dt
-                    .get()
-                    .into_u32()
-                    .saturating_sub(ticks_from_reference.into_u32());
-                if dt <= left {
-                    left -= dt;
-                    self.trigger_next_alarm();
-                } else {
-                    break;
-                }
-            }
-            // Ensure that we ate up all of the time we were suppose to run for
-            self.now.set(final_now);
-        }
-    }
-
-    impl Time for FakeAlarm<'_> {
-        type Ticks = Ticks32;
-        type Frequency = Freq1KHz;
-
-        fn now(&self) -> Ticks32 {
-            // Every time we get now, it needs to increment to represent a free running timer
-            let new_now = Ticks32::from(self.now.get().into_u32() + 1);
-            self.now.set(new_now);
-            new_now
-        }
-    }
-
-    impl<'a> Alarm<'a> for FakeAlarm<'a> {
-        fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
-            self.client.set(client);
-        }
-
-        fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
-            self.reference.set(reference);
-            self.dt.set(dt);
-            self.armed.set(true);
-        }
-
-        fn get_alarm(&self) -> Self::Ticks {
-            self.reference.get().wrapping_add(self.dt.get())
-        }
-
-        fn disarm(&self) -> Result<(), ErrorCode> {
-            self.armed.set(false);
-            Ok(())
-        }
-
-        fn is_armed(&self) -> bool {
-            self.armed.get()
-        }
-
-        fn minimum_dt(&self) -> Self::Ticks {
-            0u32.into()
-        }
-    }
-
     struct ClientCounter(Cell<usize>);
     impl ClientCounter {
         fn new() -> Self {
@@ -463,6 +361,26 @@ mod tests {
         assert_eq!(client.count(), 1);
     }
 
+    #[test]
+    fn test_cleared_client_not_called() {
+        let alarm = FakeAlarm::new();
+        let client = ClientCounter::new();
+
+        let mux = MuxAlarm::new(&alarm);
+        alarm.set_alarm_client(&mux);
+
+        let valarm = VirtualMuxAlarm::new(&mux);
+        valarm.setup();
+        valarm.set_alarm_client(&client);
+        valarm.set_alarm(valarm.now(), 10.into());
+        valarm.clear_alarm_client();
+
+        run_until_disarmed(&alarm);
+
+        assert_eq!(client.count(), 0);
+        assert!(!valarm.is_armed());
+    }
+
     #[test]
     fn test_multiple_max_ticks_dt() {
         let alarm = FakeAlarm::new();
@@ -23,6 +23,10 @@ enum Mode {
 
 /// An object to multiplex multiple "virtual" timers over a single underlying alarm. A
 /// `VirtualTimer` is a node in a linked list of timers that share the same underlying alarm.
+///
+/// A repeating timer whose callback is delayed by one or more whole intervals skips the
+/// missed intervals, rather than firing once for each of them in quick succession, and
+/// reports them through `fire_count`. The timer stays aligned to its original cadence.
 pub struct VirtualTimer<'a, A: Alarm<'a>> {
     /// Underlying alarm which multiplexes all these virtual timers.
     mux: &'a MuxTimer<'a, A>,
@@ -32,6 +36,8 @@ pub struct VirtualTimer<'a, A: Alarm<'a>> {
     interval: Cell<A::Ticks>,
     /// Current mode of this timer.
     mode: Cell<Mode>,
+    /// Number of intervals which had elapsed at the last callback.
+    fire_count: Cell<u32>,
     /// Next timer in the list.
     next: ListLink<'a, VirtualTimer<'a, A>>,
     /// Timer client for this node in the list.
@@ -53,6 +59,7 @@ impl<'a, A: Alarm<'a>> VirtualTimer<'a, A> {
             when: Cell::new(zero),
             interval: Cell::new(zero),
             mode: Cell::new(Mode::Disabled),
+            fire_count: Cell::new(0),
             next: ListLink::empty(),
             client: OptionalCell::empty(),
         };
@@ -71,12 +78,10 @@ impl<'a, A: Alarm<'a>> VirtualTimer<'a, A> {
             self.mux.enabled.increment();
         }
         self.mode.set(mode);
+        self.fire_count.set(0);
 
         // We can't fire faster than the minimum dt of the alarm.
-        let real_interval: A::Ticks = A::Ticks::from(cmp::max(
-            interval.into_u32(),
-            self.mux.alarm.minimum_dt().into_u32(),
-        ));
+        let real_interval: A::Ticks = cmp::max(interval, self.mux.alarm.minimum_dt());
 
         let now = self.mux.alarm.now();
         self.interval.set(real_interval);
@@ -158,6 +163,10 @@ impl<'a, A: Alarm<'a>> Timer<'a> for VirtualTimer<'a, A> {
             }
         }
     }
+
+    fn fire_count(&self) -> u32 {
+        self.fire_count.get()
+    }
 }
 
 impl<'a, A: Alarm<'a>> time::AlarmClient for VirtualTimer<'a, A> {
@@ -166,14 +175,27 @@ impl<'a, A: Alarm<'a>> time::AlarmClient for VirtualTimer<'a, A> {
             Mode::Disabled => {} // Do nothing
             Mode::OneShot => {
                 self.mode.set(Mode::Disabled);
+                self.fire_count.set(1);
                 self.client.map(|client| client.timer());
             }
             Mode::Repeating => {
+                // If the callback is late by one or more whole intervals,
+                // report the missed intervals through `fire_count` rather
+                // than firing again for each of them.
+                let when = self.when.get();
+                let interval = self.interval.get();
+                let late = self.mux.alarm.now().wrapping_sub(when).into_u64();
+                let missed = match interval.into_u64() {
+                    0 => 0,
+                    interval => late / interval,
+                };
+                self.fire_count
+                    .set(u32::try_from(missed + 1).unwrap_or(u32::MAX));
+
                 // By setting the 'now' to be 'when', this ensures
                 // the the repeating timer fires at a fixed interval:
                 // it'll fire at when + (k * interval), for k=0...n.
-                let when = self.when.get();
-                let interval = self.interval.get();
+                let when = when.wrapping_add(A::Ticks::from_or_max(missed * interval.into_u64()));
                 self.when.set(when.wrapping_add(interval));
                 self.mux.calculate_alarm(when, interval);
                 self.client.map(|client| client.timer());
@@ -251,7 +273,7 @@ impl<'a, A: Alarm<'a>> time::AlarmClient for MuxTimer<'a, A> {
             .timers
             .iter()
             .filter(|cur| cur.is_enabled())
-            .min_by_key(|cur| cur.when.get().wrapping_sub(now).into_u32());
+            .min_by_key(|cur| cur.when.get().wrapping_sub(now));
 
         // Set the alarm.
         if let Some(valrm) = next {
@@ -262,3 +284,96 @@ impl<'a, A: Alarm<'a>> time::AlarmClient for MuxTimer<'a, A> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::virtualizers::fake_alarm::FakeAlarm;
+    use crate::virtualizers::virtual_alarm::MuxAlarm;
+    use time::{Ticks32, Ticks64, TimerClient};
+
+    struct FireCounts<'a, T: Ticks = Ticks32> {
+        timer: &'a VirtualTimer<'a, FakeAlarm<'a, T>>,
+        last: Cell<u32>,
+        callbacks: Cell<u32>,
+    }
+
+    impl<T: Ticks> TimerClient for FireCounts<'_, T> {
+        fn timer(&self) {
+            self.last.set(self.timer.fire_count());
+            self.callbacks.set(self.callbacks.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_fire_count_delayed_callback() {
+        let alarm = FakeAlarm::<Ticks32>::stopped();
+        let mux_alarm = MuxAlarm::new(&alarm);
+        alarm.set_alarm_client(&mux_alarm);
+        let virtual_alarm = VirtualMuxAlarm::new(&mux_alarm);
+        virtual_alarm.setup();
+        let mux_timer = MuxTimer::new(&virtual_alarm);
+        virtual_alarm.set_alarm_client(&mux_timer);
+        let timer = VirtualTimer::new(&mux_timer);
+        timer.setup();
+        let client = FireCounts {
+            timer: &timer,
+            last: Cell::new(0),
+            callbacks: Cell::new(0),
+        };
+        timer.set_timer_client(&client);
+
+        timer.repeating(100u32.into());
+        assert_eq!(timer.fire_count(), 0);
+
+        // On time.
+        alarm.fire_at(100u32.into());
+        assert_eq!(client.callbacks.get(), 1);
+        assert_eq!(client.last.get(), 1);
+
+        // Late by more than two intervals: the deadlines at 200, 300 and
+        // 400 have all passed.
+        alarm.fire_at(450u32.into());
+        assert_eq!(client.callbacks.get(), 2);
+        assert_eq!(client.last.get(), 3);
+        // The cadence is preserved, skipping the missed intervals.
+        assert_eq!(timer.time_remaining(), Some(50u32.into()));
+
+        alarm.fire_at(500u32.into());
+        assert_eq!(client.callbacks.get(), 3);
+        assert_eq!(client.last.get(), 1);
+
+        // Restarting resets the count.
+        timer.oneshot(100u32.into());
+        assert_eq!(timer.fire_count(), 0);
+    }
+
+    #[test]
+    fn test_fire_count_wide_interval() {
+        let alarm = FakeAlarm::<Ticks64>::stopped();
+        let mux_alarm = MuxAlarm::new(&alarm);
+        alarm.set_alarm_client(&mux_alarm);
+        let virtual_alarm = VirtualMuxAlarm::new(&mux_alarm);
+        virtual_alarm.setup();
+        let mux_timer = MuxTimer::new(&virtual_alarm);
+        virtual_alarm.set_alarm_client(&mux_timer);
+        let timer = VirtualTimer::new(&mux_timer);
+        timer.setup();
+        let client = FireCounts {
+            timer: &timer,
+            last: Cell::new(0),
+            callbacks: Cell::new(0),
+        };
+        timer.set_timer_client(&client);
+
+        // An interval which does not fit in 32 bits.
+        const INTERVAL: u64 = 1 << 33;
+        timer.repeating(INTERVAL.into());
+
+        // Late by two and a half intervals.
+        alarm.fire_at((3 * INTERVAL + INTERVAL / 2).into());
+        assert_eq!(client.callbacks.get(), 1);
+        assert_eq!(client.last.get(), 3);
+        assert_eq!(timer.time_remaining(), Some((INTERVAL / 2).into()));
+    }
+}
@@ -0,0 +1,190 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Timer whose client chooses the next interval on each callback.
+//!
+//! Adaptive sampling schemes vary their period at runtime, e.g. sampling
+//! faster while a value is changing. Neither a one-shot nor a repeating
+//! `Timer` fits this well. An `AdaptiveTimer` calls an
+//! `AdaptiveTimerClient`, which returns the interval until the next
+//! callback, or 0 to stop the timer.
+//!
+//! Each interval is measured from the previous deadline rather than from
+//! when the callback ran, so callback latency does not accumulate. If a
+//! callback is so late that the next deadline has already passed, the
+//! next callback happens as soon as possible.
+//!
+//! The `AdaptiveTimer` must be installed as the client of the underlying
+//! alarm.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Client of an `AdaptiveTimer`.
+pub trait AdaptiveTimerClient<T: Ticks> {
+    /// Called when the timer fires. Returns the number of ticks from this
+    /// deadline to the next one, or 0 to stop the timer.
+    fn timer(&self) -> T;
+}
+
+/// Timer on `alarm` whose interval is chosen by its client.
+pub struct AdaptiveTimer<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Start of the current interval.
+    reference: Cell<A::Ticks>,
+    interval: Cell<A::Ticks>,
+    running: Cell<bool>,
+    client: OptionalCell<&'a dyn AdaptiveTimerClient<A::Ticks>>,
+}
+
+impl<'a, A: Alarm<'a>> AdaptiveTimer<'a, A> {
+    pub fn new(alarm: &'a A) -> AdaptiveTimer<'a, A> {
+        AdaptiveTimer {
+            alarm,
+            reference: Cell::new(A::Ticks::from(0)),
+            interval: Cell::new(A::Ticks::from(0)),
+            running: Cell::new(false),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn AdaptiveTimerClient<A::Ticks>) {
+        self.client.set(client);
+    }
+
+    /// Call the client `interval` ticks from now. Restarts the timer if it
+    /// is already running. An `interval` of 0 stops the timer.
+    pub fn start(&self, interval: A::Ticks) -> Result<(), ErrorCode> {
+        if interval == A::Ticks::from(0) {
+            return self.stop();
+        }
+        self.schedule(self.alarm.now(), interval);
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<(), ErrorCode> {
+        self.running.set(false);
+        self.alarm.disarm()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.get()
+    }
+
+    /// Interval until the next callback, if the timer is running.
+    pub fn interval(&self) -> Option<A::Ticks> {
+        self.running.get().then(|| self.interval.get())
+    }
+
+    fn schedule(&self, reference: A::Ticks, interval: A::Ticks) {
+        self.reference.set(reference);
+        self.interval.set(interval);
+        self.running.set(true);
+        self.alarm.set_alarm(reference, interval);
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for AdaptiveTimer<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for AdaptiveTimer<'a, A> {
+    fn alarm(&self) {
+        if !self.running.get() {
+            return;
+        }
+        let deadline = self.reference.get().wrapping_add(self.interval.get());
+        self.running.set(false);
+        let next = self
+            .client
+            .map_or(A::Ticks::from(0), |client| client.timer());
+        // The client may have restarted or stopped the timer itself.
+        if self.running.get() {
+            return;
+        }
+        if next != A::Ticks::from(0) {
+            self.schedule(deadline, next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    /// Returns intervals of 10, 20, 30, ... and stops after `limit`
+    /// callbacks, recording when each callback happened.
+    struct Increasing<'a> {
+        alarm: &'a MockAlarm<'a, Ticks32>,
+        fired_at: Cell<[u32; 4]>,
+        count: Cell<usize>,
+        limit: usize,
+    }
+
+    impl AdaptiveTimerClient<Ticks32> for Increasing<'_> {
+        fn timer(&self) -> Ticks32 {
+            let count = self.count.get();
+            let mut fired_at = self.fired_at.get();
+            fired_at[count] = self.alarm.now().into_u32();
+            self.fired_at.set(fired_at);
+            self.count.set(count + 1);
+            if count + 1 == self.limit {
+                0u32.into()
+            } else {
+                (10 * (count as u32 + 2)).into()
+            }
+        }
+    }
+
+    #[test]
+    fn test_increasing_intervals() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let timer = AdaptiveTimer::new(&alarm);
+        let client = Increasing {
+            alarm: &alarm,
+            fired_at: Cell::new([0; 4]),
+            count: Cell::new(0),
+            limit: 4,
+        };
+        alarm.set_alarm_client(&timer);
+        timer.set_client(&client);
+
+        assert_eq!(timer.start(10u32.into()), Ok(()));
+        assert_eq!(alarm.run_for(1000), 4);
+        assert_eq!(client.fired_at.get(), [10, 30, 60, 100]);
+        assert!(!timer.is_running());
+        assert!(!alarm.is_armed());
+    }
+
+    #[test]
+    fn test_no_drift() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let timer = AdaptiveTimer::new(&alarm);
+        let client = Increasing {
+            alarm: &alarm,
+            fired_at: Cell::new([0; 4]),
+            count: Cell::new(0),
+            limit: 4,
+        };
+        alarm.set_alarm_client(&timer);
+        timer.set_client(&client);
+
+        timer.start(10u32.into()).unwrap();
+        alarm.fire_late(5);
+        // The next deadline is 20 ticks after the first deadline, not
+        // after the late callback.
+        assert_eq!(alarm.get_alarm(), Ticks32::from(30u32));
+        assert_eq!(timer.interval(), Some(Ticks32::from(20u32)));
+    }
+}
@@ -0,0 +1,289 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! `Timer` implemented directly on top of a single `Alarm`.
+//!
+//! When only one timer is needed, `MuxTimer` and `VirtualTimer` are more
+//! machinery than necessary. An `AlarmToTimer` provides the `Timer`
+//! interface on an alarm it has exclusive use of, and must be installed
+//! as the client of that alarm.
+//!
+//! Intervals shorter than the alarm's `minimum_dt` are increased to
+//! `minimum_dt`. As the `Timer` contract requires, `oneshot` and
+//! `repeating` return the interval actually scheduled, which is never
+//! smaller than the one requested.
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time, Timer, TimerClient};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Disabled,
+    OneShot,
+    Repeating,
+}
+
+/// `Timer` using `alarm` exclusively.
+pub struct AlarmToTimer<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Start of the current interval.
+    reference: Cell<A::Ticks>,
+    /// Interval actually scheduled, after clamping to `minimum_dt`.
+    interval: Cell<A::Ticks>,
+    mode: Cell<Mode>,
+    /// Number of intervals which had elapsed at the last callback.
+    fire_count: Cell<u32>,
+    client: OptionalCell<&'a dyn TimerClient>,
+}
+
+impl<'a, A: Alarm<'a>> AlarmToTimer<'a, A> {
+    pub fn new(alarm: &'a A) -> AlarmToTimer<'a, A> {
+        AlarmToTimer {
+            alarm,
+            reference: Cell::new(A::Ticks::from(0)),
+            interval: Cell::new(A::Ticks::from(0)),
+            mode: Cell::new(Mode::Disabled),
+            fire_count: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    fn start_timer(&self, interval: A::Ticks, mode: Mode) -> A::Ticks {
+        // The alarm would clamp a smaller interval itself, but then the
+        // interval we report and re-arm with would be wrong.
+        let interval = cmp::max(interval, self.alarm.minimum_dt());
+        let now = self.alarm.now();
+        self.reference.set(now);
+        self.interval.set(interval);
+        self.mode.set(mode);
+        self.fire_count.set(0);
+        self.alarm.set_alarm(now, interval);
+        interval
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for AlarmToTimer<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Timer<'a> for AlarmToTimer<'a, A> {
+    fn set_timer_client(&self, client: &'a dyn TimerClient) {
+        self.client.set(client);
+    }
+
+    fn oneshot(&self, interval: Self::Ticks) -> Self::Ticks {
+        self.start_timer(interval, Mode::OneShot)
+    }
+
+    fn repeating(&self, interval: Self::Ticks) -> Self::Ticks {
+        self.start_timer(interval, Mode::Repeating)
+    }
+
+    fn interval(&self) -> Option<Self::Ticks> {
+        match self.mode.get() {
+            Mode::Disabled => None,
+            Mode::OneShot | Mode::Repeating => Some(self.interval.get()),
+        }
+    }
+
+    fn is_oneshot(&self) -> bool {
+        self.mode.get() == Mode::OneShot
+    }
+
+    fn is_repeating(&self) -> bool {
+        self.mode.get() == Mode::Repeating
+    }
+
+    fn time_remaining(&self) -> Option<Self::Ticks> {
+        match self.mode.get() {
+            Mode::Disabled => None,
+            Mode::OneShot | Mode::Repeating => {
+                let reference = self.reference.get();
+                let deadline = reference.wrapping_add(self.interval.get());
+                let now = self.alarm.now();
+                Some(if now.within_range(reference, deadline) {
+                    deadline.wrapping_sub(now)
+                } else {
+                    A::Ticks::from(0)
+                })
+            }
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.mode.get() != Mode::Disabled
+    }
+
+    fn cancel(&self) -> Result<(), ErrorCode> {
+        self.mode.set(Mode::Disabled);
+        self.alarm.disarm()
+    }
+
+    fn fire_count(&self) -> u32 {
+        self.fire_count.get()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for AlarmToTimer<'a, A> {
+    fn alarm(&self) {
+        match self.mode.get() {
+            Mode::Disabled => {}
+            Mode::OneShot => {
+                self.mode.set(Mode::Disabled);
+                self.fire_count.set(1);
+                self.client.map(|client| client.timer());
+            }
+            Mode::Repeating => {
+                // Re-arm from the previous deadline so that the timer does
+                // not drift, skipping any intervals which were missed.
+                let interval = self.interval.get();
+                let deadline = self.reference.get().wrapping_add(interval);
+                let late = self.alarm.now().wrapping_sub(deadline).into_u64();
+                let missed = match interval.into_u64() {
+                    0 => 0,
+                    interval => late / interval,
+                };
+                self.fire_count
+                    .set(u32::try_from(missed + 1).unwrap_or(u32::MAX));
+                let reference =
+                    deadline.wrapping_add(A::Ticks::from_or_max(missed * interval.into_u64()));
+                self.reference.set(reference);
+                self.alarm.set_alarm(reference, interval);
+                self.client.map(|client| client.timer());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::{CancelStatus, Ticks32};
+
+    struct TimerCounter(Cell<usize>);
+
+    impl TimerClient for TimerCounter {
+        fn timer(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_oneshot_clamped_to_minimum_dt() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        alarm.set_minimum_dt(50);
+        let timer = AlarmToTimer::new(&alarm);
+        alarm.set_alarm_client(&timer);
+
+        assert_eq!(timer.oneshot(10u32.into()), Ticks32::from(50u32));
+        assert_eq!(timer.interval(), Some(Ticks32::from(50u32)));
+        assert_eq!(alarm.get_alarm(), Ticks32::from(50u32));
+        assert_eq!(timer.time_remaining(), Some(Ticks32::from(50u32)));
+
+        assert_eq!(timer.repeating(20u32.into()), Ticks32::from(50u32));
+
+        // Intervals which are long enough are returned unchanged.
+        assert_eq!(timer.oneshot(80u32.into()), Ticks32::from(80u32));
+    }
+
+    #[test]
+    fn test_oneshot() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let timer = AlarmToTimer::new(&alarm);
+        let client = TimerCounter(Cell::new(0));
+        alarm.set_alarm_client(&timer);
+        timer.set_timer_client(&client);
+
+        timer.oneshot(100u32.into());
+        assert!(timer.is_oneshot());
+        assert_eq!(alarm.run_for(1000), 1);
+        assert_eq!(client.0.get(), 1);
+        assert_eq!(timer.fire_count(), 1);
+        assert!(!timer.is_enabled());
+        assert_eq!(timer.time_remaining(), None);
+    }
+
+    #[test]
+    fn test_repeating() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let timer = AlarmToTimer::new(&alarm);
+        let client = TimerCounter(Cell::new(0));
+        alarm.set_alarm_client(&timer);
+        timer.set_timer_client(&client);
+
+        timer.repeating(100u32.into());
+        assert_eq!(alarm.run_for(350), 3);
+        assert_eq!(timer.fire_count(), 1);
+        assert_eq!(timer.time_remaining(), Some(Ticks32::from(50u32)));
+
+        alarm.fire_late(250);
+        assert_eq!(client.0.get(), 4);
+        assert_eq!(timer.fire_count(), 3);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(700u32));
+
+        assert_eq!(timer.cancel(), Ok(()));
+        assert_eq!(alarm.run_for(1000), 0);
+        assert_eq!(client.0.get(), 4);
+    }
+
+    #[test]
+    fn test_next_deadline() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let timer = AlarmToTimer::new(&alarm);
+        alarm.set_alarm_client(&timer);
+        assert_eq!(timer.next_deadline(), None);
+
+        alarm.set_now(1000);
+        timer.repeating(100u32.into());
+        assert_eq!(timer.next_deadline(), Some(Ticks32::from(1100u32)));
+        alarm.run_for(130);
+        assert_eq!(timer.next_deadline(), Some(Ticks32::from(1200u32)));
+        assert_eq!(
+            timer.next_deadline(),
+            timer
+                .time_remaining()
+                .map(|remaining| timer.now().wrapping_add(remaining))
+        );
+
+        // Overdue callbacks are expected now.
+        alarm.advance(150);
+        assert_eq!(timer.next_deadline(), Some(timer.now()));
+    }
+
+    #[test]
+    fn test_cancel_and_status() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let timer = AlarmToTimer::new(&alarm);
+        alarm.set_alarm_client(&timer);
+        assert_eq!(timer.cancel_and_status(), Ok(CancelStatus::NotRunning));
+
+        // Cancelled before the interval elapsed.
+        timer.oneshot(100u32.into());
+        alarm.advance(50);
+        assert_eq!(timer.cancel_and_status(), Ok(CancelStatus::Cancelled));
+        assert!(!alarm.is_armed());
+        assert_eq!(timer.cancel_and_status(), Ok(CancelStatus::NotRunning));
+
+        // Cancelled after the callback was delivered.
+        timer.oneshot(100u32.into());
+        alarm.fire();
+        assert_eq!(timer.cancel_and_status(), Ok(CancelStatus::Expired));
+
+        // Cancelled when due, before the callback was delivered.
+        timer.repeating(100u32.into());
+        alarm.advance(120);
+        assert_eq!(timer.cancel_and_status(), Ok(CancelStatus::Expired));
+        assert!(!alarm.fire());
+    }
+}
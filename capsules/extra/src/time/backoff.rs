@@ -0,0 +1,158 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Exponential backoff for retrying operations.
+//!
+//! A driver retrying a failed operation (e.g. a radio transmission or an
+//! I2C transfer which was NACKed) should wait longer after each failure.
+//! A `Backoff` waits `base` ticks after the first call to `arm`, then
+//! `2 * base`, `4 * base`, and so on, never more than `max`. Its
+//! `BackoffClient` is called when each wait ends, and typically retries
+//! the operation. Once the operation succeeds, `reset` makes the next
+//! wait `base` again.
+//!
+//! The `Backoff` must be installed as the client of the underlying alarm.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let backoff = static_init!(
+//!     Backoff<'static, VirtualMuxAlarm<'static, nrf52::rtc::Rtc>>,
+//!     Backoff::new(virtual_alarm, Ticks32::from(32), Ticks32::from(4096))
+//! );
+//! virtual_alarm.set_alarm_client(backoff);
+//! backoff.set_client(driver);
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks};
+use kernel::utilities::cells::OptionalCell;
+
+/// Callback handler for when a backoff wait ends.
+pub trait BackoffClient {
+    fn backoff_expired(&self);
+}
+
+/// Schedules exponentially growing waits on `alarm`.
+pub struct Backoff<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    base: A::Ticks,
+    max: A::Ticks,
+    /// Length of the wait started by the next call to `arm`.
+    next: Cell<A::Ticks>,
+    client: OptionalCell<&'a dyn BackoffClient>,
+}
+
+impl<'a, A: Alarm<'a>> Backoff<'a, A> {
+    /// `max` is the longest wait. A `base` larger than `max` is clamped to
+    /// it.
+    pub fn new(alarm: &'a A, base: A::Ticks, max: A::Ticks) -> Backoff<'a, A> {
+        let base = cmp::min(base, max);
+        Backoff {
+            alarm,
+            base,
+            max,
+            next: Cell::new(base),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn BackoffClient) {
+        self.client.set(client);
+    }
+
+    /// Starts the next wait, replacing any wait in progress, and doubles
+    /// the length of the one after it up to `max`.
+    pub fn arm(&self) {
+        let wait = self.next.get();
+        self.alarm.set_alarm(self.alarm.now(), wait);
+        let doubled = wait.checked_mul(2).unwrap_or(self.max);
+        self.next.set(cmp::min(doubled, self.max));
+    }
+
+    /// Makes the next wait `base` again. A wait in progress is not
+    /// affected.
+    pub fn reset(&self) {
+        self.next.set(self.base);
+    }
+
+    /// Length of the wait the next call to `arm` starts.
+    pub fn next_interval(&self) -> A::Ticks {
+        self.next.get()
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for Backoff<'a, A> {
+    fn alarm(&self) {
+        self.client.map(|client| client.backoff_expired());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::{Ticks16, Ticks32};
+
+    /// Retries, i.e. re-arms, from every callback.
+    struct Retrying<'a, A: Alarm<'a>> {
+        backoff: &'a Backoff<'a, A>,
+        fired_at: Cell<[u32; 6]>,
+        fired: Cell<usize>,
+    }
+
+    impl<'a, A: Alarm<'a>> BackoffClient for Retrying<'a, A> {
+        fn backoff_expired(&self) {
+            let mut fired_at = self.fired_at.get();
+            fired_at[self.fired.get()] = self.backoff.alarm.now().into_u32();
+            self.fired_at.set(fired_at);
+            self.fired.set(self.fired.get() + 1);
+            if self.fired.get() < fired_at.len() {
+                self.backoff.arm();
+            }
+        }
+    }
+
+    #[test]
+    fn test_doubles_and_caps() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let backoff = Backoff::new(&alarm, Ticks32::from(10), Ticks32::from(100));
+        alarm.set_alarm_client(&backoff);
+        let client = Retrying {
+            backoff: &backoff,
+            fired_at: Cell::new([0; 6]),
+            fired: Cell::new(0),
+        };
+        backoff.set_client(&client);
+
+        backoff.arm();
+        assert_eq!(alarm.run_for(1000), 6);
+        // Waits of 10, 20, 40, 80, 100 and 100 ticks.
+        assert_eq!(client.fired_at.get(), [10, 30, 70, 150, 250, 350]);
+        assert_eq!(backoff.next_interval(), Ticks32::from(100));
+
+        backoff.reset();
+        assert_eq!(backoff.next_interval(), Ticks32::from(10));
+        backoff.arm();
+        assert_eq!(alarm.get_alarm().into_u32(), 1010);
+        assert_eq!(backoff.next_interval(), Ticks32::from(20));
+    }
+
+    #[test]
+    fn test_doubling_saturates() {
+        let alarm = MockAlarm::<Ticks16>::new();
+        let backoff = Backoff::new(&alarm, Ticks16::from(0x9000u16), Ticks16::max_value());
+        backoff.arm();
+        assert_eq!(backoff.next_interval(), Ticks16::max_value());
+        backoff.arm();
+        assert_eq!(backoff.next_interval(), Ticks16::max_value());
+    }
+}
@@ -0,0 +1,226 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! 32-bit `Counter` made of two cascaded hardware timers.
+//!
+//! Some microcontrollers have only 16-bit timers, but can clock one timer
+//! from the overflow of another. Together they form a 32-bit counter. A
+//! `CascadedCounter` presents such a pair as a single `Counter` with
+//! `Ticks32`. The low `LO_BITS` bits are the value of the `Lo` counter
+//! and the remaining bits are the value of the `Hi` counter, which
+//! increments each time `Lo` wraps.
+//!
+//! The two halves cannot be read atomically. `now` reads the low half,
+//! then the high half, then the low half again. If the low half wrapped
+//! in between, the high half may or may not have been read before it
+//! incremented, so it is read again and paired with the second low
+//! reading.
+//!
+//! The `CascadedCounter` must be installed as the overflow client of the
+//! high counter by calling `setup`. Its own overflow client is called
+//! when the high counter wraps, which is when the combined value wraps
+//! if the two widths add up to 32 bits.
+
+use kernel::hil::time::{Counter, OverflowClient, Ticks, Ticks32, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// `Counter` whose low `LO_BITS` bits come from `lo` and whose high bits
+/// come from `hi`.
+pub struct CascadedCounter<'a, Lo: Counter<'a>, Hi: Counter<'a>, const LO_BITS: u32 = 16> {
+    lo: &'a Lo,
+    hi: &'a Hi,
+    client: OptionalCell<&'a dyn OverflowClient>,
+}
+
+impl<'a, Lo: Counter<'a>, Hi: Counter<'a>, const LO_BITS: u32>
+    CascadedCounter<'a, Lo, Hi, LO_BITS>
+{
+    pub fn new(lo: &'a Lo, hi: &'a Hi) -> CascadedCounter<'a, Lo, Hi, LO_BITS> {
+        CascadedCounter {
+            lo,
+            hi,
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Install this as the overflow client of the high counter.
+    pub fn setup(&'a self) {
+        self.hi.set_overflow_client(self);
+    }
+
+    fn combine(hi: u32, lo: u32) -> Ticks32 {
+        let lo_mask = match LO_BITS {
+            32.. => u32::MAX,
+            bits => (1 << bits) - 1,
+        };
+        Ticks32::from(hi.checked_shl(LO_BITS).unwrap_or(0) | (lo & lo_mask))
+    }
+}
+
+impl<'a, Lo: Counter<'a>, Hi: Counter<'a>, const LO_BITS: u32> Time
+    for CascadedCounter<'a, Lo, Hi, LO_BITS>
+{
+    type Frequency = Lo::Frequency;
+    type Ticks = Ticks32;
+
+    fn now(&self) -> Ticks32 {
+        let first = self.lo.now();
+        let hi = self.hi.now();
+        let lo = self.lo.now();
+        let hi = if lo < first {
+            // The low half wrapped, so `hi` may be from before or after
+            // it incremented. Reading it again gives the value matching
+            // `lo`.
+            self.hi.now()
+        } else {
+            hi
+        };
+        Self::combine(hi.into_u32(), lo.into_u32())
+    }
+}
+
+impl<'a, Lo: Counter<'a>, Hi: Counter<'a>, const LO_BITS: u32> Counter<'a>
+    for CascadedCounter<'a, Lo, Hi, LO_BITS>
+{
+    fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+        self.client.set(client);
+    }
+
+    fn clear_overflow_client(&self) {
+        self.client.clear();
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        self.hi.start()?;
+        self.lo.start()
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        self.lo.stop()?;
+        self.hi.stop()
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        self.lo.reset()?;
+        self.hi.reset()
+    }
+
+    fn is_running(&self) -> bool {
+        self.lo.is_running()
+    }
+}
+
+impl<'a, Lo: Counter<'a>, Hi: Counter<'a>, const LO_BITS: u32> OverflowClient
+    for CascadedCounter<'a, Lo, Hi, LO_BITS>
+{
+    fn overflow(&self) {
+        self.client.map(|client| client.overflow());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockCounter};
+    use core::cell::Cell;
+    use kernel::hil::time::{Freq1KHz, Ticks16};
+
+    /// Counter returning a fixed sequence of values, one per `now()`.
+    struct SequenceCounter<'a> {
+        values: &'a [u16],
+        reads: Cell<usize>,
+    }
+
+    impl Time for SequenceCounter<'_> {
+        type Frequency = Freq1KHz;
+        type Ticks = Ticks16;
+
+        fn now(&self) -> Ticks16 {
+            let read = self.reads.get();
+            self.reads.set(read + 1);
+            Ticks16::from(self.values[core::cmp::min(read, self.values.len() - 1)])
+        }
+    }
+
+    impl<'a> Counter<'a> for SequenceCounter<'_> {
+        fn set_overflow_client(&self, _client: &'a dyn OverflowClient) {}
+
+        fn clear_overflow_client(&self) {}
+
+        fn start(&self) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn stop(&self) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn reset(&self) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn is_running(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_composed_value() {
+        let lo = MockCounter::<Ticks16>::new();
+        let hi = MockCounter::<Ticks16>::new();
+        let counter = CascadedCounter::<_, _>::new(&lo, &hi);
+        lo.set_now(0x5678);
+        hi.set_now(0x1234);
+        assert_eq!(counter.now(), Ticks32::from(0x1234_5678u32));
+
+        // A different split point.
+        let counter = CascadedCounter::<_, _, 8>::new(&lo, &hi);
+        assert_eq!(counter.now(), Ticks32::from(0x0012_3478u32));
+    }
+
+    #[test]
+    fn test_reread_on_low_wrap() {
+        // The low half wraps between the first and second reads; the high
+        // half was read before it incremented.
+        let lo = SequenceCounter {
+            values: &[0xFFFE, 0x0001],
+            reads: Cell::new(0),
+        };
+        let hi = SequenceCounter {
+            values: &[0x0011, 0x0012],
+            reads: Cell::new(0),
+        };
+        let counter = CascadedCounter::<_, _>::new(&lo, &hi);
+        assert_eq!(counter.now(), Ticks32::from(0x0012_0001u32));
+        assert_eq!(hi.reads.get(), 2);
+
+        // Without a wrap the high half is only read once.
+        let lo = SequenceCounter {
+            values: &[0x1000, 0x1001],
+            reads: Cell::new(0),
+        };
+        let hi = SequenceCounter {
+            values: &[0x0011],
+            reads: Cell::new(0),
+        };
+        let counter = CascadedCounter::<_, _>::new(&lo, &hi);
+        assert_eq!(counter.now(), Ticks32::from(0x0011_1001u32));
+        assert_eq!(hi.reads.get(), 1);
+    }
+
+    #[test]
+    fn test_overflow_from_high_counter() {
+        let lo = MockCounter::<Ticks16>::new();
+        let hi = MockCounter::<Ticks16>::new();
+        let counter = CascadedCounter::<_, _>::new(&lo, &hi);
+        counter.setup();
+        let client = ClientCounter::new();
+        counter.set_overflow_client(&client);
+
+        hi.set_now(0xFFFF);
+        assert_eq!(hi.advance(1), 1);
+        assert_eq!(client.count(), 1);
+    }
+}
@@ -0,0 +1,176 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm supporting intervals longer than the underlying counter can
+//! represent.
+//!
+//! On narrow counters the range of an alarm can be very short (e.g., a
+//! 16-bit counter at 16MHz wraps every ~4ms), and requesting a longer
+//! alarm silently wraps and fires early. A `ChainedAlarm` accepts a `u64`
+//! interval and splits it into segments of at most half the counter
+//! range, re-arming the underlying alarm from the previous deadline after
+//! each intermediate wakeup. Its client is only called once the full
+//! interval has elapsed.
+//!
+//! The `ChainedAlarm` must be installed as the client of the underlying
+//! alarm.
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Alarm which chains wakeups of `alarm` to wait for intervals of any length.
+pub struct ChainedAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Ticks still to wait after the currently programmed segment.
+    remaining: Cell<u64>,
+    armed: Cell<bool>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> ChainedAlarm<'a, A> {
+    pub fn new(alarm: &'a A) -> ChainedAlarm<'a, A> {
+        ChainedAlarm {
+            alarm,
+            remaining: Cell::new(0),
+            armed: Cell::new(false),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    /// Call the client `dt` ticks after `reference`, where `dt` may span
+    /// many periods of the underlying counter. Replaces any pending alarm.
+    pub fn set_alarm(&self, reference: A::Ticks, dt: u64) {
+        self.armed.set(true);
+        self.remaining.set(dt);
+        self.set_next_segment(reference);
+    }
+
+    /// Cancel the alarm, including any intermediate wakeup.
+    pub fn disarm(&self) -> Result<(), ErrorCode> {
+        self.armed.set(false);
+        self.remaining.set(0);
+        self.alarm.disarm()
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.armed.get()
+    }
+
+    /// Program the underlying alarm for the next segment, starting at
+    /// `reference`.
+    fn set_next_segment(&self, reference: A::Ticks) {
+        let remaining = self.remaining.get();
+        // Segments are limited to half the counter range so that latency
+        // in handling an intermediate wakeup cannot push the next deadline
+        // out of range.
+        let segment = cmp::min(
+            A::Ticks::from_or_max(cmp::min(remaining, u32::MAX as u64)),
+            A::Ticks::half_max_value(),
+        );
+        self.remaining.set(remaining - segment.into_u32() as u64);
+        self.alarm.set_alarm(reference, segment);
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for ChainedAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for ChainedAlarm<'a, A> {
+    fn alarm(&self) {
+        if !self.armed.get() {
+            // Cancelled after the underlying alarm already fired.
+            return;
+        }
+        if self.remaining.get() == 0 {
+            self.armed.set(false);
+            self.client.map(|client| client.alarm());
+        } else {
+            // Continue from the previous deadline rather than from now so
+            // that latency does not accumulate across segments.
+            self.set_next_segment(self.alarm.get_alarm());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockAlarm};
+    use kernel::hil::time::Ticks16;
+
+    const PERIOD: u32 = 0x1_0000;
+
+    #[test]
+    fn test_three_periods() {
+        let alarm = MockAlarm::<Ticks16>::new();
+        let chained = ChainedAlarm::new(&alarm);
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&chained);
+        chained.set_alarm_client(&client);
+        alarm.set_now(1234);
+
+        chained.set_alarm(alarm.now(), 3 * PERIOD as u64);
+
+        // Intermediate wakeups are not forwarded.
+        assert_eq!(alarm.run_for(PERIOD), 2);
+        assert_eq!(alarm.run_for(PERIOD), 2);
+        assert_eq!(alarm.run_for(PERIOD - 1), 1);
+        assert_eq!(client.count(), 0);
+        assert!(chained.is_armed());
+
+        assert_eq!(alarm.run_for(1), 1);
+        assert_eq!(client.count(), 1);
+        assert_eq!(alarm.now(), Ticks16::from(1234u16));
+        assert!(!chained.is_armed());
+        assert!(!alarm.is_armed());
+
+        assert_eq!(alarm.run_for(PERIOD), 0);
+        assert_eq!(client.count(), 1);
+    }
+
+    #[test]
+    fn test_short_interval() {
+        let alarm = MockAlarm::<Ticks16>::new();
+        let chained = ChainedAlarm::new(&alarm);
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&chained);
+        chained.set_alarm_client(&client);
+
+        chained.set_alarm(alarm.now(), 100);
+        assert_eq!(alarm.get_alarm(), Ticks16::from(100u16));
+        alarm.run_for(100);
+        assert_eq!(client.count(), 1);
+    }
+
+    #[test]
+    fn test_cancel_mid_chain() {
+        let alarm = MockAlarm::<Ticks16>::new();
+        let chained = ChainedAlarm::new(&alarm);
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&chained);
+        chained.set_alarm_client(&client);
+
+        chained.set_alarm(alarm.now(), 3 * PERIOD as u64);
+        alarm.run_for(PERIOD);
+        assert_eq!(chained.disarm(), Ok(()));
+        assert!(!alarm.is_armed());
+
+        alarm.run_for(3 * PERIOD);
+        assert_eq!(client.count(), 0);
+    }
+}
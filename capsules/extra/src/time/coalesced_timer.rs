@@ -0,0 +1,392 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Timers whose expirations are aligned to a coarse grid, so that nearby
+//! ones share a single wakeup.
+//!
+//! A `CoalescedTimerMux` shares one alarm between several `CoalescedTimer`s.
+//! Every expiration is rounded up to the next multiple of the mux's
+//! `granule` (in absolute counter values), so timers which expire within
+//! the same slot of the grid expire at exactly the same time and the
+//! alarm fires once for all of them. This suits clients which tolerate
+//! up to `granule` ticks of extra delay in exchange for fewer wakeups.
+//!
+//! As the `Timer` contract requires, a timer never expires before the
+//! interval it was started with: `oneshot` returns the rounded interval,
+//! and `repeating` rounds the period up to a multiple of `granule` so
+//! that every expiration stays on the grid.
+//!
+//! `granule` should be a power of two, so that the grid stays aligned
+//! when the counter wraps. A `granule` of 0 disables the rounding.
+//!
+//! The `CoalescedTimerMux` must be installed as the client of the
+//! underlying alarm.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let mux = static_init!(
+//!     CoalescedTimerMux<'static, nrf52::rtc::Rtc>,
+//!     CoalescedTimerMux::new(rtc, Ticks32::from(1024))
+//! );
+//! rtc.set_alarm_client(mux);
+//! let timer = static_init!(
+//!     CoalescedTimer<'static, nrf52::rtc::Rtc>,
+//!     CoalescedTimer::new(mux)
+//! );
+//! timer.setup();
+//! ```
+
+use core::cell::Cell;
+
+use kernel::collections::list::{List, ListLink, ListNode};
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time, Timer, TimerClient};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Disabled,
+    OneShot,
+    Repeating,
+}
+
+/// One `Timer` on top of a `CoalescedTimerMux`.
+pub struct CoalescedTimer<'a, A: Alarm<'a>> {
+    mux: &'a CoalescedTimerMux<'a, A>,
+    /// When the timer was started, or its previous expiration.
+    reference: Cell<A::Ticks>,
+    /// Next expiration, on the grid.
+    deadline: Cell<A::Ticks>,
+    /// Interval returned when the timer was started.
+    interval: Cell<A::Ticks>,
+    mode: Cell<Mode>,
+    /// Number of intervals which had elapsed at the last callback.
+    fire_count: Cell<u32>,
+    next: ListLink<'a, CoalescedTimer<'a, A>>,
+    client: OptionalCell<&'a dyn TimerClient>,
+}
+
+impl<'a, A: Alarm<'a>> ListNode<'a, CoalescedTimer<'a, A>> for CoalescedTimer<'a, A> {
+    fn next(&self) -> &'a ListLink<CoalescedTimer<'a, A>> {
+        &self.next
+    }
+}
+
+impl<'a, A: Alarm<'a>> CoalescedTimer<'a, A> {
+    /// After calling new, always call setup()
+    pub fn new(mux: &'a CoalescedTimerMux<'a, A>) -> CoalescedTimer<'a, A> {
+        let zero = A::Ticks::from(0);
+        CoalescedTimer {
+            mux,
+            reference: Cell::new(zero),
+            deadline: Cell::new(zero),
+            interval: Cell::new(zero),
+            mode: Cell::new(Mode::Disabled),
+            fire_count: Cell::new(0),
+            next: ListLink::empty(),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Call this method immediately after new() to link this timer to the
+    /// `CoalescedTimerMux`.
+    pub fn setup(&'a self) {
+        self.mux.timers.push_head(self);
+    }
+
+    /// Ticks from `now` until the next expiration, or 0 if it has passed.
+    fn remaining(&self, now: A::Ticks) -> A::Ticks {
+        let deadline = self.deadline.get();
+        if now.within_range(self.reference.get(), deadline) {
+            deadline.wrapping_sub(now)
+        } else {
+            A::Ticks::from(0)
+        }
+    }
+
+    fn expire(&self) {
+        match self.mode.get() {
+            Mode::Disabled => {}
+            Mode::OneShot => {
+                self.mode.set(Mode::Disabled);
+                self.fire_count.set(1);
+                self.client.map(|client| client.timer());
+            }
+            Mode::Repeating => {
+                // The period is a multiple of the granule, so stepping
+                // from the previous deadline stays on the grid. Skip any
+                // periods which were missed.
+                let interval = self.interval.get();
+                let deadline = self.deadline.get();
+                let late = self.mux.alarm.now().wrapping_sub(deadline).into_u64();
+                let missed = match interval.into_u64() {
+                    0 => 0,
+                    interval => late / interval,
+                };
+                self.fire_count
+                    .set(u32::try_from(missed + 1).unwrap_or(u32::MAX));
+                let reference =
+                    deadline.wrapping_add(A::Ticks::from_or_max(missed * interval.into_u64()));
+                self.reference.set(reference);
+                self.deadline.set(reference.wrapping_add(interval));
+                self.client.map(|client| client.timer());
+            }
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for CoalescedTimer<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.mux.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Timer<'a> for CoalescedTimer<'a, A> {
+    fn set_timer_client(&self, client: &'a dyn TimerClient) {
+        self.client.set(client);
+    }
+
+    fn oneshot(&self, interval: Self::Ticks) -> Self::Ticks {
+        let now = self.mux.alarm.now();
+        let deadline = self.mux.align_up(now.wrapping_add(interval));
+        let interval = deadline.wrapping_sub(now);
+        self.mux.start(self, now, deadline, interval, Mode::OneShot);
+        interval
+    }
+
+    fn repeating(&self, interval: Self::Ticks) -> Self::Ticks {
+        let now = self.mux.alarm.now();
+        let interval = self.mux.align_up(interval);
+        // Start the first period at the current slot boundary so that
+        // every expiration lands on the grid, without it being early.
+        let deadline = self.mux.align_up(now).wrapping_add(interval);
+        self.mux
+            .start(self, now, deadline, interval, Mode::Repeating);
+        interval
+    }
+
+    fn interval(&self) -> Option<Self::Ticks> {
+        match self.mode.get() {
+            Mode::Disabled => None,
+            Mode::OneShot | Mode::Repeating => Some(self.interval.get()),
+        }
+    }
+
+    fn is_oneshot(&self) -> bool {
+        self.mode.get() == Mode::OneShot
+    }
+
+    fn is_repeating(&self) -> bool {
+        self.mode.get() == Mode::Repeating
+    }
+
+    fn time_remaining(&self) -> Option<Self::Ticks> {
+        match self.mode.get() {
+            Mode::Disabled => None,
+            Mode::OneShot | Mode::Repeating => Some(self.remaining(self.mux.alarm.now())),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.mode.get() != Mode::Disabled
+    }
+
+    fn cancel(&self) -> Result<(), ErrorCode> {
+        self.mode.set(Mode::Disabled);
+        self.mux.rearm();
+        Ok(())
+    }
+
+    fn fire_count(&self) -> u32 {
+        self.fire_count.get()
+    }
+}
+
+/// Shares `alarm` between `CoalescedTimer`s aligned to a grid of `granule`
+/// ticks.
+pub struct CoalescedTimerMux<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    granule: A::Ticks,
+    /// Whether timer callbacks are being delivered; the alarm is re-armed
+    /// once they all return.
+    firing: Cell<bool>,
+    timers: List<'a, CoalescedTimer<'a, A>>,
+}
+
+impl<'a, A: Alarm<'a>> CoalescedTimerMux<'a, A> {
+    pub const fn new(alarm: &'a A, granule: A::Ticks) -> CoalescedTimerMux<'a, A> {
+        CoalescedTimerMux {
+            alarm,
+            granule,
+            firing: Cell::new(false),
+            timers: List::new(),
+        }
+    }
+
+    /// Rounds `ticks` up to the next multiple of the granule, wrapping
+    /// around at the width of the type.
+    fn align_up(&self, ticks: A::Ticks) -> A::Ticks {
+        let granule = self.granule.into_u64();
+        match ticks.into_u64().checked_rem(granule) {
+            None | Some(0) => ticks,
+            Some(rem) => ticks.wrapping_add(A::Ticks::from_or_max(granule - rem)),
+        }
+    }
+
+    fn start(
+        &self,
+        timer: &CoalescedTimer<'a, A>,
+        now: A::Ticks,
+        deadline: A::Ticks,
+        interval: A::Ticks,
+        mode: Mode,
+    ) {
+        timer.reference.set(now);
+        timer.deadline.set(deadline);
+        timer.interval.set(interval);
+        timer.mode.set(mode);
+        timer.fire_count.set(0);
+        self.rearm();
+    }
+
+    /// Arms the alarm for the earliest expiration of any enabled timer.
+    fn rearm(&self) {
+        if self.firing.get() {
+            return;
+        }
+        let now = self.alarm.now();
+        let next = self
+            .timers
+            .iter()
+            .filter(|timer| timer.is_enabled())
+            .map(|timer| timer.remaining(now))
+            .min();
+        match next {
+            Some(dt) => self.alarm.set_alarm(now, dt),
+            None => {
+                let _ = self.alarm.disarm();
+            }
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for CoalescedTimerMux<'a, A> {
+    fn alarm(&self) {
+        self.firing.set(true);
+        self.timers
+            .iter()
+            .filter(|timer| {
+                timer.is_enabled()
+                    && !self
+                        .alarm
+                        .now()
+                        .within_range(timer.reference.get(), timer.deadline.get())
+            })
+            .for_each(|timer| timer.expire());
+        self.firing.set(false);
+        self.rearm();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    /// Records the time of its last callback.
+    struct Recorder<'a> {
+        alarm: &'a MockAlarm<'a>,
+        fired_at: Cell<Option<u32>>,
+    }
+
+    impl<'a> Recorder<'a> {
+        fn new(alarm: &'a MockAlarm<'a>) -> Self {
+            Recorder {
+                alarm,
+                fired_at: Cell::new(None),
+            }
+        }
+    }
+
+    impl TimerClient for Recorder<'_> {
+        fn timer(&self) {
+            self.fired_at.set(Some(self.alarm.now().into_u32()));
+        }
+    }
+
+    #[test]
+    fn test_timers_in_same_slot_fire_together() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        alarm.set_now(1001);
+        let mux = CoalescedTimerMux::new(&alarm, Ticks32::from(16));
+        alarm.set_alarm_client(&mux);
+        let timers = [
+            CoalescedTimer::new(&mux),
+            CoalescedTimer::new(&mux),
+            CoalescedTimer::new(&mux),
+        ];
+        let clients = [
+            Recorder::new(&alarm),
+            Recorder::new(&alarm),
+            Recorder::new(&alarm),
+        ];
+        for (timer, client) in timers.iter().zip(clients.iter()) {
+            timer.setup();
+            timer.set_timer_client(client);
+        }
+
+        // All of them expire in the slot ending at 1008.
+        for (timer, interval) in timers.iter().zip([2u32, 5, 7]) {
+            let actual = timer.oneshot(interval.into());
+            assert!(actual >= Ticks32::from(interval));
+            assert_eq!(actual, Ticks32::from(7u32));
+        }
+        assert_eq!(alarm.get_alarm().into_u32(), 1008);
+
+        assert_eq!(alarm.run_for(100), 1);
+        for client in clients.iter() {
+            assert_eq!(client.fired_at.get(), Some(1008));
+        }
+        assert!(!alarm.is_armed());
+        assert!(timers.iter().all(|timer| !timer.is_enabled()));
+    }
+
+    #[test]
+    fn test_repeating_stays_on_grid() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        alarm.set_now(5);
+        let mux = CoalescedTimerMux::new(&alarm, Ticks32::from(16));
+        alarm.set_alarm_client(&mux);
+        let periodic = CoalescedTimer::new(&mux);
+        let once = CoalescedTimer::new(&mux);
+        periodic.setup();
+        once.setup();
+        let periodic_client = Recorder::new(&alarm);
+        let once_client = Recorder::new(&alarm);
+        periodic.set_timer_client(&periodic_client);
+        once.set_timer_client(&once_client);
+
+        // Rounded up to 32 ticks; first expiry one period after the slot
+        // boundary at 16.
+        assert_eq!(periodic.repeating(20u32.into()), Ticks32::from(32u32));
+        assert_eq!(periodic.time_remaining(), Some(Ticks32::from(43u32)));
+        assert_eq!(once.oneshot(60u32.into()), Ticks32::from(75u32));
+
+        assert_eq!(alarm.run_for(43), 1);
+        assert_eq!(periodic_client.fired_at.get(), Some(48));
+        // The one-shot timer shares the periodic timer's second expiry.
+        assert_eq!(alarm.run_for(32), 1);
+        assert_eq!(periodic_client.fired_at.get(), Some(80));
+        assert_eq!(once_client.fired_at.get(), Some(80));
+
+        periodic.cancel().unwrap();
+        assert!(!alarm.is_armed());
+    }
+}
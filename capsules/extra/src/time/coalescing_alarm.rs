@@ -0,0 +1,308 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Share one hardware `Alarm` between several users, merging wakeups
+//! whose deadlines are close together.
+//!
+//! A `CoalescingAlarm` hands out a `CoalescedAlarm` to each user. When a
+//! user asks for a deadline that is slightly *earlier* than the wakeup
+//! already scheduled on the hardware alarm, and the scheduled wakeup is at
+//! most `window` ticks after it, the scheduled wakeup is kept and serves
+//! both users. The new user is then called up to `window` ticks late.
+//!
+//! A user is never called before its own deadline: a deadline that falls
+//! *after* the scheduled wakeup is not merged into it, but is handled by
+//! the next wakeup, and a deadline more than `window` ticks before the
+//! scheduled wakeup moves the hardware alarm earlier.
+//!
+//! The `CoalescingAlarm` must be installed as the client of the
+//! underlying alarm.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let shared = static_init!(
+//!     CoalescingAlarm<'static, nrf52::rtc::Rtc>,
+//!     CoalescingAlarm::new(rtc, Ticks32::from(10))
+//! );
+//! rtc.set_alarm_client(shared);
+//! let user = static_init!(
+//!     CoalescedAlarm<'static, nrf52::rtc::Rtc>,
+//!     CoalescedAlarm::new(shared)
+//! );
+//! user.setup();
+//! ```
+
+use core::cell::Cell;
+
+use kernel::collections::list::{List, ListLink, ListNode};
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// One user's alarm on top of a `CoalescingAlarm`.
+pub struct CoalescedAlarm<'a, A: Alarm<'a>> {
+    shared: &'a CoalescingAlarm<'a, A>,
+    reference: Cell<A::Ticks>,
+    dt: Cell<A::Ticks>,
+    armed: Cell<bool>,
+    next: ListLink<'a, CoalescedAlarm<'a, A>>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> ListNode<'a, CoalescedAlarm<'a, A>> for CoalescedAlarm<'a, A> {
+    fn next(&self) -> &'a ListLink<CoalescedAlarm<'a, A>> {
+        &self.next
+    }
+}
+
+impl<'a, A: Alarm<'a>> CoalescedAlarm<'a, A> {
+    /// After calling new, always call setup()
+    pub fn new(shared: &'a CoalescingAlarm<'a, A>) -> CoalescedAlarm<'a, A> {
+        let zero = A::Ticks::from(0);
+        CoalescedAlarm {
+            shared,
+            reference: Cell::new(zero),
+            dt: Cell::new(zero),
+            armed: Cell::new(false),
+            next: ListLink::empty(),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Call this method immediately after new() to link this alarm to the
+    /// `CoalescingAlarm`.
+    pub fn setup(&'a self) {
+        self.shared.users.push_head(self);
+    }
+
+    /// Ticks from `now` until this alarm's deadline, or 0 if it has passed.
+    fn remaining(&self, now: A::Ticks) -> A::Ticks {
+        let deadline = self.get_alarm();
+        if now.within_range(self.reference.get(), deadline) {
+            deadline.wrapping_sub(now)
+        } else {
+            A::Ticks::from(0)
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for CoalescedAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.shared.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for CoalescedAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        self.reference.set(reference);
+        self.dt.set(dt);
+        self.armed.set(true);
+        self.shared.schedule(self);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.reference.get().wrapping_add(self.dt.get())
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.armed.set(false);
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.armed.get()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.shared.alarm.minimum_dt()
+    }
+}
+
+/// Schedules the wakeups of a set of `CoalescedAlarm`s on `alarm`.
+pub struct CoalescingAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// How late a user may be called to share an already-scheduled wakeup.
+    window: Cell<A::Ticks>,
+    /// Whether user callbacks are being delivered; the hardware alarm is
+    /// re-armed once they all return.
+    firing: Cell<bool>,
+    users: List<'a, CoalescedAlarm<'a, A>>,
+}
+
+impl<'a, A: Alarm<'a>> CoalescingAlarm<'a, A> {
+    pub const fn new(alarm: &'a A, window: A::Ticks) -> CoalescingAlarm<'a, A> {
+        CoalescingAlarm {
+            alarm,
+            window: Cell::new(window),
+            firing: Cell::new(false),
+            users: List::new(),
+        }
+    }
+
+    /// Sets how many ticks late a user may be called so that it can share
+    /// an already-scheduled wakeup.
+    pub fn set_window(&self, window: A::Ticks) {
+        self.window.set(window);
+    }
+
+    pub fn window(&self) -> A::Ticks {
+        self.window.get()
+    }
+
+    /// Updates the hardware alarm after `user` has been given a new
+    /// deadline.
+    fn schedule(&self, user: &CoalescedAlarm<'a, A>) {
+        if self.firing.get() {
+            return;
+        }
+        let now = self.alarm.now();
+        let wanted = user.remaining(now);
+        if self.alarm.is_armed() {
+            let scheduled = self.alarm.get_alarm().wrapping_sub(now);
+            // The scheduled wakeup is either due, or earlier than this
+            // deadline, in which case this user is handled when it fires,
+            // or late by at most the window, in which case it is shared.
+            if scheduled > A::Ticks::half_max_value()
+                || scheduled < wanted
+                || scheduled.wrapping_sub(wanted) <= self.window.get()
+            {
+                return;
+            }
+        }
+        self.alarm.set_alarm(now, wanted);
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for CoalescingAlarm<'a, A> {
+    fn alarm(&self) {
+        self.firing.set(true);
+        self.users
+            .iter()
+            .filter(|user| {
+                user.armed.get()
+                    && !self
+                        .alarm
+                        .now()
+                        .within_range(user.reference.get(), user.get_alarm())
+            })
+            .for_each(|user| {
+                user.armed.set(false);
+                user.client.map(|client| client.alarm());
+            });
+        self.firing.set(false);
+
+        let now = self.alarm.now();
+        let next = self
+            .users
+            .iter()
+            .filter(|user| user.armed.get())
+            .map(|user| user.remaining(now))
+            .min();
+        match next {
+            Some(dt) => self.alarm.set_alarm(now, dt),
+            None => {
+                let _ = self.alarm.disarm();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    /// Records the time of each callback.
+    struct Recorder<'a> {
+        alarm: &'a MockAlarm<'a>,
+        fired_at: Cell<Option<u32>>,
+    }
+
+    impl<'a> Recorder<'a> {
+        fn new(alarm: &'a MockAlarm<'a>) -> Self {
+            Recorder {
+                alarm,
+                fired_at: Cell::new(None),
+            }
+        }
+    }
+
+    impl AlarmClient for Recorder<'_> {
+        fn alarm(&self) {
+            self.fired_at.set(Some(self.alarm.now().into_u32()));
+        }
+    }
+
+    #[test]
+    fn test_deadlines_within_window_share_wakeup() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        alarm.set_now(1000);
+        let shared = CoalescingAlarm::new(&alarm, Ticks32::from(10));
+        alarm.set_alarm_client(&shared);
+        let a = CoalescedAlarm::new(&shared);
+        let b = CoalescedAlarm::new(&shared);
+        a.setup();
+        b.setup();
+        let a_client = Recorder::new(&alarm);
+        let b_client = Recorder::new(&alarm);
+        a.set_alarm_client(&a_client);
+        b.set_alarm_client(&b_client);
+
+        a.set_alarm(Ticks32::from(1000), Ticks32::from(100));
+        // 8 ticks earlier than the scheduled wakeup: shared.
+        b.set_alarm(Ticks32::from(1000), Ticks32::from(92));
+        assert_eq!(alarm.get_alarm().into_u32(), 1100);
+
+        assert_eq!(alarm.run_for(200), 1);
+        assert_eq!(a_client.fired_at.get(), Some(1100));
+        assert_eq!(b_client.fired_at.get(), Some(1100));
+        assert!(!alarm.is_armed());
+    }
+
+    #[test]
+    fn test_no_early_fires() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        alarm.set_now(1000);
+        let shared = CoalescingAlarm::new(&alarm, Ticks32::from(10));
+        alarm.set_alarm_client(&shared);
+        let a = CoalescedAlarm::new(&shared);
+        let b = CoalescedAlarm::new(&shared);
+        let c = CoalescedAlarm::new(&shared);
+        a.setup();
+        b.setup();
+        c.setup();
+        let a_client = Recorder::new(&alarm);
+        let b_client = Recorder::new(&alarm);
+        let c_client = Recorder::new(&alarm);
+        a.set_alarm_client(&a_client);
+        b.set_alarm_client(&b_client);
+        c.set_alarm_client(&c_client);
+
+        a.set_alarm(Ticks32::from(1000), Ticks32::from(100));
+        // Slightly later than the scheduled wakeup: must not share it.
+        b.set_alarm(Ticks32::from(1000), Ticks32::from(105));
+        // Further before the scheduled wakeup than the window allows.
+        c.set_alarm(Ticks32::from(1000), Ticks32::from(50));
+        assert_eq!(alarm.get_alarm().into_u32(), 1050);
+
+        assert_eq!(alarm.run_for(200), 3);
+        assert_eq!(c_client.fired_at.get(), Some(1050));
+        assert_eq!(a_client.fired_at.get(), Some(1100));
+        assert_eq!(b_client.fired_at.get(), Some(1105));
+    }
+}
@@ -0,0 +1,148 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm which fires a fixed number of times at a regular interval.
+//!
+//! Sits between a one-shot alarm and an infinitely repeating timer, e.g.
+//! for retrying an operation three times. The `CountdownAlarm` must be
+//! installed as the client of the underlying alarm. Each wakeup is
+//! scheduled relative to the previous deadline, so latency in handling
+//! one callback does not delay the following ones.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Alarm which calls its client a given number of times.
+pub struct CountdownAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    interval: Cell<A::Ticks>,
+    /// Callbacks still to be delivered, including the pending one.
+    remaining: Cell<u32>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> CountdownAlarm<'a, A> {
+    pub fn new(alarm: &'a A) -> CountdownAlarm<'a, A> {
+        CountdownAlarm {
+            alarm,
+            interval: Cell::new(A::Ticks::from(0)),
+            remaining: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    /// Call the client `count` times, every `interval` ticks starting
+    /// from now. Replaces any countdown in progress. A `count` of 0
+    /// cancels the countdown.
+    pub fn start(&self, interval: A::Ticks, count: u32) {
+        self.interval.set(interval);
+        self.remaining.set(count);
+        if count == 0 {
+            let _ = self.alarm.disarm();
+        } else {
+            self.alarm.set_alarm(self.alarm.now(), interval);
+        }
+    }
+
+    /// Number of callbacks still to be delivered.
+    pub fn remaining(&self) -> u32 {
+        self.remaining.get()
+    }
+
+    /// Stop the countdown before all callbacks have been delivered.
+    pub fn cancel(&self) -> Result<(), ErrorCode> {
+        self.remaining.set(0);
+        self.alarm.disarm()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for CountdownAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for CountdownAlarm<'a, A> {
+    fn alarm(&self) {
+        let remaining = self.remaining.get();
+        if remaining == 0 {
+            // Cancelled after the underlying alarm already fired.
+            return;
+        }
+        self.remaining.set(remaining - 1);
+        // Re-arm before calling the client so that the client can cancel
+        // or restart the countdown from its callback.
+        if remaining > 1 {
+            self.alarm
+                .set_alarm(self.alarm.get_alarm(), self.interval.get());
+        }
+        self.client.map(|client| client.alarm());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockAlarm};
+    use kernel::hil::time::Ticks32;
+
+    #[test]
+    fn test_exactly_n_callbacks() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let countdown = CountdownAlarm::new(&alarm);
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&countdown);
+        countdown.set_alarm_client(&client);
+
+        countdown.start(100u32.into(), 3);
+        assert_eq!(countdown.remaining(), 3);
+
+        assert_eq!(alarm.run_for(150), 1);
+        assert_eq!(client.count(), 1);
+        assert_eq!(countdown.remaining(), 2);
+
+        assert_eq!(alarm.run_for(10_000), 2);
+        assert_eq!(client.count(), 3);
+        assert_eq!(countdown.remaining(), 0);
+        assert!(!alarm.is_armed());
+    }
+
+    #[test]
+    fn test_no_drift() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let countdown = CountdownAlarm::new(&alarm);
+        alarm.set_alarm_client(&countdown);
+
+        countdown.start(100u32.into(), 2);
+        alarm.fire_late(30);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(200u32));
+    }
+
+    #[test]
+    fn test_cancel_early() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let countdown = CountdownAlarm::new(&alarm);
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&countdown);
+        countdown.set_alarm_client(&client);
+
+        countdown.start(100u32.into(), 5);
+        alarm.run_for(100);
+        assert_eq!(countdown.cancel(), Ok(()));
+        assert_eq!(countdown.remaining(), 0);
+
+        alarm.run_for(10_000);
+        assert_eq!(client.count(), 1);
+    }
+}
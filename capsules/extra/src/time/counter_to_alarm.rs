@@ -0,0 +1,200 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Software `Alarm` for counters without compare hardware.
+//!
+//! Some free-running counters can only signal overflows. A
+//! `CounterToAlarm` provides an `Alarm` on top of such a `Counter` by
+//! registering as its `OverflowClient` and checking the deadline on every
+//! overflow.
+//!
+//! Because the deadline is only checked on overflow, the alarm fires at
+//! the first overflow after the deadline: its granularity is one full
+//! period of the counter, and callbacks are up to one period late. The
+//! counter must be started for the alarm to fire.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Counter, OverflowClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// `Alarm` implemented by polling the deadline on overflows of `counter`.
+pub struct CounterToAlarm<'a, C: Counter<'a>> {
+    counter: &'a C,
+    reference: Cell<C::Ticks>,
+    dt: Cell<C::Ticks>,
+    /// Counter value at the last time the deadline was checked.
+    last_check: Cell<C::Ticks>,
+    /// Whether the deadline had already passed when the alarm was set.
+    expired: Cell<bool>,
+    armed: Cell<bool>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, C: Counter<'a>> CounterToAlarm<'a, C> {
+    pub fn new(counter: &'a C) -> CounterToAlarm<'a, C> {
+        let zero = C::Ticks::from(0);
+        CounterToAlarm {
+            counter,
+            reference: Cell::new(zero),
+            dt: Cell::new(zero),
+            last_check: Cell::new(zero),
+            expired: Cell::new(false),
+            armed: Cell::new(false),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Register as the overflow client of the underlying counter.
+    pub fn setup(&'a self) {
+        self.counter.set_overflow_client(self);
+    }
+}
+
+impl<'a, C: Counter<'a>> Time for CounterToAlarm<'a, C> {
+    type Frequency = C::Frequency;
+    type Ticks = C::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.counter.now()
+    }
+}
+
+impl<'a, C: Counter<'a>> Alarm<'a> for CounterToAlarm<'a, C> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        let now = self.counter.now();
+        self.reference.set(reference);
+        self.dt.set(dt);
+        self.last_check.set(now);
+        self.expired
+            .set(!now.within_range(reference, reference.wrapping_add(dt)));
+        self.armed.set(true);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.reference.get().wrapping_add(self.dt.get())
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.armed.set(false);
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.armed.get()
+    }
+
+    /// Any `dt` is accepted, but callbacks are delivered at the next
+    /// overflow after the deadline.
+    fn minimum_dt(&self) -> Self::Ticks {
+        Self::Ticks::from(0)
+    }
+}
+
+impl<'a, C: Counter<'a>> OverflowClient for CounterToAlarm<'a, C> {
+    fn overflow(&self) {
+        if !self.armed.get() {
+            return;
+        }
+        // Since the last check, the counter went from `last_check` up to
+        // its maximum value and then from 0 to `now`; the deadline passed
+        // if it lies in either part.
+        let now = self.counter.now();
+        let deadline = self.get_alarm();
+        let passed = self.expired.get() || deadline >= self.last_check.get() || deadline <= now;
+        self.last_check.set(now);
+        if passed {
+            self.armed.set(false);
+            self.client.map(|client| client.alarm());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockCounter};
+    use kernel::hil::time::Ticks16;
+
+    const PERIOD: u64 = 0x1_0000;
+
+    #[test]
+    fn test_fires_on_overflow_after_deadline() {
+        let counter = MockCounter::<Ticks16>::new();
+        let alarm = CounterToAlarm::new(&counter);
+        alarm.setup();
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&client);
+        let _ = counter.start();
+
+        // The deadline wraps past 0, so the first overflow is too early.
+        counter.set_now(0xF000);
+        alarm.set_alarm(alarm.now(), 0x2000u32.into());
+        assert_eq!(counter.advance(0x1000), 1);
+        assert_eq!(client.count(), 0);
+        assert!(alarm.is_armed());
+
+        // The next overflow is after the deadline.
+        assert_eq!(counter.advance(PERIOD), 1);
+        assert_eq!(client.count(), 1);
+        assert!(!alarm.is_armed());
+
+        // Disarmed alarms do not fire again.
+        counter.advance(3 * PERIOD);
+        assert_eq!(client.count(), 1);
+    }
+
+    #[test]
+    fn test_fires_at_first_overflow() {
+        let counter = MockCounter::<Ticks16>::new();
+        let alarm = CounterToAlarm::new(&counter);
+        alarm.setup();
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&client);
+
+        counter.set_now(0x100);
+        alarm.set_alarm(alarm.now(), 0x100u32.into());
+        counter.advance(0x200);
+        assert_eq!(client.count(), 0);
+        counter.advance(PERIOD - 0x300);
+        assert_eq!(client.count(), 1);
+    }
+
+    #[test]
+    fn test_already_expired() {
+        let counter = MockCounter::<Ticks16>::new();
+        let alarm = CounterToAlarm::new(&counter);
+        alarm.setup();
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&client);
+
+        counter.set_now(0x8000);
+        alarm.set_alarm(0x1000u32.into(), 0x10u32.into());
+        counter.advance(PERIOD - 0x8000);
+        assert_eq!(client.count(), 1);
+    }
+
+    #[test]
+    fn test_disarm() {
+        let counter = MockCounter::<Ticks16>::new();
+        let alarm = CounterToAlarm::new(&counter);
+        alarm.setup();
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&client);
+
+        alarm.set_alarm(alarm.now(), 0x10u32.into());
+        assert_eq!(alarm.disarm(), Ok(()));
+        counter.advance(2 * PERIOD);
+        assert_eq!(client.count(), 0);
+    }
+}
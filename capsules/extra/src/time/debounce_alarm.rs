@@ -0,0 +1,202 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm which coalesces deadlines that are close together.
+//!
+//! When an alarm is repeatedly re-set to deadlines only a few ticks
+//! apart, reprogramming the hardware each time and waking up at
+//! near-identical times wastes power. A `DebounceAlarm` keeps the
+//! currently programmed deadline if a new deadline falls at most `window`
+//! ticks after it, so the callback may be delivered up to `window` ticks
+//! before the requested time. Deadlines earlier than the programmed one,
+//! or further than `window` after it, are programmed as usual.
+//!
+//! If callbacks must never be early, enable `set_never_early`: an early
+//! wakeup of the underlying alarm then re-arms it for the requested
+//! deadline instead of calling the client.
+//!
+//! The `DebounceAlarm` must be installed as the client of the underlying
+//! alarm.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Alarm that keeps its programmed deadline for requests within `window`
+/// ticks after it.
+pub struct DebounceAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    window: Cell<A::Ticks>,
+    never_early: Cell<bool>,
+    /// Most recently requested deadline, which may be later than the
+    /// deadline of the underlying alarm.
+    reference: Cell<A::Ticks>,
+    dt: Cell<A::Ticks>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> DebounceAlarm<'a, A> {
+    pub fn new(alarm: &'a A, window: A::Ticks) -> DebounceAlarm<'a, A> {
+        DebounceAlarm {
+            alarm,
+            window: Cell::new(window),
+            never_early: Cell::new(false),
+            reference: Cell::new(A::Ticks::from(0)),
+            dt: Cell::new(A::Ticks::from(0)),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Change the coalescing window. This only affects subsequent calls
+    /// to `set_alarm`.
+    pub fn set_window(&self, window: A::Ticks) {
+        self.window.set(window);
+    }
+
+    pub fn window(&self) -> A::Ticks {
+        self.window.get()
+    }
+
+    /// If `never_early` is true, the client is not called before the most
+    /// recently requested deadline, at the cost of an extra wakeup when a
+    /// deadline was coalesced.
+    pub fn set_never_early(&self, never_early: bool) {
+        self.never_early.set(never_early);
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for DebounceAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for DebounceAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        self.reference.set(reference);
+        self.dt.set(dt);
+        if self.alarm.is_armed() {
+            let requested = reference.wrapping_add(dt);
+            // A deadline before the programmed one wraps around to a large
+            // difference, so it is always programmed.
+            if requested.wrapping_sub(self.alarm.get_alarm()) <= self.window.get() {
+                return;
+            }
+        }
+        self.alarm.set_alarm(reference, dt);
+    }
+
+    /// Returns the most recently requested deadline, which may be up to
+    /// `window` ticks after the deadline the underlying alarm fires at.
+    fn get_alarm(&self) -> Self::Ticks {
+        self.reference.get().wrapping_add(self.dt.get())
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()
+    }
+
+    fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for DebounceAlarm<'a, A> {
+    fn alarm(&self) {
+        let reference = self.reference.get();
+        let dt = self.dt.get();
+        if self.never_early.get()
+            && self
+                .alarm
+                .now()
+                .within_range(reference, reference.wrapping_add(dt))
+        {
+            self.alarm.set_alarm(reference, dt);
+        } else {
+            self.client.map(|client| client.alarm());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockAlarm};
+    use kernel::hil::time::Ticks32;
+
+    #[test]
+    fn test_coalesces_within_window() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let debounce = DebounceAlarm::new(&alarm, 50u32.into());
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&debounce);
+        debounce.set_alarm_client(&client);
+
+        debounce.set_alarm(0u32.into(), 100u32.into());
+        debounce.set_alarm(0u32.into(), 110u32.into());
+        debounce.set_alarm(0u32.into(), 120u32.into());
+        assert_eq!(alarm.get_alarm(), Ticks32::from(100u32));
+        assert_eq!(debounce.get_alarm(), Ticks32::from(120u32));
+
+        assert_eq!(alarm.run_for(100), 1);
+        assert_eq!(client.count(), 1);
+        assert_eq!(alarm.run_for(1000), 0);
+    }
+
+    #[test]
+    fn test_reprograms_outside_window() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let debounce = DebounceAlarm::new(&alarm, 50u32.into());
+        alarm.set_alarm_client(&debounce);
+
+        debounce.set_alarm(0u32.into(), 100u32.into());
+        debounce.set_alarm(0u32.into(), 90u32.into());
+        assert_eq!(alarm.get_alarm(), Ticks32::from(90u32));
+        debounce.set_alarm(0u32.into(), 200u32.into());
+        assert_eq!(alarm.get_alarm(), Ticks32::from(200u32));
+
+        // Nothing is coalesced once the underlying alarm is disarmed.
+        assert_eq!(debounce.disarm(), Ok(()));
+        debounce.set_alarm(0u32.into(), 210u32.into());
+        assert_eq!(alarm.get_alarm(), Ticks32::from(210u32));
+    }
+
+    #[test]
+    fn test_never_early() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let debounce = DebounceAlarm::new(&alarm, 50u32.into());
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&debounce);
+        debounce.set_alarm_client(&client);
+        debounce.set_never_early(true);
+
+        debounce.set_alarm(0u32.into(), 100u32.into());
+        debounce.set_alarm(0u32.into(), 110u32.into());
+
+        assert_eq!(alarm.run_for(100), 1);
+        assert_eq!(client.count(), 0);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(110u32));
+
+        assert_eq!(alarm.run_for(10), 1);
+        assert_eq!(client.count(), 1);
+        assert!(!alarm.is_armed());
+    }
+}
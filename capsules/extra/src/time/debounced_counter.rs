@@ -0,0 +1,211 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Counter wrapper which confirms each reading with a second sample.
+//!
+//! A counter read across a noisy clock domain crossing can occasionally
+//! return a transient wrong value. A `DebouncedCounter` only returns a
+//! reading from `now()` once it is confirmed by the next sample: the
+//! second of two consecutive samples is returned if it is at most
+//! `tolerance` ticks ahead of the first, with wraparound. Otherwise the
+//! first sample is discarded and another one taken, up to `MAX_SAMPLES`
+//! samples in total. If no pair of samples agrees, the last sample is
+//! returned and counted as unconfirmed. `raw_now()` returns a single
+//! unchecked sample.
+//!
+//! Each call to `now()` reads the underlying counter at least twice, and
+//! up to `MAX_SAMPLES` times around a glitch, which adds latency to every
+//! reading. `tolerance` must cover the ticks which can elapse between two
+//! back-to-back reads, otherwise readings of a fast counter are never
+//! confirmed.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Counter, OverflowClient, Ticks, Time};
+use kernel::ErrorCode;
+
+/// Largest number of samples taken by one call to `now()`.
+pub const MAX_SAMPLES: usize = 4;
+
+/// `Counter` whose readings of `counter` are confirmed by two samples.
+pub struct DebouncedCounter<'a, C: Counter<'a>> {
+    counter: &'a C,
+    tolerance: C::Ticks,
+    unconfirmed: Cell<u32>,
+}
+
+impl<'a, C: Counter<'a>> DebouncedCounter<'a, C> {
+    pub fn new(counter: &'a C, tolerance: C::Ticks) -> DebouncedCounter<'a, C> {
+        DebouncedCounter {
+            counter,
+            tolerance,
+            unconfirmed: Cell::new(0),
+        }
+    }
+
+    /// A single reading of the underlying counter, without confirmation.
+    pub fn raw_now(&self) -> C::Ticks {
+        self.counter.now()
+    }
+
+    /// Number of calls to `now()` which returned a reading that no other
+    /// sample confirmed.
+    pub fn unconfirmed(&self) -> u32 {
+        self.unconfirmed.get()
+    }
+}
+
+impl<'a, C: Counter<'a>> Time for DebouncedCounter<'a, C> {
+    type Frequency = C::Frequency;
+    type Ticks = C::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        let mut previous = self.counter.now();
+        for _ in 1..MAX_SAMPLES {
+            let sample = self.counter.now();
+            if sample.wrapping_sub(previous) <= self.tolerance {
+                return sample;
+            }
+            previous = sample;
+        }
+        self.unconfirmed
+            .set(self.unconfirmed.get().saturating_add(1));
+        previous
+    }
+}
+
+impl<'a, C: Counter<'a>> Counter<'a> for DebouncedCounter<'a, C> {
+    fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+        self.counter.set_overflow_client(client);
+    }
+
+    fn clear_overflow_client(&self) {
+        self.counter.clear_overflow_client();
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        self.counter.start()
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        self.counter.stop()
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        self.counter.reset()
+    }
+
+    fn is_running(&self) -> bool {
+        self.counter.is_running()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockCounter;
+    use kernel::hil::time::{Freq1KHz, Ticks16};
+
+    /// Returns queued glitch values from `now()` before the value of the
+    /// underlying counter.
+    struct GlitchyCounter<'a> {
+        counter: MockCounter<'a, Ticks16>,
+        glitches: Cell<[Option<u16>; 3]>,
+        reads: Cell<usize>,
+    }
+
+    impl<'a> GlitchyCounter<'a> {
+        fn new() -> Self {
+            GlitchyCounter {
+                counter: MockCounter::new(),
+                glitches: Cell::new([None; 3]),
+                reads: Cell::new(0),
+            }
+        }
+    }
+
+    impl Time for GlitchyCounter<'_> {
+        type Frequency = Freq1KHz;
+        type Ticks = Ticks16;
+
+        fn now(&self) -> Ticks16 {
+            self.reads.set(self.reads.get() + 1);
+            let [first, second, third] = self.glitches.get();
+            self.glitches.set([second, third, None]);
+            first.map_or(self.counter.now(), Ticks16::from)
+        }
+    }
+
+    impl<'a> Counter<'a> for GlitchyCounter<'a> {
+        fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+            self.counter.set_overflow_client(client);
+        }
+
+        fn clear_overflow_client(&self) {
+            self.counter.clear_overflow_client();
+        }
+
+        fn start(&self) -> Result<(), ErrorCode> {
+            self.counter.start()
+        }
+
+        fn stop(&self) -> Result<(), ErrorCode> {
+            self.counter.stop()
+        }
+
+        fn reset(&self) -> Result<(), ErrorCode> {
+            self.counter.reset()
+        }
+
+        fn is_running(&self) -> bool {
+            self.counter.is_running()
+        }
+    }
+
+    #[test]
+    fn test_rejects_glitch() {
+        let counter = GlitchyCounter::new();
+        counter.counter.set_now(0x100);
+        let debounced = DebouncedCounter::new(&counter, Ticks16::from(2u16));
+
+        // A stable reading is confirmed with two samples.
+        assert_eq!(debounced.now(), Ticks16::from(0x100u16));
+        assert_eq!(counter.reads.get(), 2);
+
+        // A glitch in the first sample.
+        counter.glitches.set([Some(0x8100), None, None]);
+        assert_eq!(debounced.now(), Ticks16::from(0x100u16));
+
+        // A glitch in the second sample needs two more to confirm.
+        counter.reads.set(0);
+        counter.glitches.set([None, Some(0x0010), None]);
+        assert_eq!(debounced.now(), Ticks16::from(0x100u16));
+        assert_eq!(counter.reads.get(), 4);
+        assert_eq!(debounced.unconfirmed(), 0);
+
+        // The raw reading is not checked.
+        counter.glitches.set([Some(0x0010), None, None]);
+        assert_eq!(debounced.raw_now(), Ticks16::from(0x0010u16));
+    }
+
+    #[test]
+    fn test_unconfirmed() {
+        let counter = GlitchyCounter::new();
+        counter.counter.set_now(0x100);
+        let debounced = DebouncedCounter::new(&counter, Ticks16::from(2u16));
+
+        // No two consecutive samples agree, so the last one is returned.
+        counter
+            .glitches
+            .set([Some(0x200), Some(0x300), Some(0x400)]);
+        assert_eq!(debounced.now(), Ticks16::from(0x100u16));
+        assert_eq!(counter.reads.get(), MAX_SAMPLES);
+        assert_eq!(debounced.unconfirmed(), 1);
+
+        // A sample slightly ahead of the previous one confirms it.
+        counter.glitches.set([Some(0x0FF), None, None]);
+        assert_eq!(debounced.now(), Ticks16::from(0x100u16));
+        assert_eq!(debounced.unconfirmed(), 1);
+    }
+}
@@ -0,0 +1,133 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm wrapper which drops duplicate callbacks.
+//!
+//! Buggy hardware or alarm implementations can call `alarm()` more than
+//! once for a single deadline, e.g. when an interrupt flag is cleared too
+//! late. Few clients guard against this. A `DedupAlarm` forwards every
+//! call to the underlying alarm and records whether a callback is
+//! expected: `set_alarm` expects one, and delivering it or calling
+//! `disarm` clears the expectation. Any callback which is not expected
+//! is swallowed and counted.
+//!
+//! The `DedupAlarm` must be installed as the client of the underlying
+//! alarm.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Alarm that passes on at most one callback per `set_alarm` of `alarm`.
+pub struct DedupAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Whether a deadline was set and its callback not delivered yet.
+    expected: Cell<bool>,
+    duplicates: Cell<u32>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> DedupAlarm<'a, A> {
+    pub fn new(alarm: &'a A) -> DedupAlarm<'a, A> {
+        DedupAlarm {
+            alarm,
+            expected: Cell::new(false),
+            duplicates: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Number of callbacks which were swallowed because none was expected.
+    pub fn duplicates(&self) -> u32 {
+        self.duplicates.get()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for DedupAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for DedupAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        self.expected.set(true);
+        self.alarm.set_alarm(reference, dt);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.alarm.get_alarm()
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()?;
+        self.expected.set(false);
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for DedupAlarm<'a, A> {
+    fn alarm(&self) {
+        if self.expected.replace(false) {
+            self.client.map(|client| client.alarm());
+        } else {
+            self.duplicates.set(self.duplicates.get().saturating_add(1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockAlarm};
+    use kernel::hil::time::Ticks32;
+
+    #[test]
+    fn test_swallows_duplicates() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let dedup = DedupAlarm::new(&alarm);
+        alarm.set_alarm_client(&dedup);
+        let client = ClientCounter::new();
+        dedup.set_alarm_client(&client);
+
+        dedup.set_alarm(Ticks32::from(0), Ticks32::from(10));
+        assert!(alarm.fire());
+        // The hardware calls back a second time for the same deadline.
+        AlarmClient::alarm(&dedup);
+        assert_eq!(client.count(), 1);
+        assert_eq!(dedup.duplicates(), 1);
+
+        dedup.set_alarm(dedup.now(), Ticks32::from(10));
+        assert!(alarm.fire());
+        assert_eq!(client.count(), 2);
+
+        // Nor is a callback after disarming passed on.
+        dedup.set_alarm(dedup.now(), Ticks32::from(10));
+        dedup.disarm().unwrap();
+        AlarmClient::alarm(&dedup);
+        assert_eq!(client.count(), 2);
+        assert_eq!(dedup.duplicates(), 2);
+    }
+}
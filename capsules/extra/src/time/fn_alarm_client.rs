@@ -0,0 +1,62 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! `AlarmClient` which calls a closure.
+//!
+//! Trivial one-off callbacks in tests and glue code otherwise need their
+//! own struct and `AlarmClient` impl. A `FnAlarmClient` calls a closure
+//! instead. It holds a reference to the closure rather than the closure
+//! itself, so the closure can capture anything that outlives the client
+//! without allocation.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let fired = Cell::new(false);
+//! let callback = || fired.set(true);
+//! let client = FnAlarmClient::new(&callback);
+//! alarm.set_alarm_client(&client);
+//! ```
+
+use kernel::hil::time::AlarmClient;
+
+/// `AlarmClient` which calls `callback` on every alarm.
+pub struct FnAlarmClient<'a, F: Fn()> {
+    callback: &'a F,
+}
+
+impl<'a, F: Fn()> FnAlarmClient<'a, F> {
+    pub fn new(callback: &'a F) -> FnAlarmClient<'a, F> {
+        FnAlarmClient { callback }
+    }
+}
+
+impl<F: Fn()> AlarmClient for FnAlarmClient<'_, F> {
+    fn alarm(&self) {
+        (self.callback)();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use core::cell::Cell;
+    use kernel::hil::time::{Alarm, Ticks32};
+
+    #[test]
+    fn test_closure_called() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let fired = Cell::new(0);
+        let callback = || fired.set(fired.get() + 1);
+        let client = FnAlarmClient::new(&callback);
+        alarm.set_alarm_client(&client);
+
+        alarm.set_alarm(0u32.into(), 10u32.into());
+        assert_eq!(fired.get(), 0);
+        assert!(alarm.fire());
+        assert_eq!(fired.get(), 1);
+    }
+}
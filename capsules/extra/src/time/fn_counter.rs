@@ -0,0 +1,218 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! `Counter` backed by a function returning a monotonic time value.
+//!
+//! Simulators and hosted tests have no hardware counter. A `FnCounter`
+//! provides one from any monotonically increasing `u64` source, such as
+//! a closure reading an atomic or an emulator's cycle count. The value is
+//! truncated to a configurable width to simulate a narrower counter.
+//! Since there is no interrupt to signal overflows, the owner must call
+//! `poll` regularly; it calls the overflow client once for each time the
+//! value wrapped since the previous poll.
+//!
+//! While the counter is stopped the source is not sampled, and `now`
+//! returns the value at the time it was stopped.
+
+use core::cell::Cell;
+use core::marker::PhantomData;
+
+use kernel::hil::time::{Counter, Frequency, OverflowClient, Ticks64, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// `Counter` of `width` bits whose value is read from `source`.
+pub struct FnCounter<'a, F: Frequency, Src: Fn() -> u64> {
+    source: Src,
+    width: u32,
+    running: Cell<bool>,
+    /// Value of `source` when the counter was last reset.
+    offset: Cell<u64>,
+    /// Untruncated value at the last sample, relative to `offset`.
+    last: Cell<u64>,
+    client: OptionalCell<&'a dyn OverflowClient>,
+    _frequency: PhantomData<F>,
+}
+
+impl<'a, F: Frequency, Src: Fn() -> u64> FnCounter<'a, F, Src> {
+    /// Create a stopped counter which wraps every 2^`width` ticks of
+    /// `source`. `width` is clamped to 64.
+    pub fn new(source: Src, width: u32) -> FnCounter<'a, F, Src> {
+        FnCounter {
+            source,
+            width: core::cmp::min(width, 64),
+            running: Cell::new(false),
+            offset: Cell::new(0),
+            last: Cell::new(0),
+            client: OptionalCell::empty(),
+            _frequency: PhantomData,
+        }
+    }
+
+    /// Sample the source and call the overflow client once for each time
+    /// the counter wrapped since the previous sample. Does nothing while
+    /// the counter is stopped.
+    pub fn poll(&self) {
+        if !self.running.get() {
+            return;
+        }
+        let previous = self.last.get();
+        let current = self.sample();
+        let wraps = self.wraps(current).wrapping_sub(self.wraps(previous));
+        for _ in 0..wraps {
+            self.client.map(|client| client.overflow());
+        }
+    }
+
+    fn sample(&self) -> u64 {
+        let value = (self.source)().wrapping_sub(self.offset.get());
+        self.last.set(value);
+        value
+    }
+
+    fn wraps(&self, value: u64) -> u64 {
+        value.checked_shr(self.width).unwrap_or(0)
+    }
+
+    fn truncate(&self, value: u64) -> u64 {
+        match self.width {
+            64 => value,
+            width => value & ((1 << width) - 1),
+        }
+    }
+}
+
+impl<'a, F: Frequency, Src: Fn() -> u64> Time for FnCounter<'a, F, Src> {
+    type Frequency = F;
+    type Ticks = Ticks64;
+
+    fn now(&self) -> Ticks64 {
+        // Only `poll` may advance `last`, otherwise wraps would be missed.
+        let value = if self.running.get() {
+            (self.source)().wrapping_sub(self.offset.get())
+        } else {
+            self.last.get()
+        };
+        Ticks64::from(self.truncate(value))
+    }
+}
+
+impl<'a, F: Frequency, Src: Fn() -> u64> Counter<'a> for FnCounter<'a, F, Src> {
+    fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+        self.client.set(client);
+    }
+
+    fn clear_overflow_client(&self) {
+        self.client.clear();
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        if !self.running.get() {
+            // Carry on from the value the counter was stopped at.
+            let resumed = (self.source)().wrapping_sub(self.last.get());
+            self.offset.set(resumed);
+            self.running.set(true);
+        }
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        if self.running.get() {
+            self.poll();
+            self.running.set(false);
+        }
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        self.offset.set((self.source)());
+        self.last.set(0);
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.get()
+    }
+
+    fn overflow_period(&self) -> Ticks64 {
+        Ticks64::from(self.truncate(u64::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockCounter};
+    use kernel::hil::time::{Freq16MHz, Freq1KHz, Ticks, Ticks16};
+
+    #[test]
+    fn test_wrap() {
+        let time = Cell::new(0u64);
+        let counter = FnCounter::<Freq1KHz, _>::new(|| time.get(), 16);
+        let client = ClientCounter::new();
+        counter.set_overflow_client(&client);
+        assert_eq!(counter.start(), Ok(()));
+
+        time.set(0xFFF0);
+        counter.poll();
+        assert_eq!(counter.now(), Ticks64::from(0xFFF0u64));
+        assert_eq!(client.count(), 0);
+
+        time.set(0x1_0010);
+        assert_eq!(counter.now(), Ticks64::from(0x10u64));
+        counter.poll();
+        assert_eq!(client.count(), 1);
+
+        // Several wraps between polls are all reported.
+        time.set(0x4_0000);
+        counter.poll();
+        assert_eq!(client.count(), 4);
+        assert_eq!(counter.now(), Ticks64::from(0u64));
+    }
+
+    #[test]
+    fn test_frequency_through_dyn() {
+        let counter = FnCounter::<Freq1KHz, _>::new(|| 0, 16);
+        let counter: &dyn Counter<Frequency = Freq1KHz, Ticks = Ticks64> = &counter;
+        assert_eq!(counter.frequency_hz(), 1_000);
+    }
+
+    #[test]
+    fn test_overflow_period() {
+        // A 16-bit counter at 16MHz overflows every 4.096ms.
+        let counter = FnCounter::<Freq16MHz, _>::new(|| 0, 16);
+        assert_eq!(counter.overflow_period(), Ticks64::from(0xFFFFu64));
+        assert_eq!(counter.overflow_period_us(), 4_096);
+
+        let mock = MockCounter::<Ticks16, Freq16MHz>::new();
+        assert_eq!(mock.overflow_period(), Ticks16::max_value());
+        assert_eq!(mock.overflow_period_us(), 4_096);
+
+        let counter = FnCounter::<Freq1KHz, _>::new(|| 0, 64);
+        assert_eq!(counter.overflow_period_us(), u64::MAX);
+    }
+
+    #[test]
+    fn test_stop_start_reset() {
+        let time = Cell::new(100u64);
+        let counter = FnCounter::<Freq1KHz, _>::new(|| time.get(), 64);
+
+        // Not sampled until started.
+        assert_eq!(counter.now(), Ticks64::from(0u64));
+        counter.start().unwrap();
+        time.set(150);
+        assert_eq!(counter.now(), Ticks64::from(50u64));
+
+        counter.stop().unwrap();
+        time.set(1000);
+        assert_eq!(counter.now(), Ticks64::from(50u64));
+        counter.start().unwrap();
+        time.set(1010);
+        assert_eq!(counter.now(), Ticks64::from(60u64));
+
+        counter.reset().unwrap();
+        time.set(1015);
+        assert_eq!(counter.now(), Ticks64::from(5u64));
+    }
+}
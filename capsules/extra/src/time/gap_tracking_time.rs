@@ -0,0 +1,108 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Time wrapper which records the largest gap between readings.
+//!
+//! Code which extends a narrow time source in software, such as
+//! `SystemTick`, only works if `now()` is called at least once per wrap
+//! period. A `GapTrackingTime` forwards `now()` and keeps the largest
+//! forward distance seen between two successive readings, so diagnostics
+//! can check how close a system comes to that limit. Distances are
+//! computed with wrapping arithmetic, so a gap which crosses the wrap
+//! point of the underlying time source is measured correctly; a gap of a
+//! whole wrap period or more cannot be detected.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+
+/// `Time` which tracks the maximum gap between readings of `time`.
+pub struct GapTrackingTime<'a, T: Time> {
+    time: &'a T,
+    /// Last value returned by `now()`, if any since the last reset.
+    last: OptionalCell<T::Ticks>,
+    max_gap: Cell<T::Ticks>,
+}
+
+impl<'a, T: Time> GapTrackingTime<'a, T> {
+    pub fn new(time: &'a T) -> GapTrackingTime<'a, T> {
+        GapTrackingTime {
+            time,
+            last: OptionalCell::empty(),
+            max_gap: Cell::new(T::Ticks::from(0)),
+        }
+    }
+
+    /// Largest number of ticks between two successive calls to `now()`.
+    pub fn max_gap(&self) -> T::Ticks {
+        self.max_gap.get()
+    }
+
+    /// Clears the maximum. The gap from the previous reading to the next
+    /// one is not counted.
+    pub fn reset_max_gap(&self) {
+        self.last.clear();
+        self.max_gap.set(T::Ticks::from(0));
+    }
+}
+
+impl<'a, T: Time> Time for GapTrackingTime<'a, T> {
+    type Frequency = T::Frequency;
+    type Ticks = T::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        let now = self.time.now();
+        if let Some(last) = self.last.get() {
+            let gap = now.wrapping_sub(last);
+            if gap > self.max_gap.get() {
+                self.max_gap.set(gap);
+            }
+        }
+        self.last.set(now);
+        now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockCounter;
+    use kernel::hil::time::{Ticks16, Ticks32};
+
+    #[test]
+    fn test_records_max_gap() {
+        let counter = MockCounter::<Ticks32>::new();
+        let time = GapTrackingTime::new(&counter);
+        counter.set_now(1_000);
+        time.now();
+        // The first reading has nothing to compare with.
+        assert_eq!(time.max_gap().into_u32(), 0);
+
+        for gap in [10, 300, 20, 299, 5] {
+            counter.advance(gap);
+            time.now();
+        }
+        assert_eq!(time.max_gap().into_u32(), 300);
+
+        time.reset_max_gap();
+        counter.advance(1_000);
+        time.now();
+        assert_eq!(time.max_gap().into_u32(), 0);
+        counter.advance(7);
+        time.now();
+        assert_eq!(time.max_gap().into_u32(), 7);
+    }
+
+    #[test]
+    fn test_gap_across_wrap() {
+        let counter = MockCounter::<Ticks16>::new();
+        let time = GapTrackingTime::new(&counter);
+        counter.set_now(0xFF00);
+        time.now();
+        counter.advance(0x0180);
+        assert_eq!(time.now().into_u32(), 0x0080);
+        assert_eq!(time.max_gap().into_u32(), 0x0180);
+    }
+}
@@ -0,0 +1,181 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm which rounds deadlines up to a coarser grid of ticks.
+//!
+//! Batching wakeups onto a coarse grid (e.g., multiples of 1ms) lets
+//! independent alarms share a wakeup and the CPU sleep for longer. A
+//! `GridAlarm` rounds the deadline of every `set_alarm` call *up* to the
+//! next multiple of the grid size, so it may fire later than requested
+//! but never earlier.
+//!
+//! Grid boundaries are computed on the low 32 bits of the counter value,
+//! so for wider counters the grid is only aligned modulo 2^32.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let grid_alarm = static_init!(
+//!     GridAlarm<'static, VirtualMuxAlarm<'static, nrf52::rtc::Rtc>>,
+//!     GridAlarm::new(virtual_alarm, 32u32.into())
+//! );
+//! grid_alarm.set_alarm_client(client);
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::ErrorCode;
+
+/// Alarm that rounds deadlines up to multiples of `grid` ticks.
+pub struct GridAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Size of the grid in ticks. A grid of 0 disables rounding.
+    grid: Cell<A::Ticks>,
+}
+
+impl<'a, A: Alarm<'a>> GridAlarm<'a, A> {
+    pub fn new(alarm: &'a A, grid: A::Ticks) -> GridAlarm<'a, A> {
+        GridAlarm {
+            alarm,
+            grid: Cell::new(grid),
+        }
+    }
+
+    /// Change the grid size. This only affects subsequent calls to
+    /// `set_alarm`.
+    pub fn set_grid(&self, grid: A::Ticks) {
+        self.grid.set(grid);
+    }
+
+    pub fn grid(&self) -> A::Ticks {
+        self.grid.get()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for GridAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for GridAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.alarm.set_alarm_client(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.alarm.clear_alarm_client();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        // Clamp before rounding so that the inner alarm does not move the
+        // deadline off the grid by clamping it itself.
+        let dt = cmp::max(dt, self.alarm.minimum_dt());
+        let grid = self.grid.get().into_u32();
+        let dt = if grid == 0 {
+            dt
+        } else {
+            let remainder = reference.wrapping_add(dt).into_u32() % grid;
+            if remainder == 0 {
+                dt
+            } else {
+                let rounded = dt.wrapping_add(Self::Ticks::from(grid - remainder));
+                // If rounding up does not fit in the width of the counter,
+                // fire at the requested deadline instead.
+                if rounded < dt {
+                    dt
+                } else {
+                    rounded
+                }
+            }
+        };
+        self.alarm.set_alarm(reference, dt);
+    }
+
+    /// Returns the rounded deadline that the underlying alarm is set for.
+    fn get_alarm(&self) -> Self::Ticks {
+        self.alarm.get_alarm()
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()
+    }
+
+    fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockAlarm};
+    use kernel::hil::time::Ticks32;
+
+    #[test]
+    fn test_rounds_up_to_grid() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let client = ClientCounter::new();
+        let grid_alarm = GridAlarm::new(&alarm, 1000u32.into());
+        grid_alarm.set_alarm_client(&client);
+
+        grid_alarm.set_alarm(0u32.into(), 1500u32.into());
+        assert_eq!(grid_alarm.get_alarm(), Ticks32::from(2000u32));
+
+        // Nothing fires at the requested deadline...
+        assert_eq!(alarm.run_for(1500), 0);
+        assert_eq!(client.count(), 0);
+
+        // ...only at the grid boundary.
+        assert_eq!(alarm.run_for(500), 1);
+        assert_eq!(client.count(), 1);
+        assert_eq!(alarm.now(), Ticks32::from(2000u32));
+    }
+
+    #[test]
+    fn test_on_grid_unchanged() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let grid_alarm = GridAlarm::new(&alarm, 1000u32.into());
+
+        grid_alarm.set_alarm(500u32.into(), 1500u32.into());
+        assert_eq!(grid_alarm.get_alarm(), Ticks32::from(2000u32));
+
+        grid_alarm.set_grid(0u32.into());
+        grid_alarm.set_alarm(0u32.into(), 1500u32.into());
+        assert_eq!(grid_alarm.get_alarm(), Ticks32::from(1500u32));
+    }
+
+    #[test]
+    fn test_minimum_dt_clamped_before_rounding() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        alarm.set_minimum_dt(1200);
+        let grid_alarm = GridAlarm::new(&alarm, 1000u32.into());
+
+        grid_alarm.set_alarm(0u32.into(), 10u32.into());
+        assert_eq!(grid_alarm.get_alarm(), Ticks32::from(2000u32));
+        assert_eq!(
+            alarm.last_set_alarm(),
+            (Ticks32::from(0u32), Ticks32::from(2000u32))
+        );
+    }
+
+    #[test]
+    fn test_rounding_past_max_keeps_deadline() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let grid_alarm = GridAlarm::new(&alarm, 1000u32.into());
+
+        grid_alarm.set_alarm(0u32.into(), (u32::MAX - 10).into());
+        assert_eq!(grid_alarm.get_alarm(), Ticks32::from(u32::MAX - 10));
+    }
+}
@@ -0,0 +1,116 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm wrapper which records a histogram of requested intervals.
+//!
+//! To profile how a system uses its timers, a `HistogramAlarm` forwards
+//! every call to the underlying alarm unchanged and, on each
+//! `set_alarm`, counts the requested `dt` in a bucket by its magnitude.
+//! Bucket 0 counts a `dt` of 0, and bucket `i` counts a `dt` of `2^(i-1)`
+//! up to `2^i - 1` ticks, i.e. one with `i` significant bits. Intervals
+//! too large for the last bucket are counted in it. Counts saturate at
+//! `u32::MAX`.
+//!
+//! Callbacks go directly from the underlying alarm to the client.
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::ErrorCode;
+
+/// Alarm that counts the `dt` of each `set_alarm` of `alarm` in
+/// `BUCKETS` log2-sized buckets.
+pub struct HistogramAlarm<'a, A: Alarm<'a>, const BUCKETS: usize> {
+    alarm: &'a A,
+    histogram: Cell<[u32; BUCKETS]>,
+}
+
+impl<'a, A: Alarm<'a>, const BUCKETS: usize> HistogramAlarm<'a, A, BUCKETS> {
+    pub fn new(alarm: &'a A) -> HistogramAlarm<'a, A, BUCKETS> {
+        HistogramAlarm {
+            alarm,
+            histogram: Cell::new([0; BUCKETS]),
+        }
+    }
+
+    /// Number of intervals counted in each bucket.
+    pub fn histogram(&self) -> [u32; BUCKETS] {
+        self.histogram.get()
+    }
+
+    pub fn reset_histogram(&self) {
+        self.histogram.set([0; BUCKETS]);
+    }
+}
+
+impl<'a, A: Alarm<'a>, const BUCKETS: usize> Time for HistogramAlarm<'a, A, BUCKETS> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>, const BUCKETS: usize> Alarm<'a> for HistogramAlarm<'a, A, BUCKETS> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.alarm.set_alarm_client(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.alarm.clear_alarm_client();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        let mut histogram = self.histogram.get();
+        let bucket = cmp::min(dt.significant_bits() as usize, BUCKETS.saturating_sub(1));
+        if let Some(count) = histogram.get_mut(bucket) {
+            *count = count.saturating_add(1);
+        }
+        self.histogram.set(histogram);
+        self.alarm.set_alarm(reference, dt);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.alarm.get_alarm()
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()
+    }
+
+    fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    #[test]
+    fn test_buckets() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let histogram = HistogramAlarm::<_, 6>::new(&alarm);
+
+        for dt in [0u32, 1, 2, 3, 4, 7, 8, 16, 31, 32, 1_000_000] {
+            histogram.set_alarm(Ticks32::from(0), Ticks32::from(dt));
+        }
+        // Buckets: 0, 1, 2..=3, 4..=7, 8..=15, 16 and above.
+        assert_eq!(histogram.histogram(), [1, 1, 2, 2, 1, 4]);
+        // The call is forwarded unchanged.
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1_000_000));
+        assert_eq!(alarm.programmed(), 11);
+
+        histogram.reset_histogram();
+        assert_eq!(histogram.histogram(), [0; 6]);
+    }
+}
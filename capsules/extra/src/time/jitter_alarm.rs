@@ -0,0 +1,195 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm wrapper which delivers callbacks late by a random amount.
+//!
+//! Capsules are expected to tolerate alarm callbacks arriving after
+//! their deadline, but this is rarely tested. A `JitterAlarm` sits
+//! between an alarm and its client. When the underlying alarm fires, it
+//! waits a further pseudo-random number of ticks, up to a configurable
+//! bound, before calling the client. Callbacks are therefore never early,
+//! only late. The delays come from a seedable linear congruential
+//! generator, so a failing test can be reproduced with the same seed.
+//!
+//! This is a testing aid. The `JitterAlarm` must be installed as the
+//! client of the underlying alarm.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Alarm that delays the callbacks of `alarm` by up to `bound` ticks.
+pub struct JitterAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Largest delay added to a callback, in ticks.
+    bound: Cell<u32>,
+    /// State of the pseudo-random generator.
+    state: Cell<u32>,
+    /// Deadline requested by the client.
+    deadline: Cell<A::Ticks>,
+    /// Whether the underlying alarm is armed for the added delay rather
+    /// than for the requested deadline.
+    delaying: Cell<bool>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> JitterAlarm<'a, A> {
+    pub fn new(alarm: &'a A, bound: u32, seed: u32) -> JitterAlarm<'a, A> {
+        JitterAlarm {
+            alarm,
+            bound: Cell::new(bound),
+            state: Cell::new(seed),
+            deadline: Cell::new(A::Ticks::from(0)),
+            delaying: Cell::new(false),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_bound(&self, bound: u32) {
+        self.bound.set(bound);
+    }
+
+    pub fn set_seed(&self, seed: u32) {
+        self.state.set(seed);
+    }
+
+    /// Next delay, uniformly distributed in `0..=bound`.
+    fn next_delay(&self) -> u32 {
+        // Constants from Numerical Recipes.
+        let state = self
+            .state
+            .get()
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        self.state.set(state);
+        // The high bits of an LCG are the most random.
+        match self.bound.get().checked_add(1) {
+            Some(range) => (state >> 16) % range,
+            None => state,
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for JitterAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for JitterAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        self.delaying.set(false);
+        self.deadline.set(reference.wrapping_add(dt));
+        self.alarm.set_alarm(reference, dt);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.deadline.get()
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.delaying.set(false);
+        self.alarm.disarm()
+    }
+
+    fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for JitterAlarm<'a, A> {
+    fn alarm(&self) {
+        if !self.delaying.get() {
+            let delay = self.next_delay();
+            if delay > 0 {
+                self.delaying.set(true);
+                self.alarm
+                    .set_alarm(self.alarm.now(), A::Ticks::from(delay));
+                return;
+            }
+        }
+        self.delaying.set(false);
+        self.client.map(|client| client.alarm());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    /// Records how late the latest callback was.
+    struct LateBy<'a> {
+        alarm: &'a JitterAlarm<'a, MockAlarm<'a, Ticks32>>,
+        late: Cell<Option<u32>>,
+    }
+
+    impl AlarmClient for LateBy<'_> {
+        fn alarm(&self) {
+            let late = self.alarm.now().wrapping_sub(self.alarm.get_alarm());
+            self.late.set(Some(late.into_u32()));
+        }
+    }
+
+    #[test]
+    fn test_late_within_bound() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let jitter = JitterAlarm::new(&alarm, 20, 1);
+        let client = LateBy {
+            alarm: &jitter,
+            late: Cell::new(None),
+        };
+        alarm.set_alarm_client(&jitter);
+        jitter.set_alarm_client(&client);
+
+        let mut delayed = 0;
+        for _ in 0..100 {
+            client.late.set(None);
+            jitter.set_alarm(jitter.now(), 100u32.into());
+            assert_eq!(alarm.run_for(99), 0);
+            alarm.run_for(1 + 20);
+            // Never early, and never later than the bound.
+            let late = client.late.get().unwrap();
+            assert!(late <= 20);
+            if late > 0 {
+                delayed += 1;
+            }
+        }
+        assert!(delayed > 50);
+    }
+
+    #[test]
+    fn test_zero_bound() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let jitter = JitterAlarm::new(&alarm, 0, 1);
+        let client = LateBy {
+            alarm: &jitter,
+            late: Cell::new(None),
+        };
+        alarm.set_alarm_client(&jitter);
+        jitter.set_alarm_client(&client);
+
+        jitter.set_alarm(0u32.into(), 10u32.into());
+        assert!(alarm.fire());
+        assert_eq!(client.late.get(), Some(0));
+    }
+}
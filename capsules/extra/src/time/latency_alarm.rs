@@ -0,0 +1,145 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm wrapper which measures how late callbacks are delivered.
+//!
+//! An alarm callback always runs some time after its deadline, because of
+//! interrupt latency, deferred calls and other work. To tune a system it
+//! helps to know by how much. A `LatencyAlarm` forwards every call to
+//! the underlying alarm. On each callback it records `now() -
+//! get_alarm()` before calling its own client, and keeps the minimum,
+//! maximum and most recent latency in ticks.
+//!
+//! The `LatencyAlarm` must be installed as the client of the underlying
+//! alarm.
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Alarm that records the callback latency of `alarm`.
+pub struct LatencyAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    min: OptionalCell<A::Ticks>,
+    max: OptionalCell<A::Ticks>,
+    last: OptionalCell<A::Ticks>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> LatencyAlarm<'a, A> {
+    pub fn new(alarm: &'a A) -> LatencyAlarm<'a, A> {
+        LatencyAlarm {
+            alarm,
+            min: OptionalCell::empty(),
+            max: OptionalCell::empty(),
+            last: OptionalCell::empty(),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Smallest latency recorded, or `None` if there was no callback yet.
+    pub fn min_latency(&self) -> Option<A::Ticks> {
+        self.min.get()
+    }
+
+    /// Largest latency recorded, or `None` if there was no callback yet.
+    pub fn max_latency(&self) -> Option<A::Ticks> {
+        self.max.get()
+    }
+
+    /// Latency of the most recent callback, or `None` if there was no
+    /// callback yet.
+    pub fn last_latency(&self) -> Option<A::Ticks> {
+        self.last.get()
+    }
+
+    /// Forget all recorded latencies.
+    pub fn reset_latencies(&self) {
+        self.min.clear();
+        self.max.clear();
+        self.last.clear();
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for LatencyAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for LatencyAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        self.alarm.set_alarm(reference, dt);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.alarm.get_alarm()
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()
+    }
+
+    fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for LatencyAlarm<'a, A> {
+    fn alarm(&self) {
+        let latency = self.alarm.now().wrapping_sub(self.alarm.get_alarm());
+        self.last.set(latency);
+        if self.min.map_or(true, |min| latency < min) {
+            self.min.set(latency);
+        }
+        if self.max.map_or(true, |max| latency > max) {
+            self.max.set(latency);
+        }
+        self.client.map(|client| client.alarm());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockAlarm};
+    use kernel::hil::time::Ticks32;
+
+    #[test]
+    fn test_records_latency() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let latency = LatencyAlarm::new(&alarm);
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&latency);
+        latency.set_alarm_client(&client);
+        assert_eq!(latency.last_latency(), None);
+
+        for delay in [7, 2, 30, 12] {
+            latency.set_alarm(latency.now(), 100u32.into());
+            alarm.fire_late(delay);
+            assert_eq!(latency.last_latency(), Some(Ticks32::from(delay)));
+        }
+        assert_eq!(client.count(), 4);
+        assert_eq!(latency.min_latency(), Some(Ticks32::from(2u32)));
+        assert_eq!(latency.max_latency(), Some(Ticks32::from(30u32)));
+
+        latency.reset_latencies();
+        assert_eq!(latency.max_latency(), None);
+    }
+}
@@ -0,0 +1,264 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Deterministic implementations of the time HIL traits for unit tests.
+//!
+//! Unlike real hardware, time only advances when a test explicitly moves
+//! it forward.
+
+use core::cell::Cell;
+use core::marker::PhantomData;
+
+use kernel::hil::time::{
+    Alarm, AlarmClient, Counter, Freq1KHz, Frequency, OverflowClient, Ticks, Ticks32, Time,
+};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// An `Alarm` whose notion of `now` is controlled by the test.
+pub struct MockAlarm<'a, T: Ticks = Ticks32, F: Frequency = Freq1KHz> {
+    now: Cell<T>,
+    reference: Cell<T>,
+    dt: Cell<T>,
+    armed: Cell<bool>,
+    minimum_dt: Cell<T>,
+    /// Number of calls to `set_alarm`.
+    programmed: Cell<usize>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+    _frequency: PhantomData<F>,
+}
+
+impl<'a, T: Ticks, F: Frequency> MockAlarm<'a, T, F> {
+    pub fn new() -> Self {
+        Self {
+            now: Cell::new(T::from(0)),
+            reference: Cell::new(T::from(0)),
+            dt: Cell::new(T::from(0)),
+            armed: Cell::new(false),
+            minimum_dt: Cell::new(T::from(0)),
+            programmed: Cell::new(0),
+            client: OptionalCell::empty(),
+            _frequency: PhantomData,
+        }
+    }
+
+    pub fn set_now(&self, now: u32) {
+        self.now.set(T::from(now));
+    }
+
+    pub fn advance(&self, ticks: u32) {
+        self.now.set(self.now.get().wrapping_add(T::from(ticks)));
+    }
+
+    pub fn set_minimum_dt(&self, minimum_dt: u32) {
+        self.minimum_dt.set(T::from(minimum_dt));
+    }
+
+    /// Returns how many times `set_alarm` has been called.
+    pub fn programmed(&self) -> usize {
+        self.programmed.get()
+    }
+
+    /// The `(reference, dt)` pair passed to the last `set_alarm` call.
+    pub fn last_set_alarm(&self) -> (T, T) {
+        (self.reference.get(), self.dt.get())
+    }
+
+    /// Moves `now` to the armed deadline (if it has not been reached yet)
+    /// and then fires the alarm. Returns whether the alarm was armed.
+    pub fn fire(&self) -> bool {
+        self.fire_late(0)
+    }
+
+    /// Like `fire`, but the callback is delivered `delay` ticks after the
+    /// deadline.
+    pub fn fire_late(&self, delay: u32) -> bool {
+        if !self.armed.get() {
+            return false;
+        }
+        let deadline = self.get_alarm();
+        if self.now.get().within_range(self.reference.get(), deadline) {
+            self.now.set(deadline);
+        }
+        self.advance(delay);
+        self.armed.set(false);
+        self.client.map(|client| client.alarm());
+        true
+    }
+
+    /// Advances `now` by `ticks`, firing the alarm every time its deadline
+    /// is reached along the way. Returns the number of times it fired.
+    pub fn run_for(&self, ticks: u32) -> usize {
+        let mut left = ticks;
+        let mut fired = 0;
+        while self.armed.get() {
+            let now = self.now.get();
+            let deadline = self.get_alarm();
+            let until_deadline = if now.within_range(self.reference.get(), deadline) {
+                deadline.wrapping_sub(now).into_u32()
+            } else {
+                0
+            };
+            if until_deadline > left {
+                break;
+            }
+            left -= until_deadline;
+            self.fire();
+            fired += 1;
+        }
+        self.advance(left);
+        fired
+    }
+}
+
+impl<T: Ticks, F: Frequency> Time for MockAlarm<'_, T, F> {
+    type Frequency = F;
+    type Ticks = T;
+
+    fn now(&self) -> T {
+        self.now.get()
+    }
+}
+
+impl<'a, T: Ticks, F: Frequency> Alarm<'a> for MockAlarm<'a, T, F> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: T, dt: T) {
+        self.reference.set(reference);
+        self.dt.set(core::cmp::max(dt, self.minimum_dt.get()));
+        self.armed.set(true);
+        self.programmed.set(self.programmed.get() + 1);
+    }
+
+    fn get_alarm(&self) -> T {
+        self.reference.get().wrapping_add(self.dt.get())
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.armed.set(false);
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.armed.get()
+    }
+
+    fn minimum_dt(&self) -> T {
+        self.minimum_dt.get()
+    }
+}
+
+/// A `Counter` whose value is controlled by the test. Overflow callbacks
+/// are delivered as `advance` moves the value past `T::max_value()`.
+pub struct MockCounter<'a, T: Ticks = Ticks32, F: Frequency = Freq1KHz> {
+    now: Cell<T>,
+    running: Cell<bool>,
+    client: OptionalCell<&'a dyn OverflowClient>,
+    _frequency: PhantomData<F>,
+}
+
+impl<'a, T: Ticks, F: Frequency> MockCounter<'a, T, F> {
+    pub fn new() -> Self {
+        Self {
+            now: Cell::new(T::from(0)),
+            running: Cell::new(false),
+            client: OptionalCell::empty(),
+            _frequency: PhantomData,
+        }
+    }
+
+    pub fn set_now(&self, now: u32) {
+        self.now.set(T::from(now));
+    }
+
+    /// Advances the counter by `ticks`, calling the overflow client each
+    /// time it wraps to 0. Returns the number of overflows.
+    pub fn advance(&self, ticks: u64) -> usize {
+        // In `u128` so that the period of a 64-bit counter fits.
+        let period = T::max_value().into_u64() as u128 + 1;
+        let mut left = ticks as u128;
+        let mut overflows = 0;
+        let mut until_overflow = period - self.now.get().into_u64() as u128;
+        while left >= until_overflow {
+            left -= until_overflow;
+            until_overflow = period;
+            self.now.set(T::from(0));
+            overflows += 1;
+            self.client.map(|client| client.overflow());
+        }
+        // `left` is now less than `period`.
+        self.now
+            .set(self.now.get().wrapping_add(T::from_or_max(left as u64)));
+        overflows
+    }
+}
+
+impl<T: Ticks, F: Frequency> Time for MockCounter<'_, T, F> {
+    type Frequency = F;
+    type Ticks = T;
+
+    fn now(&self) -> T {
+        self.now.get()
+    }
+}
+
+impl<'a, T: Ticks, F: Frequency> Counter<'a> for MockCounter<'a, T, F> {
+    fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+        self.client.set(client);
+    }
+
+    fn clear_overflow_client(&self) {
+        self.client.clear();
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        self.running.set(true);
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        self.running.set(false);
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        self.now.set(T::from(0));
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.get()
+    }
+}
+
+/// An `AlarmClient` and `OverflowClient` which counts its callbacks.
+pub struct ClientCounter(Cell<usize>);
+
+impl ClientCounter {
+    pub fn new() -> Self {
+        Self(Cell::new(0))
+    }
+
+    pub fn count(&self) -> usize {
+        self.0.get()
+    }
+}
+
+impl AlarmClient for ClientCounter {
+    fn alarm(&self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+impl OverflowClient for ClientCounter {
+    fn overflow(&self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
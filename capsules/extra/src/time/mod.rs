@@ -0,0 +1,52 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Adapters and utilities layered on top of the time HIL
+//! (`kernel::hil::time`), such as alarms with modified scheduling
+//! policies.
+
+pub mod adaptive_timer;
+pub mod alarm_to_timer;
+pub mod backoff;
+pub mod cascaded_counter;
+pub mod chained_alarm;
+pub mod coalesced_timer;
+pub mod coalescing_alarm;
+pub mod countdown_alarm;
+pub mod counter_to_alarm;
+pub mod debounce_alarm;
+pub mod debounced_counter;
+pub mod dedup_alarm;
+pub mod fn_alarm_client;
+pub mod fn_counter;
+pub mod gap_tracking_time;
+pub mod grid_alarm;
+pub mod histogram_alarm;
+pub mod jitter_alarm;
+pub mod latency_alarm;
+pub mod monotonic_checker;
+pub mod observable_counter;
+pub mod overflow_mux;
+pub mod phased_periodic;
+pub mod prescaled_counter;
+pub mod priority_mux_alarm;
+pub mod quantized_time;
+pub mod rate_limiter;
+pub mod reentrant_safe_alarm;
+pub mod refcounted_counter;
+pub mod repeating_with_skip;
+pub mod sim;
+pub mod single_shot_guard_alarm;
+pub mod skewed_time;
+pub mod sleep_aware_counter;
+pub mod system_tick;
+pub mod timer_wheel;
+pub mod timestamp_log;
+pub mod tracing;
+pub mod uptime;
+pub mod verifying_alarm;
+pub mod watchdog_alarm;
+
+#[cfg(test)]
+mod mock;
@@ -0,0 +1,161 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Counter wrapper which detects readings going backward.
+//!
+//! Some hardware bugs, such as a glitchy synchronizer between clock
+//! domains, make a counter occasionally read a value behind the previous
+//! one. This silently corrupts any deadline computed from it. A
+//! `MonotonicChecker` remembers the last value returned by `now()` and
+//! compares each new reading to it with `Ticks::cmp_wrapping`. A reading
+//! less than half the range of the tick type ahead is a normal advance,
+//! which may include one wrap of the counter. A reading behind the
+//! previous one is an anomaly.
+//!
+//! Anomalies are counted and, while `set_strict` is enabled (the
+//! default), also trigger a `debug_assert!`. The count is available in
+//! release builds too, e.g. to report through a process console command.
+//!
+//! Gaps of more than half the range between two readings are also
+//! reported as anomalies, so `now()` must be called often enough.
+
+use core::cell::Cell;
+use core::cmp::Ordering;
+
+use kernel::hil::time::{Counter, OverflowClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// `Counter` which checks that the readings of `counter` never go back.
+pub struct MonotonicChecker<'a, C: Counter<'a>> {
+    counter: &'a C,
+    /// Last value returned by `now()`, if any since the last reset.
+    last: OptionalCell<C::Ticks>,
+    anomalies: Cell<u32>,
+    strict: Cell<bool>,
+}
+
+impl<'a, C: Counter<'a>> MonotonicChecker<'a, C> {
+    pub fn new(counter: &'a C) -> MonotonicChecker<'a, C> {
+        MonotonicChecker {
+            counter,
+            last: OptionalCell::empty(),
+            anomalies: Cell::new(0),
+            strict: Cell::new(true),
+        }
+    }
+
+    /// Number of readings which were behind the previous one.
+    pub fn anomalies(&self) -> u32 {
+        self.anomalies.get()
+    }
+
+    /// Whether an anomaly also fails a `debug_assert!`. Enabled
+    /// initially; disable it to only count anomalies.
+    pub fn set_strict(&self, strict: bool) {
+        self.strict.set(strict);
+    }
+}
+
+impl<'a, C: Counter<'a>> Time for MonotonicChecker<'a, C> {
+    type Frequency = C::Frequency;
+    type Ticks = C::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        let now = self.counter.now();
+        let backward = self
+            .last
+            .map_or(false, |last| now.cmp_wrapping(last) == Ordering::Less);
+        if backward {
+            self.anomalies.set(self.anomalies.get().saturating_add(1));
+            debug_assert!(!self.strict.get(), "counter reading went backward");
+        }
+        self.last.set(now);
+        now
+    }
+}
+
+impl<'a, C: Counter<'a>> Counter<'a> for MonotonicChecker<'a, C> {
+    fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+        self.counter.set_overflow_client(client);
+    }
+
+    fn clear_overflow_client(&self) {
+        self.counter.clear_overflow_client();
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        self.counter.start()
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        self.counter.stop()
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        // Going back to 0 is expected here.
+        self.last.clear();
+        self.counter.reset()
+    }
+
+    fn is_running(&self) -> bool {
+        self.counter.is_running()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockCounter;
+    use kernel::hil::time::Ticks16;
+
+    #[test]
+    fn test_counts_backward_readings() {
+        let counter = MockCounter::<Ticks16>::new();
+        let checker = MonotonicChecker::new(&counter);
+        checker.set_strict(false);
+
+        counter.set_now(0x100);
+        checker.now();
+        counter.set_now(0x200);
+        checker.now();
+        assert_eq!(checker.anomalies(), 0);
+
+        // Glitch backward, then recover.
+        counter.set_now(0x1F0);
+        assert_eq!(checker.now(), Ticks16::from(0x1F0u16));
+        assert_eq!(checker.anomalies(), 1);
+        counter.set_now(0x210);
+        checker.now();
+        assert_eq!(checker.anomalies(), 1);
+
+        // A genuine wrap is not an anomaly.
+        for now in [0x7000, 0xE000, 0xFFF0, 0x10] {
+            counter.set_now(now);
+            checker.now();
+        }
+        assert_eq!(checker.anomalies(), 1);
+
+        // Neither is a reset.
+        counter.set_now(0x8000);
+        checker.now();
+        counter.set_now(0x4000);
+        checker.now();
+        assert_eq!(checker.anomalies(), 2);
+        assert_eq!(checker.reset(), Ok(()));
+        checker.now();
+        assert_eq!(checker.anomalies(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "went backward")]
+    fn test_strict() {
+        let counter = MockCounter::<Ticks16>::new();
+        let checker = MonotonicChecker::new(&counter);
+        counter.set_now(10);
+        checker.now();
+        counter.set_now(9);
+        checker.now();
+    }
+}
@@ -0,0 +1,147 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Counter wrapper which reports when the counter starts and stops.
+//!
+//! `Counter` only notifies its client of overflows, but a power manager
+//! deciding whether a clock can be gated needs to know when the counter
+//! actually starts or stops running. An `ObservableCounter` forwards
+//! every call to the underlying counter and calls its
+//! `CounterStateClient` after a `start` or `stop` which succeeded and
+//! changed whether the counter is running. Starting a counter which is
+//! already running, or stopping one which is already stopped, is not
+//! reported.
+//!
+//! Overflow callbacks go directly from the underlying counter to the
+//! client set with `set_overflow_client`.
+
+use kernel::hil::time::{Counter, OverflowClient, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Callback handler for when an `ObservableCounter` starts or stops
+/// running.
+pub trait CounterStateClient {
+    /// The counter was not running and has been started.
+    fn started(&self);
+
+    /// The counter was running and has been stopped.
+    fn stopped(&self);
+}
+
+/// `Counter` which reports the start and stop transitions of `counter`.
+pub struct ObservableCounter<'a, C: Counter<'a>> {
+    counter: &'a C,
+    state_client: OptionalCell<&'a dyn CounterStateClient>,
+}
+
+impl<'a, C: Counter<'a>> ObservableCounter<'a, C> {
+    pub fn new(counter: &'a C) -> ObservableCounter<'a, C> {
+        ObservableCounter {
+            counter,
+            state_client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_state_client(&self, client: &'a dyn CounterStateClient) {
+        self.state_client.set(client);
+    }
+}
+
+impl<'a, C: Counter<'a>> Time for ObservableCounter<'a, C> {
+    type Frequency = C::Frequency;
+    type Ticks = C::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.counter.now()
+    }
+}
+
+impl<'a, C: Counter<'a>> Counter<'a> for ObservableCounter<'a, C> {
+    fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+        self.counter.set_overflow_client(client);
+    }
+
+    fn clear_overflow_client(&self) {
+        self.counter.clear_overflow_client();
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        let was_running = self.counter.is_running();
+        self.counter.start()?;
+        if !was_running {
+            self.state_client.map(|client| client.started());
+        }
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        let was_running = self.counter.is_running();
+        self.counter.stop()?;
+        if was_running {
+            self.state_client.map(|client| client.stopped());
+        }
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        self.counter.reset()
+    }
+
+    fn is_running(&self) -> bool {
+        self.counter.is_running()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockCounter;
+    use core::cell::Cell;
+    use kernel::hil::time::Ticks32;
+
+    struct StateCounter {
+        started: Cell<usize>,
+        stopped: Cell<usize>,
+    }
+
+    impl CounterStateClient for StateCounter {
+        fn started(&self) {
+            self.started.set(self.started.get() + 1);
+        }
+
+        fn stopped(&self) {
+            self.stopped.set(self.stopped.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_reports_transitions_only() {
+        let counter = MockCounter::<Ticks32>::new();
+        let observable = ObservableCounter::new(&counter);
+        let client = StateCounter {
+            started: Cell::new(0),
+            stopped: Cell::new(0),
+        };
+        observable.set_state_client(&client);
+
+        // Stopping a stopped counter is not a transition.
+        observable.stop().unwrap();
+        assert_eq!(client.stopped.get(), 0);
+
+        observable.start().unwrap();
+        observable.start().unwrap();
+        assert_eq!(client.started.get(), 1);
+        observable.reset().unwrap();
+
+        observable.stop().unwrap();
+        observable.stop().unwrap();
+        assert_eq!(client.stopped.get(), 1);
+
+        observable.start().unwrap();
+        observable.stop().unwrap();
+        assert_eq!(client.started.get(), 2);
+        assert_eq!(client.stopped.get(), 2);
+    }
+}
@@ -0,0 +1,180 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Fan out counter overflow notifications to multiple clients.
+//!
+//! A `Counter` only supports a single `OverflowClient`. If several
+//! modules need to know about overflows (e.g., a 64-bit extended counter
+//! and an uptime accounting module), install an `OverflowClientMux` as
+//! the counter's overflow client and register each module with the mux.
+//!
+//! Clients may register or unregister themselves (or other clients) from
+//! within their `overflow` callback. A client registered during dispatch
+//! may or may not be called for the overflow currently being dispatched,
+//! depending on which slot it is assigned.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let overflow_mux = static_init!(
+//!     OverflowClientMux<'static, 4>,
+//!     OverflowClientMux::new()
+//! );
+//! counter.set_overflow_client(overflow_mux);
+//! overflow_mux.add_client(extended_counter)?;
+//! overflow_mux.add_client(uptime)?;
+//! ```
+
+use kernel::hil::time::OverflowClient;
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// `OverflowClient` which forwards overflows to up to `N` clients.
+pub struct OverflowClientMux<'a, const N: usize> {
+    clients: [OptionalCell<&'a dyn OverflowClient>; N],
+}
+
+impl<'a, const N: usize> OverflowClientMux<'a, N> {
+    pub fn new() -> OverflowClientMux<'a, N> {
+        OverflowClientMux {
+            clients: core::array::from_fn(|_| OptionalCell::empty()),
+        }
+    }
+
+    /// Register `client` to receive overflow callbacks. Registering a
+    /// client which is already registered has no effect. Returns
+    /// `Err(ErrorCode::NOMEM)` if all `N` slots are in use.
+    pub fn add_client(&self, client: &'a dyn OverflowClient) -> Result<(), ErrorCode> {
+        if self.contains(client) {
+            return Ok(());
+        }
+        self.clients
+            .iter()
+            .find(|slot| slot.is_none())
+            .map_or(Err(ErrorCode::NOMEM), |slot| {
+                slot.set(client);
+                Ok(())
+            })
+    }
+
+    /// Unregister `client`. Returns `Err(ErrorCode::INVAL)` if it was not
+    /// registered.
+    pub fn remove_client(&self, client: &dyn OverflowClient) -> Result<(), ErrorCode> {
+        self.clients
+            .iter()
+            .find(|slot| slot.get().map_or(false, |c| same_client(c, client)))
+            .map_or(Err(ErrorCode::INVAL), |slot| {
+                slot.clear();
+                Ok(())
+            })
+    }
+
+    fn contains(&self, client: &dyn OverflowClient) -> bool {
+        self.clients
+            .iter()
+            .any(|slot| slot.get().map_or(false, |c| same_client(c, client)))
+    }
+}
+
+/// Compare clients by address only, as vtable pointers for the same type
+/// are not guaranteed to be unique.
+fn same_client(a: &dyn OverflowClient, b: &dyn OverflowClient) -> bool {
+    core::ptr::eq(
+        a as *const dyn OverflowClient as *const u8,
+        b as *const dyn OverflowClient as *const u8,
+    )
+}
+
+impl<const N: usize> OverflowClient for OverflowClientMux<'_, N> {
+    fn overflow(&self) {
+        // Each slot is read just before its client is called, so clients
+        // changing the registrations during dispatch only affect slots which
+        // have not been visited yet.
+        for slot in self.clients.iter() {
+            slot.get().map(|client| client.overflow());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    struct OverflowCounter(Cell<usize>);
+
+    impl OverflowClient for OverflowCounter {
+        fn overflow(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_two_listeners() {
+        let mux = OverflowClientMux::<2>::new();
+        let a = OverflowCounter(Cell::new(0));
+        let b = OverflowCounter(Cell::new(0));
+        assert_eq!(mux.add_client(&a), Ok(()));
+        assert_eq!(mux.add_client(&b), Ok(()));
+        // Re-adding is a no-op rather than using another slot.
+        assert_eq!(mux.add_client(&a), Ok(()));
+
+        mux.overflow();
+        mux.overflow();
+        assert_eq!(a.0.get(), 2);
+        assert_eq!(b.0.get(), 2);
+
+        assert_eq!(mux.remove_client(&a), Ok(()));
+        assert_eq!(mux.remove_client(&a), Err(ErrorCode::INVAL));
+        mux.overflow();
+        assert_eq!(a.0.get(), 2);
+        assert_eq!(b.0.get(), 3);
+    }
+
+    #[test]
+    fn test_full() {
+        let mux = OverflowClientMux::<1>::new();
+        let a = OverflowCounter(Cell::new(0));
+        let b = OverflowCounter(Cell::new(0));
+        assert_eq!(mux.add_client(&a), Ok(()));
+        assert_eq!(mux.add_client(&b), Err(ErrorCode::NOMEM));
+    }
+
+    struct Unregister<'a> {
+        mux: &'a OverflowClientMux<'a, 2>,
+        other: &'a OverflowCounter,
+        count: Cell<usize>,
+    }
+
+    impl OverflowClient for Unregister<'_> {
+        fn overflow(&self) {
+            self.count.set(self.count.get() + 1);
+            let _ = self.mux.remove_client(self);
+            let _ = self.mux.add_client(self.other);
+        }
+    }
+
+    #[test]
+    fn test_unregister_during_dispatch() {
+        let other = OverflowCounter(Cell::new(0));
+        let mux = OverflowClientMux::<2>::new();
+        let unregister = Unregister {
+            mux: &mux,
+            other: &other,
+            count: Cell::new(0),
+        };
+        assert_eq!(mux.add_client(&unregister), Ok(()));
+
+        mux.overflow();
+        assert_eq!(unregister.count.get(), 1);
+        // `other` took the first free slot, which was already visited, so it
+        // is only called on the next overflow.
+        assert_eq!(other.0.get(), 0);
+
+        mux.overflow();
+        assert_eq!(unregister.count.get(), 1);
+        assert_eq!(other.0.get(), 1);
+    }
+}
@@ -0,0 +1,174 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Periodic callbacks with a phase offset.
+//!
+//! Sensors sharing a bus can be sampled at the same rate but staggered,
+//! e.g. four sensors every 100ms with offsets of 0, 25, 50 and 75ms, to
+//! spread the bus load. A `PhasedPeriodic` calls its client first
+//! `phase` ticks after `start`, and then every `period` ticks after that.
+//! Each deadline is computed from the previous deadline rather than from
+//! the time the callback ran, so callbacks do not drift apart however
+//! late they are delivered.
+//!
+//! A `phase` shorter than `minimum_dt()` of the underlying alarm delays
+//! the first callback, but not the ones after it, which stay at `phase`
+//! plus a multiple of `period`. A `period` shorter than `minimum_dt()` is
+//! increased to it.
+//!
+//! The `PhasedPeriodic` must be installed as the client of the underlying
+//! alarm.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let periodic = static_init!(
+//!     PhasedPeriodic<'static, VirtualMuxAlarm<'static, nrf52::rtc::Rtc>>,
+//!     PhasedPeriodic::new(virtual_alarm)
+//! );
+//! virtual_alarm.set_alarm_client(periodic);
+//! periodic.set_client(sensor);
+//! periodic.start(virtual_alarm.ticks_from_ms(100), virtual_alarm.ticks_from_ms(25))?;
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Calls its client every `period` ticks of `alarm`, offset by `phase`.
+pub struct PhasedPeriodic<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    period: Cell<A::Ticks>,
+    /// Deadline of the pending callback, on the phase-shifted grid.
+    deadline: Cell<A::Ticks>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> PhasedPeriodic<'a, A> {
+    pub fn new(alarm: &'a A) -> PhasedPeriodic<'a, A> {
+        let zero = A::Ticks::from(0);
+        PhasedPeriodic {
+            alarm,
+            period: Cell::new(zero),
+            deadline: Cell::new(zero),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    /// Calls the client `phase` ticks from now and every `period` ticks
+    /// after that, replacing any previous schedule. Returns
+    /// `Err(ErrorCode::INVAL)` if `period` is 0.
+    pub fn start(&self, period: A::Ticks, phase: A::Ticks) -> Result<(), ErrorCode> {
+        if period.into_u64() == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        let now = self.alarm.now();
+        self.period.set(cmp::max(period, self.alarm.minimum_dt()));
+        self.deadline.set(now.wrapping_add(phase));
+        self.alarm
+            .set_alarm(now, cmp::max(phase, self.alarm.minimum_dt()));
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.alarm.is_armed()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for PhasedPeriodic<'a, A> {
+    fn alarm(&self) {
+        let previous = self.deadline.get();
+        let period = self.period.get();
+        self.deadline.set(previous.wrapping_add(period));
+        // Relative to the previous deadline, so a late callback does not
+        // move the ones after it. If the next deadline has passed as
+        // well, the alarm fires immediately.
+        self.alarm.set_alarm(previous, period);
+        self.client.map(|client| client.alarm());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockAlarm};
+    use kernel::hil::time::{Ticks16, Ticks32, Time};
+
+    #[test]
+    fn test_phase_then_period() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        alarm.set_now(1_000);
+        let periodic = PhasedPeriodic::new(&alarm);
+        alarm.set_alarm_client(&periodic);
+        let client = ClientCounter::new();
+        periodic.set_client(&client);
+        periodic
+            .start(Ticks32::from(100), Ticks32::from(25))
+            .unwrap();
+
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1_025));
+        assert_eq!(alarm.run_for(24), 0);
+        assert_eq!(alarm.run_for(1), 1);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1_125));
+        assert_eq!(alarm.run_for(300), 3);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1_425));
+
+        // A late callback does not shift the grid.
+        assert!(alarm.fire_late(40));
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1_525));
+        assert_eq!(client.count(), 5);
+
+        periodic.stop().unwrap();
+        assert!(!periodic.is_running());
+    }
+
+    #[test]
+    fn test_phase_across_wrap() {
+        let alarm = MockAlarm::<Ticks16>::new();
+        alarm.set_now(0xFFF0);
+        let periodic = PhasedPeriodic::new(&alarm);
+        alarm.set_alarm_client(&periodic);
+        periodic
+            .start(Ticks16::from(0x40u16), Ticks16::from(0x20u16))
+            .unwrap();
+        assert_eq!(alarm.get_alarm(), Ticks16::from(0x0010u16));
+        assert!(alarm.fire());
+        assert_eq!(alarm.now(), Ticks16::from(0x0010u16));
+        assert_eq!(alarm.get_alarm(), Ticks16::from(0x0050u16));
+    }
+
+    #[test]
+    fn test_phase_below_minimum_dt() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        alarm.set_minimum_dt(10);
+        let periodic = PhasedPeriodic::new(&alarm);
+        alarm.set_alarm_client(&periodic);
+        periodic
+            .start(Ticks32::from(100), Ticks32::from(0))
+            .unwrap();
+
+        // The first callback is delayed to the minimum...
+        assert_eq!(alarm.get_alarm(), Ticks32::from(10));
+        assert!(alarm.fire());
+        // ...but the following ones stay on the grid.
+        assert_eq!(alarm.get_alarm(), Ticks32::from(100));
+
+        assert_eq!(
+            periodic.start(Ticks32::from(0), Ticks32::from(5)),
+            Err(ErrorCode::INVAL)
+        );
+    }
+}
@@ -0,0 +1,191 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Counter running at a fixed fraction of the rate of another counter.
+//!
+//! A fast hardware counter (e.g., 16MHz) on a narrow width wraps very
+//! often. A `PrescaledCounter` presents a logically slower counter which
+//! ticks once every `DIV` ticks of the underlying counter, and spans the
+//! full width of its `Ticks` type: it counts the overflows of the
+//! underlying counter, and only reports an overflow to its own client
+//! when the prescaled value wraps, i.e. once every `DIV` overflows of the
+//! underlying counter.
+//!
+//! The `PrescaledCounter` must be installed as the overflow client of the
+//! underlying counter by calling `setup`. Between the underlying counter
+//! wrapping and its overflow callback being handled, `now` lags behind
+//! by one underlying period. `DIV` must not be 0.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let prescaled = static_init!(
+//!     PrescaledCounter<'static, nrf52::rtc::Rtc, 16>,
+//!     PrescaledCounter::new(rtc)
+//! );
+//! prescaled.setup();
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Counter, DividedFreq, OverflowClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// `Counter` which ticks once every `DIV` ticks of `counter`.
+pub struct PrescaledCounter<'a, C: Counter<'a>, const DIV: u32> {
+    counter: &'a C,
+    /// Overflows of the underlying counter since the prescaled value last
+    /// wrapped, always less than `DIV`.
+    overflows: Cell<u32>,
+    client: OptionalCell<&'a dyn OverflowClient>,
+}
+
+impl<'a, C: Counter<'a>, const DIV: u32> PrescaledCounter<'a, C, DIV> {
+    pub fn new(counter: &'a C) -> PrescaledCounter<'a, C, DIV> {
+        PrescaledCounter {
+            counter,
+            overflows: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Register as the overflow client of the underlying counter.
+    pub fn setup(&'a self) {
+        self.counter.set_overflow_client(self);
+    }
+}
+
+impl<'a, C: Counter<'a>, const DIV: u32> Time for PrescaledCounter<'a, C, DIV> {
+    type Frequency = DividedFreq<C::Frequency, DIV>;
+    type Ticks = C::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        // Computed in `u128` so that 64-bit counters do not overflow.
+        let period = C::Ticks::max_value().into_u64() as u128 + 1;
+        let ticks = self.overflows.get() as u128 * period + self.counter.now().into_u64() as u128;
+        // `overflows < DIV`, so the quotient is less than `period`.
+        Self::Ticks::from_or_max((ticks / DIV as u128) as u64)
+    }
+}
+
+impl<'a, C: Counter<'a>, const DIV: u32> Counter<'a> for PrescaledCounter<'a, C, DIV> {
+    fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+        self.client.set(client);
+    }
+
+    fn clear_overflow_client(&self) {
+        self.client.clear();
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        self.counter.start()
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        self.counter.stop()
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        self.counter.reset()?;
+        self.overflows.set(0);
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.counter.is_running()
+    }
+}
+
+impl<'a, C: Counter<'a>, const DIV: u32> OverflowClient for PrescaledCounter<'a, C, DIV> {
+    fn overflow(&self) {
+        let overflows = (self.overflows.get() + 1) % DIV;
+        self.overflows.set(overflows);
+        if overflows == 0 {
+            self.client.map(|client| client.overflow());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockCounter};
+    use kernel::hil::time::{Freq16MHz, Ticks16, Ticks64};
+
+    const PERIOD: u64 = 0x1_0000;
+
+    #[test]
+    fn test_now_and_frequency() {
+        let counter = MockCounter::<Ticks16, Freq16MHz>::new();
+        let prescaled = PrescaledCounter::<_, 16>::new(&counter);
+        prescaled.setup();
+
+        assert_eq!(prescaled.frequency_hz(), 1_000_000);
+
+        counter.advance(0x100);
+        assert_eq!(prescaled.now(), Ticks16::from(0x10u16));
+        counter.advance(0xF);
+        assert_eq!(prescaled.now(), Ticks16::from(0x10u16));
+        counter.advance(1);
+        assert_eq!(prescaled.now(), Ticks16::from(0x11u16));
+
+        // Underlying overflows extend the prescaled value.
+        counter.set_now(0);
+        counter.advance(3 * PERIOD);
+        assert_eq!(prescaled.now(), Ticks16::from(0x3000u16));
+    }
+
+    #[test]
+    fn test_overflow_once_per_div_periods() {
+        let counter = MockCounter::<Ticks16>::new();
+        let prescaled = PrescaledCounter::<_, 16>::new(&counter);
+        prescaled.setup();
+        let client = ClientCounter::new();
+        prescaled.set_overflow_client(&client);
+
+        assert_eq!(counter.advance(15 * PERIOD), 15);
+        assert_eq!(client.count(), 0);
+        assert_eq!(prescaled.now(), Ticks16::from(0xF000u16));
+
+        assert_eq!(counter.advance(PERIOD + 0x20), 1);
+        assert_eq!(client.count(), 1);
+        assert_eq!(prescaled.now(), Ticks16::from(0x2u16));
+
+        assert_eq!(counter.advance(16 * PERIOD), 16);
+        assert_eq!(client.count(), 2);
+
+        prescaled.clear_overflow_client();
+        assert_eq!(counter.advance(16 * PERIOD), 16);
+        assert_eq!(client.count(), 2);
+    }
+
+    #[test]
+    fn test_now_64_bit() {
+        let counter = MockCounter::<Ticks64>::new();
+        let prescaled = PrescaledCounter::<_, 4>::new(&counter);
+        prescaled.setup();
+
+        counter.advance(0x100);
+        assert_eq!(prescaled.now(), Ticks64::from(0x40u64));
+
+        // One wrap of the underlying counter is a quarter of the full
+        // 64-bit range.
+        assert_eq!(counter.advance(u64::MAX), 1);
+        assert_eq!(counter.advance(1), 0);
+        assert_eq!(prescaled.now(), Ticks64::from((1u64 << 62) + 0x40));
+    }
+
+    #[test]
+    fn test_reset() {
+        let counter = MockCounter::<Ticks16>::new();
+        let prescaled = PrescaledCounter::<_, 16>::new(&counter);
+        prescaled.setup();
+
+        counter.advance(5 * PERIOD + 0x100);
+        assert_eq!(prescaled.reset(), Ok(()));
+        assert_eq!(prescaled.now(), Ticks16::from(0u16));
+    }
+}
@@ -0,0 +1,308 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Virtual alarms whose callbacks run in priority order when they are
+//! due at the same time.
+//!
+//! `MuxAlarm` calls the clients of all virtual alarms which are due in
+//! list order, which is effectively arbitrary. When the order matters,
+//! e.g. a safety handler must run before telemetry sampling at the same
+//! deadline, a `PriorityMuxAlarm` can be used instead. Each
+//! `PriorityVirtualAlarm` has a fixed priority. When the underlying alarm
+//! fires, all virtual alarms which are due at that moment are collected,
+//! and their callbacks run from the highest priority to the lowest.
+//! Alarms of equal priority run in the order their `setup` was called.
+//!
+//! A virtual alarm re-armed from a callback is not considered again until
+//! the next time the underlying alarm fires, even if it is already due.
+//!
+//! The `PriorityMuxAlarm` must be installed as the client of the
+//! underlying alarm.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let mux = static_init!(
+//!     PriorityMuxAlarm<'static, nrf52::rtc::Rtc>,
+//!     PriorityMuxAlarm::new(rtc)
+//! );
+//! rtc.set_alarm_client(mux);
+//! let safety_alarm = static_init!(
+//!     PriorityVirtualAlarm<'static, nrf52::rtc::Rtc>,
+//!     PriorityVirtualAlarm::new(mux, 10)
+//! );
+//! safety_alarm.setup();
+//! ```
+
+use core::cell::Cell;
+use core::cmp::Reverse;
+
+use kernel::collections::list::{List, ListLink, ListNode};
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// A virtual alarm with a priority, on top of a `PriorityMuxAlarm`.
+pub struct PriorityVirtualAlarm<'a, A: Alarm<'a>> {
+    mux: &'a PriorityMuxAlarm<'a, A>,
+    /// Higher priorities run first.
+    priority: u8,
+    reference: Cell<A::Ticks>,
+    dt: Cell<A::Ticks>,
+    armed: Cell<bool>,
+    /// Whether this alarm was due when the underlying alarm last fired
+    /// and its callback has not run yet.
+    firing: Cell<bool>,
+    next: ListLink<'a, PriorityVirtualAlarm<'a, A>>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> ListNode<'a, PriorityVirtualAlarm<'a, A>> for PriorityVirtualAlarm<'a, A> {
+    fn next(&self) -> &'a ListLink<PriorityVirtualAlarm<'a, A>> {
+        &self.next
+    }
+}
+
+impl<'a, A: Alarm<'a>> PriorityVirtualAlarm<'a, A> {
+    /// After calling new, always call setup()
+    pub fn new(mux: &'a PriorityMuxAlarm<'a, A>, priority: u8) -> PriorityVirtualAlarm<'a, A> {
+        let zero = A::Ticks::from(0);
+        PriorityVirtualAlarm {
+            mux,
+            priority,
+            reference: Cell::new(zero),
+            dt: Cell::new(zero),
+            armed: Cell::new(false),
+            firing: Cell::new(false),
+            next: ListLink::empty(),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Call this method immediately after new() to link this alarm to the
+    /// `PriorityMuxAlarm`. Alarms of equal priority run in the order of
+    /// their calls to `setup`.
+    pub fn setup(&'a self) {
+        self.mux.alarms.push_tail(self);
+    }
+
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    /// Ticks from `now` until the deadline, or 0 if it has passed.
+    fn remaining(&self, now: A::Ticks) -> A::Ticks {
+        let deadline = self.get_alarm();
+        if now.within_range(self.reference.get(), deadline) {
+            deadline.wrapping_sub(now)
+        } else {
+            A::Ticks::from(0)
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for PriorityVirtualAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.mux.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for PriorityVirtualAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        self.reference.set(reference);
+        self.dt.set(dt);
+        self.armed.set(true);
+        self.firing.set(false);
+        self.mux.rearm();
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.reference.get().wrapping_add(self.dt.get())
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.armed.set(false);
+        self.firing.set(false);
+        self.mux.rearm();
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.armed.get()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.mux.alarm.minimum_dt()
+    }
+}
+
+/// Multiplexes `PriorityVirtualAlarm`s on `alarm`.
+pub struct PriorityMuxAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Whether callbacks are being delivered; the alarm is re-armed once
+    /// they all return.
+    firing: Cell<bool>,
+    alarms: List<'a, PriorityVirtualAlarm<'a, A>>,
+}
+
+impl<'a, A: Alarm<'a>> PriorityMuxAlarm<'a, A> {
+    pub const fn new(alarm: &'a A) -> PriorityMuxAlarm<'a, A> {
+        PriorityMuxAlarm {
+            alarm,
+            firing: Cell::new(false),
+            alarms: List::new(),
+        }
+    }
+
+    /// Arms the alarm for the earliest deadline of any virtual alarm.
+    fn rearm(&self) {
+        if self.firing.get() {
+            return;
+        }
+        let now = self.alarm.now();
+        let next = self
+            .alarms
+            .iter()
+            .filter(|alarm| alarm.armed.get())
+            .map(|alarm| alarm.remaining(now))
+            .min();
+        match next {
+            Some(dt) => self.alarm.set_alarm(now, dt),
+            None => {
+                let _ = self.alarm.disarm();
+            }
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for PriorityMuxAlarm<'a, A> {
+    fn alarm(&self) {
+        self.firing.set(true);
+        // Take a single snapshot of which alarms are due, so that alarms
+        // sharing a deadline are ordered together.
+        let now = self.alarm.now();
+        for alarm in self.alarms.iter() {
+            if alarm.armed.get() && !now.within_range(alarm.reference.get(), alarm.get_alarm()) {
+                alarm.armed.set(false);
+                alarm.firing.set(true);
+            }
+        }
+        // `min_by_key` returns the first of equal elements, i.e. the one
+        // set up first.
+        while let Some(alarm) = self
+            .alarms
+            .iter()
+            .filter(|alarm| alarm.firing.get())
+            .min_by_key(|alarm| Reverse(alarm.priority))
+        {
+            alarm.firing.set(false);
+            alarm.client.map(|client| client.alarm());
+        }
+        self.firing.set(false);
+        self.rearm();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    /// Appends its id to a shared log of callbacks.
+    struct Logger<'a> {
+        id: u8,
+        log: &'a Cell<[u8; 4]>,
+        len: &'a Cell<usize>,
+    }
+
+    impl AlarmClient for Logger<'_> {
+        fn alarm(&self) {
+            let mut log = self.log.get();
+            log[self.len.get()] = self.id;
+            self.log.set(log);
+            self.len.set(self.len.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_same_deadline_runs_by_priority() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let mux = PriorityMuxAlarm::new(&alarm);
+        alarm.set_alarm_client(&mux);
+        let telemetry = PriorityVirtualAlarm::new(&mux, 1);
+        let safety = PriorityVirtualAlarm::new(&mux, 10);
+        let logging = PriorityVirtualAlarm::new(&mux, 1);
+        let control = PriorityVirtualAlarm::new(&mux, 5);
+        telemetry.setup();
+        safety.setup();
+        logging.setup();
+        control.setup();
+
+        let log = Cell::new([0; 4]);
+        let len = Cell::new(0);
+        let clients = [1, 2, 3, 4].map(|id| Logger {
+            id,
+            log: &log,
+            len: &len,
+        });
+        telemetry.set_alarm_client(&clients[0]);
+        safety.set_alarm_client(&clients[1]);
+        logging.set_alarm_client(&clients[2]);
+        control.set_alarm_client(&clients[3]);
+
+        for virtual_alarm in [&telemetry, &safety, &logging, &control] {
+            virtual_alarm.set_alarm(Ticks32::from(0), Ticks32::from(100));
+        }
+        assert_eq!(alarm.run_for(200), 1);
+        // Safety (10), control (5), then telemetry and logging (1) in
+        // the order they were set up.
+        assert_eq!(log.get(), [2, 4, 1, 3]);
+        assert!(!alarm.is_armed());
+    }
+
+    #[test]
+    fn test_different_deadlines_run_in_time_order() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let mux = PriorityMuxAlarm::new(&alarm);
+        alarm.set_alarm_client(&mux);
+        let low = PriorityVirtualAlarm::new(&mux, 0);
+        let high = PriorityVirtualAlarm::new(&mux, 9);
+        low.setup();
+        high.setup();
+
+        let log = Cell::new([0; 4]);
+        let len = Cell::new(0);
+        let low_client = Logger {
+            id: 1,
+            log: &log,
+            len: &len,
+        };
+        let high_client = Logger {
+            id: 2,
+            log: &log,
+            len: &len,
+        };
+        low.set_alarm_client(&low_client);
+        high.set_alarm_client(&high_client);
+
+        high.set_alarm(Ticks32::from(0), Ticks32::from(50));
+        low.set_alarm(Ticks32::from(0), Ticks32::from(20));
+        assert_eq!(alarm.get_alarm(), Ticks32::from(20));
+        assert_eq!(alarm.run_for(100), 2);
+        assert_eq!(&log.get()[..2], &[1, 2]);
+    }
+}
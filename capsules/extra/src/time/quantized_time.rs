@@ -0,0 +1,75 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! `Time` with a coarser resolution than the underlying clock.
+//!
+//! Code that should work across clock resolutions, e.g. on a 1kHz clock
+//! as well as a 16MHz one, is easiest to test by making a fast clock
+//! look coarse. A `QuantizedTime` rounds each `now()` of the underlying
+//! time source down to a multiple of a configurable granule, so the
+//! reported value only changes every `granule` ticks. The frequency is
+//! unchanged, so conversions such as `ticks_from_ms` still reflect the
+//! true rate of the clock.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Ticks, Time};
+
+/// `Time` reporting `time.now()` rounded down to a multiple of `granule`.
+pub struct QuantizedTime<'a, T: Time> {
+    time: &'a T,
+    /// Resolution in ticks. A granule of 0 or 1 disables rounding.
+    granule: Cell<T::Ticks>,
+}
+
+impl<'a, T: Time> QuantizedTime<'a, T> {
+    pub fn new(time: &'a T, granule: T::Ticks) -> QuantizedTime<'a, T> {
+        QuantizedTime {
+            time,
+            granule: Cell::new(granule),
+        }
+    }
+
+    pub fn set_granule(&self, granule: T::Ticks) {
+        self.granule.set(granule);
+    }
+
+    pub fn granule(&self) -> T::Ticks {
+        self.granule.get()
+    }
+}
+
+impl<'a, T: Time> Time for QuantizedTime<'a, T> {
+    type Frequency = T::Frequency;
+    type Ticks = T::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.time.now().round_down_to(self.granule.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::{ConvertTicks, Freq16MHz, Ticks32};
+
+    #[test]
+    fn test_multiples_of_granule() {
+        let alarm = MockAlarm::<Ticks32, Freq16MHz>::new();
+        let coarse = QuantizedTime::new(&alarm, 16_000u32.into());
+
+        for _ in 0..100 {
+            alarm.advance(1_234);
+            assert_eq!(coarse.now().into_u32() % 16_000, 0);
+            assert!(alarm.now().into_u32() - coarse.now().into_u32() < 16_000);
+        }
+
+        // Conversions use the real frequency.
+        assert_eq!(coarse.ticks_from_ms(1), Ticks32::from(16_000u32));
+
+        coarse.set_granule(0u32.into());
+        assert_eq!(coarse.now(), alarm.now());
+    }
+}
@@ -0,0 +1,166 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Token bucket rate limiter driven by a `Time`.
+//!
+//! Capsules which must not perform an action too often, e.g. printing a
+//! log message for every dropped packet, can ask a `RateLimiter` before
+//! each action. The limiter holds up to `tokens` tokens and starts full.
+//! Each granted `try_acquire` takes one token, and one token is added
+//! back for every `refill_interval` ticks which elapse, up to the
+//! capacity. This allows bursts of up to `tokens` actions, and on average
+//! one action per `refill_interval`.
+//!
+//! Time is only read in `try_acquire`, so no alarm is needed. Elapsed
+//! time is computed with wrapping arithmetic, which is correct as long as
+//! `try_acquire` is called at least once per wrap period of the
+//! underlying time source; longer gaps lose whole wrap periods, which
+//! can only leave the bucket emptier than it should be.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! // At most 5 messages at once, and one more every second.
+//! let limiter = static_init!(
+//!     RateLimiter<'static, nrf52::rtc::Rtc>,
+//!     RateLimiter::new(rtc, 5, rtc.ticks_from_seconds(1))
+//! );
+//! if limiter.try_acquire() {
+//!     debug!("packet dropped");
+//! }
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Ticks, Time};
+
+/// Grants at most `capacity` actions at once, and one more per
+/// `refill_interval` ticks of `time`.
+pub struct RateLimiter<'a, T: Time> {
+    time: &'a T,
+    capacity: u32,
+    refill_interval: T::Ticks,
+    tokens: Cell<u32>,
+    /// When tokens were last added; advanced by whole refill intervals so
+    /// that partial intervals are not lost.
+    last_refill: Cell<T::Ticks>,
+}
+
+impl<'a, T: Time> RateLimiter<'a, T> {
+    pub fn new(time: &'a T, tokens: u32, refill_interval: T::Ticks) -> RateLimiter<'a, T> {
+        RateLimiter {
+            time,
+            capacity: tokens,
+            refill_interval,
+            tokens: Cell::new(tokens),
+            last_refill: Cell::new(time.now()),
+        }
+    }
+
+    /// Takes a token if one is available. Returns whether the action is
+    /// allowed. With a `refill_interval` of 0 the bucket is refilled on
+    /// every call.
+    pub fn try_acquire(&self) -> bool {
+        self.refill();
+        match self.tokens.get().checked_sub(1) {
+            Some(tokens) => {
+                self.tokens.set(tokens);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of tokens available now.
+    pub fn available(&self) -> u32 {
+        self.refill();
+        self.tokens.get()
+    }
+
+    fn refill(&self) {
+        let now = self.time.now();
+        let interval = self.refill_interval.into_u64();
+        if interval == 0 {
+            self.tokens.set(self.capacity);
+            self.last_refill.set(now);
+            return;
+        }
+        let elapsed = now.wrapping_sub(self.last_refill.get()).into_u64();
+        let intervals = elapsed / interval;
+        let tokens = u32::try_from(intervals)
+            .unwrap_or(u32::MAX)
+            .saturating_add(self.tokens.get());
+        if tokens >= self.capacity {
+            // A full bucket does not bank time towards later tokens.
+            self.tokens.set(self.capacity);
+            self.last_refill.set(now);
+        } else {
+            self.tokens.set(tokens);
+            self.last_refill.set(
+                self.last_refill
+                    .get()
+                    .wrapping_add(T::Ticks::from_or_max(intervals * interval)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockCounter;
+    use kernel::hil::time::{Freq1KHz, Ticks16, Ticks32};
+
+    #[test]
+    fn test_depletes_and_refills() {
+        let counter = MockCounter::<Ticks32, Freq1KHz>::new();
+        let limiter = RateLimiter::new(&counter, 3, Ticks32::from(100));
+
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        counter.advance(99);
+        assert!(!limiter.try_acquire());
+        counter.advance(1);
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        // The partial interval before the last refill is kept.
+        counter.advance(150);
+        assert_eq!(limiter.available(), 1);
+        counter.advance(50);
+        assert_eq!(limiter.available(), 2);
+
+        // The bucket never holds more than its capacity.
+        counter.advance(10_000);
+        assert_eq!(limiter.available(), 3);
+    }
+
+    #[test]
+    fn test_refills_across_wrap() {
+        let counter = MockCounter::<Ticks16, Freq1KHz>::new();
+        counter.set_now(0xFFC0);
+        let limiter = RateLimiter::new(&counter, 1, Ticks16::from(0x80u16));
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        counter.advance(0x7F);
+        assert!(!limiter.try_acquire());
+        counter.advance(1);
+        assert_eq!(counter.now(), Ticks16::from(0x0040u16));
+        assert!(limiter.try_acquire());
+    }
+
+    #[test]
+    fn test_zero_interval_allows_everything() {
+        let counter = MockCounter::<Ticks32, Freq1KHz>::new();
+        let limiter = RateLimiter::new(&counter, 1, Ticks32::from(0));
+        for _ in 0..10 {
+            assert!(limiter.try_acquire());
+        }
+    }
+}
@@ -0,0 +1,197 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm wrapper which defers re-arming from within a callback.
+//!
+//! Clients commonly call `set_alarm` from their own `alarm()` callback to
+//! build a periodic alarm, sometimes more than once (e.g. setting a
+//! default interval and then correcting it). Some hardware
+//! implementations do not handle being reprogrammed while their
+//! interrupt is still being serviced.
+//!
+//! A `ReentrantSafeAlarm` guarantees that, while its client's callback
+//! runs, the underlying alarm is not touched. A `set_alarm` or `disarm`
+//! issued during the callback is buffered, with later calls replacing
+//! earlier ones, and `get_alarm` and `is_armed` report the buffered
+//! state. Once the callback returns, the underlying alarm is programmed
+//! exactly once with the last requested deadline, or left disarmed if
+//! the client did not re-arm (or disarmed after re-arming).
+//!
+//! The `ReentrantSafeAlarm` must be installed as the client of the
+//! underlying alarm.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Alarm that applies re-arming requests made during its callback only
+/// after the callback returns.
+pub struct ReentrantSafeAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Whether the client's callback is running.
+    in_callback: Cell<bool>,
+    /// `(reference, dt)` of the last `set_alarm` during the callback.
+    pending: OptionalCell<(A::Ticks, A::Ticks)>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> ReentrantSafeAlarm<'a, A> {
+    pub fn new(alarm: &'a A) -> ReentrantSafeAlarm<'a, A> {
+        ReentrantSafeAlarm {
+            alarm,
+            in_callback: Cell::new(false),
+            pending: OptionalCell::empty(),
+            client: OptionalCell::empty(),
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for ReentrantSafeAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for ReentrantSafeAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        if self.in_callback.get() {
+            self.pending.set((reference, dt));
+        } else {
+            self.alarm.set_alarm(reference, dt);
+        }
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.pending.map_or_else(
+            || self.alarm.get_alarm(),
+            |(reference, dt)| reference.wrapping_add(dt),
+        )
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        if self.in_callback.get() {
+            // The underlying alarm is already disarmed while its callback
+            // runs.
+            self.pending.clear();
+            Ok(())
+        } else {
+            self.alarm.disarm()
+        }
+    }
+
+    fn is_armed(&self) -> bool {
+        if self.in_callback.get() {
+            self.pending.is_some()
+        } else {
+            self.alarm.is_armed()
+        }
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for ReentrantSafeAlarm<'a, A> {
+    fn alarm(&self) {
+        self.in_callback.set(true);
+        self.client.map(|client| client.alarm());
+        self.in_callback.set(false);
+        if let Some((reference, dt)) = self.pending.take() {
+            self.alarm.set_alarm(reference, dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    /// Re-arms twice from its callback, disarming in between on odd
+    /// callbacks, and counts its callbacks.
+    struct Rearming<'a> {
+        alarm: &'a ReentrantSafeAlarm<'a, MockAlarm<'a>>,
+        fired: Cell<usize>,
+    }
+
+    impl AlarmClient for Rearming<'_> {
+        fn alarm(&self) {
+            self.fired.set(self.fired.get() + 1);
+            assert!(!self.alarm.is_armed());
+            let now = self.alarm.now();
+            self.alarm.set_alarm(now, Ticks32::from(10));
+            assert!(self.alarm.is_armed());
+            if self.fired.get() % 2 == 1 {
+                self.alarm.disarm().unwrap();
+                assert!(!self.alarm.is_armed());
+            }
+            self.alarm.set_alarm(now, Ticks32::from(100));
+            assert_eq!(self.alarm.get_alarm(), now.wrapping_add(Ticks32::from(100)));
+        }
+    }
+
+    #[test]
+    fn test_rearm_in_callback_programs_once() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let safe = ReentrantSafeAlarm::new(&alarm);
+        alarm.set_alarm_client(&safe);
+        let client = Rearming {
+            alarm: &safe,
+            fired: Cell::new(0),
+        };
+        safe.set_alarm_client(&client);
+
+        safe.set_alarm(Ticks32::from(0), Ticks32::from(100));
+        assert_eq!(alarm.programmed(), 1);
+
+        // Only the last deadline is programmed, once per callback, so the
+        // intermediate 10-tick deadline never fires.
+        assert_eq!(alarm.run_for(350), 3);
+        assert_eq!(client.fired.get(), 3);
+        assert_eq!(alarm.programmed(), 4);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(400));
+        assert_eq!(safe.get_alarm(), Ticks32::from(400));
+    }
+
+    /// Disarms from its callback after re-arming.
+    struct Stopping<'a> {
+        alarm: &'a ReentrantSafeAlarm<'a, MockAlarm<'a>>,
+    }
+
+    impl AlarmClient for Stopping<'_> {
+        fn alarm(&self) {
+            self.alarm.set_alarm(self.alarm.now(), Ticks32::from(10));
+            self.alarm.disarm().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_disarm_in_callback() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let safe = ReentrantSafeAlarm::new(&alarm);
+        alarm.set_alarm_client(&safe);
+        let client = Stopping { alarm: &safe };
+        safe.set_alarm_client(&client);
+
+        safe.set_alarm(Ticks32::from(0), Ticks32::from(100));
+        assert_eq!(alarm.run_for(1000), 1);
+        assert_eq!(alarm.programmed(), 1);
+        assert!(!safe.is_armed());
+    }
+}
@@ -0,0 +1,225 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Share one hardware `Counter` between several users.
+//!
+//! If capsules share a counter directly, one of them calling `stop()`
+//! stops it for all the others. A `RefCountedCounter` instead hands out a
+//! `CounterUser` to each of them. Each `CounterUser` implements `Counter`
+//! and records whether its own user has started it. The hardware counter
+//! is started when the first user starts and stopped only when the last
+//! user stops. `is_running` on a `CounterUser` reports that user's own
+//! view.
+//!
+//! Resetting the counter would disturb every other user, so `reset`
+//! fails with `ErrorCode::BUSY` while any other user has the counter
+//! started.
+//!
+//! Every `CounterUser` has its own overflow client. The
+//! `RefCountedCounter` must be installed as the overflow client of the
+//! underlying counter.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let shared = static_init!(
+//!     RefCountedCounter<'static, nrf52::rtc::Rtc>,
+//!     RefCountedCounter::new(rtc)
+//! );
+//! rtc.set_overflow_client(shared);
+//! let user = static_init!(
+//!     CounterUser<'static, nrf52::rtc::Rtc>,
+//!     CounterUser::new(shared)
+//! );
+//! user.setup();
+//! ```
+
+use core::cell::Cell;
+
+use kernel::collections::list::{List, ListLink, ListNode};
+use kernel::hil::time::{Counter, OverflowClient, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// One user's handle on a counter shared through a `RefCountedCounter`.
+pub struct CounterUser<'a, C: Counter<'a>> {
+    shared: &'a RefCountedCounter<'a, C>,
+    /// Whether this user has started the counter.
+    started: Cell<bool>,
+    next: ListLink<'a, CounterUser<'a, C>>,
+    client: OptionalCell<&'a dyn OverflowClient>,
+}
+
+impl<'a, C: Counter<'a>> ListNode<'a, CounterUser<'a, C>> for CounterUser<'a, C> {
+    fn next(&self) -> &'a ListLink<CounterUser<'a, C>> {
+        &self.next
+    }
+}
+
+impl<'a, C: Counter<'a>> CounterUser<'a, C> {
+    /// After calling new, always call setup()
+    pub fn new(shared: &'a RefCountedCounter<'a, C>) -> CounterUser<'a, C> {
+        CounterUser {
+            shared,
+            started: Cell::new(false),
+            next: ListLink::empty(),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Call this method immediately after new() to receive overflow
+    /// callbacks.
+    pub fn setup(&'a self) {
+        self.shared.users.push_head(self);
+    }
+}
+
+impl<'a, C: Counter<'a>> Time for CounterUser<'a, C> {
+    type Frequency = C::Frequency;
+    type Ticks = C::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.shared.counter.now()
+    }
+}
+
+impl<'a, C: Counter<'a>> Counter<'a> for CounterUser<'a, C> {
+    fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+        self.client.set(client);
+    }
+
+    fn clear_overflow_client(&self) {
+        self.client.clear();
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        if !self.started.get() {
+            self.shared.acquire()?;
+            self.started.set(true);
+        }
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        if self.started.get() {
+            self.shared.release()?;
+            self.started.set(false);
+        }
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        let others = self.shared.running.get() - usize::from(self.started.get());
+        if others > 0 {
+            return Err(ErrorCode::BUSY);
+        }
+        self.shared.counter.reset()
+    }
+
+    fn is_running(&self) -> bool {
+        self.started.get()
+    }
+}
+
+/// Reference counts the users of `counter` which have started it.
+pub struct RefCountedCounter<'a, C: Counter<'a>> {
+    counter: &'a C,
+    /// Number of users which have started the counter.
+    running: Cell<usize>,
+    users: List<'a, CounterUser<'a, C>>,
+}
+
+impl<'a, C: Counter<'a>> RefCountedCounter<'a, C> {
+    pub const fn new(counter: &'a C) -> RefCountedCounter<'a, C> {
+        RefCountedCounter {
+            counter,
+            running: Cell::new(0),
+            users: List::new(),
+        }
+    }
+
+    fn acquire(&self) -> Result<(), ErrorCode> {
+        if self.running.get() == 0 {
+            self.counter.start()?;
+        }
+        self.running.set(self.running.get() + 1);
+        Ok(())
+    }
+
+    fn release(&self) -> Result<(), ErrorCode> {
+        if self.running.get() == 1 {
+            self.counter.stop()?;
+        }
+        self.running.set(self.running.get() - 1);
+        Ok(())
+    }
+}
+
+impl<'a, C: Counter<'a>> OverflowClient for RefCountedCounter<'a, C> {
+    fn overflow(&self) {
+        for user in self.users.iter() {
+            user.client.map(|client| client.overflow());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockCounter};
+    use kernel::hil::time::{Ticks, Ticks32};
+
+    #[test]
+    fn test_runs_until_last_user_stops() {
+        let counter = MockCounter::<Ticks32>::new();
+        let shared = RefCountedCounter::new(&counter);
+        let a = CounterUser::new(&shared);
+        let b = CounterUser::new(&shared);
+        a.setup();
+        b.setup();
+
+        assert_eq!(a.start(), Ok(()));
+        assert_eq!(b.start(), Ok(()));
+        // Starting twice does not count twice.
+        assert_eq!(a.start(), Ok(()));
+        assert!(counter.is_running());
+
+        assert_eq!(a.stop(), Ok(()));
+        assert!(!a.is_running());
+        assert!(b.is_running());
+        assert!(counter.is_running());
+
+        assert_eq!(b.stop(), Ok(()));
+        assert!(!counter.is_running());
+        assert_eq!(b.stop(), Ok(()));
+    }
+
+    #[test]
+    fn test_reset_and_overflow() {
+        let counter = MockCounter::<Ticks32>::new();
+        counter.set_now(1000);
+        let shared = RefCountedCounter::new(&counter);
+        counter.set_overflow_client(&shared);
+        let a = CounterUser::new(&shared);
+        let b = CounterUser::new(&shared);
+        a.setup();
+        b.setup();
+        let a_client = ClientCounter::new();
+        let b_client = ClientCounter::new();
+        a.set_overflow_client(&a_client);
+        b.set_overflow_client(&b_client);
+
+        a.start().unwrap();
+        b.start().unwrap();
+        assert_eq!(a.reset(), Err(ErrorCode::BUSY));
+        b.stop().unwrap();
+        assert_eq!(a.reset(), Ok(()));
+        assert_eq!(a.now().into_u32(), 0);
+
+        counter.advance(1 << 32);
+        assert_eq!(a_client.count(), 1);
+        assert_eq!(b_client.count(), 1);
+    }
+}
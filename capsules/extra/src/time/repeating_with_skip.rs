@@ -0,0 +1,172 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Repeating alarm which reports periods skipped because of latency.
+//!
+//! A repeating timer whose callback is delayed past one or more later
+//! deadlines has to either fire for each of them in a burst, or skip
+//! them. A `RepeatingWithSkip` skips them, but tells its client how many
+//! were skipped, e.g. so a sampling driver can mark gaps in its data.
+//! Deadlines lie on a fixed grid of `period` ticks from the call to
+//! `start`. When the underlying alarm fires, the number of whole periods
+//! between the scheduled deadline and `now()` is reported as `missed`,
+//! and the alarm is set for the next grid point after `now()`. The grid
+//! therefore does not drift however late callbacks are.
+//!
+//! The `RepeatingWithSkip` must be installed as the client of the
+//! underlying alarm.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let sampler = static_init!(
+//!     RepeatingWithSkip<'static, VirtualMuxAlarm<'static, nrf52::rtc::Rtc>>,
+//!     RepeatingWithSkip::new(virtual_alarm)
+//! );
+//! virtual_alarm.set_alarm_client(sampler);
+//! sampler.set_client(driver);
+//! sampler.start(Ticks32::from(328))?;
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Callback handler for a `RepeatingWithSkip`.
+pub trait RepeatSkipClient {
+    /// Called once per callback of the underlying alarm. `missed` is the
+    /// number of grid points which passed before it was delivered, not
+    /// counting the one it was scheduled for.
+    fn fired(&self, missed: u32);
+}
+
+/// Repeats every `period` ticks of `alarm`, skipping late deadlines.
+pub struct RepeatingWithSkip<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    period: Cell<A::Ticks>,
+    /// Grid point the alarm is currently set for.
+    deadline: Cell<A::Ticks>,
+    client: OptionalCell<&'a dyn RepeatSkipClient>,
+}
+
+impl<'a, A: Alarm<'a>> RepeatingWithSkip<'a, A> {
+    pub fn new(alarm: &'a A) -> RepeatingWithSkip<'a, A> {
+        let zero = A::Ticks::from(0);
+        RepeatingWithSkip {
+            alarm,
+            period: Cell::new(zero),
+            deadline: Cell::new(zero),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn RepeatSkipClient) {
+        self.client.set(client);
+    }
+
+    /// Starts repeating every `period` ticks from now, replacing any
+    /// previous period. Returns `Err(ErrorCode::INVAL)` if `period` is 0.
+    pub fn start(&self, period: A::Ticks) -> Result<(), ErrorCode> {
+        if period.into_u64() == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        let now = self.alarm.now();
+        self.period.set(period);
+        self.deadline.set(now.wrapping_add(period));
+        self.alarm.set_alarm(now, period);
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.alarm.is_armed()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for RepeatingWithSkip<'a, A> {
+    fn alarm(&self) {
+        let now = self.alarm.now();
+        let period = self.period.get().into_u64();
+        let late = now.wrapping_sub(self.deadline.get()).into_u64();
+        let missed = late / period;
+        // Time until the first grid point after `now`.
+        let dt = A::Ticks::from_or_max(period - late % period);
+        self.deadline.set(now.wrapping_add(dt));
+        self.alarm.set_alarm(now, dt);
+        self.client
+            .map(|client| client.fired(u32::try_from(missed).unwrap_or(u32::MAX)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::{Ticks32, Time};
+
+    struct Recorder {
+        fired: Cell<u32>,
+        missed: Cell<u32>,
+    }
+
+    impl RepeatSkipClient for Recorder {
+        fn fired(&self, missed: u32) {
+            self.fired.set(self.fired.get() + 1);
+            self.missed.set(missed);
+        }
+    }
+
+    #[test]
+    fn test_reports_missed_periods() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let repeating = RepeatingWithSkip::new(&alarm);
+        alarm.set_alarm_client(&repeating);
+        let recorder = Recorder {
+            fired: Cell::new(0),
+            missed: Cell::new(0),
+        };
+        repeating.set_client(&recorder);
+        repeating.start(Ticks32::from(100)).unwrap();
+
+        // On time.
+        assert!(alarm.fire());
+        assert_eq!(recorder.missed.get(), 0);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(200));
+
+        // 30 ticks late: still within the period.
+        assert!(alarm.fire_late(30));
+        assert_eq!(recorder.missed.get(), 0);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(300));
+
+        // 250 ticks late: the deadlines at 400 and 500 passed as well.
+        assert!(alarm.fire_late(250));
+        assert_eq!(alarm.now(), Ticks32::from(550));
+        assert_eq!(recorder.missed.get(), 2);
+        // The next deadline stays on the grid.
+        assert_eq!(alarm.get_alarm(), Ticks32::from(600));
+
+        // Exactly one period late.
+        assert!(alarm.fire_late(100));
+        assert_eq!(recorder.missed.get(), 1);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(800));
+        assert_eq!(recorder.fired.get(), 4);
+
+        repeating.stop().unwrap();
+        assert!(!repeating.is_running());
+    }
+
+    #[test]
+    fn test_zero_period() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let repeating = RepeatingWithSkip::new(&alarm);
+        assert_eq!(repeating.start(Ticks32::from(0)), Err(ErrorCode::INVAL));
+        assert!(!repeating.is_running());
+    }
+}
@@ -0,0 +1,258 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Simulated clock for testing alarm-driven capsules off hardware.
+//!
+//! A `SimClock` holds the current time, which only moves when the test
+//! calls `advance_to`. Any number of `SimAlarm`s share a clock; they
+//! implement `Alarm` and can be given to the capsules under test in place
+//! of a hardware alarm. Advancing the clock fires every alarm which
+//! comes due along the way, in deadline order and with `now` set to each
+//! deadline in turn, so that interleaved and re-armed alarms behave
+//! deterministically.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let clock = SimClock::<Ticks32>::new();
+//! let alarm = SimAlarm::new(&clock);
+//! alarm.setup();
+//! let timer = AlarmToTimer::new(&alarm);
+//! alarm.set_alarm_client(&timer);
+//! timer.repeating(Ticks32::from(10));
+//! clock.advance_to(Ticks32::from(100));
+//! ```
+
+use core::cell::Cell;
+
+use kernel::collections::list::{List, ListLink, ListNode};
+use kernel::hil::time::{Alarm, AlarmClient, Freq1KHz, Frequency, Ticks, Ticks32, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// A simulated clock driving any number of `SimAlarm`s. Time only moves
+/// when `advance_to` is called, which fires every alarm that comes due
+/// along the way, in deadline order, with `now` set to each deadline in
+/// turn.
+pub struct SimClock<'a, T: Ticks = Ticks32, F: Frequency = Freq1KHz> {
+    now: Cell<T>,
+    alarms: List<'a, SimAlarm<'a, T, F>>,
+}
+
+impl<'a, T: Ticks, F: Frequency> SimClock<'a, T, F> {
+    pub fn new() -> Self {
+        Self {
+            now: Cell::new(T::from(0)),
+            alarms: List::new(),
+        }
+    }
+
+    /// Moves `now` forward to `target`, firing each alarm whose deadline
+    /// is reached on the way. An alarm re-armed from its callback is
+    /// scheduled relative to the deadline it fired at, and fires again in
+    /// the same call if it comes due before `target`. Returns the number
+    /// of callbacks delivered.
+    pub fn advance_to(&self, target: T) -> usize {
+        let mut fired = 0;
+        loop {
+            let now = self.now.get();
+            let until_target = target.wrapping_sub(now);
+            let next = self
+                .alarms
+                .iter()
+                .filter(|alarm| alarm.armed.get())
+                .map(|alarm| (alarm.remaining(now), alarm))
+                .filter(|(remaining, _)| *remaining <= until_target)
+                .min_by_key(|(remaining, _)| *remaining);
+            match next {
+                Some((remaining, alarm)) => {
+                    self.now.set(now.wrapping_add(remaining));
+                    alarm.armed.set(false);
+                    alarm.client.map(|client| client.alarm());
+                    fired += 1;
+                }
+                None => break,
+            }
+        }
+        self.now.set(target);
+        fired
+    }
+}
+
+/// An `Alarm` driven by a `SimClock`.
+pub struct SimAlarm<'a, T: Ticks = Ticks32, F: Frequency = Freq1KHz> {
+    clock: &'a SimClock<'a, T, F>,
+    reference: Cell<T>,
+    dt: Cell<T>,
+    armed: Cell<bool>,
+    next: ListLink<'a, SimAlarm<'a, T, F>>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, T: Ticks, F: Frequency> ListNode<'a, SimAlarm<'a, T, F>> for SimAlarm<'a, T, F> {
+    fn next(&self) -> &'a ListLink<SimAlarm<'a, T, F>> {
+        &self.next
+    }
+}
+
+impl<'a, T: Ticks, F: Frequency> SimAlarm<'a, T, F> {
+    /// After calling new, always call setup()
+    pub fn new(clock: &'a SimClock<'a, T, F>) -> Self {
+        Self {
+            clock,
+            reference: Cell::new(T::from(0)),
+            dt: Cell::new(T::from(0)),
+            armed: Cell::new(false),
+            next: ListLink::empty(),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn setup(&'a self) {
+        self.clock.alarms.push_head(self);
+    }
+
+    /// Ticks from `now` until the deadline, or 0 if it has passed.
+    fn remaining(&self, now: T) -> T {
+        let deadline = self.get_alarm();
+        if now.within_range(self.reference.get(), deadline) {
+            deadline.wrapping_sub(now)
+        } else {
+            T::from(0)
+        }
+    }
+}
+
+impl<T: Ticks, F: Frequency> Time for SimAlarm<'_, T, F> {
+    type Frequency = F;
+    type Ticks = T;
+
+    fn now(&self) -> T {
+        self.clock.now.get()
+    }
+}
+
+impl<'a, T: Ticks, F: Frequency> Alarm<'a> for SimAlarm<'a, T, F> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: T, dt: T) {
+        self.reference.set(reference);
+        self.dt.set(dt);
+        self.armed.set(true);
+    }
+
+    fn get_alarm(&self) -> T {
+        self.reference.get().wrapping_add(self.dt.get())
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.armed.set(false);
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.armed.get()
+    }
+
+    fn minimum_dt(&self) -> T {
+        T::from(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::ClientCounter;
+    use kernel::hil::time::Ticks64;
+
+    /// Records the times of its callbacks, re-arming `period` ticks after
+    /// each one while `period` is not 0.
+    struct Log<'a> {
+        alarm: &'a SimAlarm<'a>,
+        period: u32,
+        fired_at: Cell<[u32; 4]>,
+        fired: Cell<usize>,
+    }
+
+    impl<'a> Log<'a> {
+        fn new(alarm: &'a SimAlarm<'a>, period: u32) -> Self {
+            Log {
+                alarm,
+                period,
+                fired_at: Cell::new([0; 4]),
+                fired: Cell::new(0),
+            }
+        }
+    }
+
+    impl AlarmClient for Log<'_> {
+        fn alarm(&self) {
+            let mut fired_at = self.fired_at.get();
+            fired_at[self.fired.get()] = self.alarm.now().into_u32();
+            self.fired_at.set(fired_at);
+            self.fired.set(self.fired.get() + 1);
+            if self.period != 0 && self.fired.get() < fired_at.len() {
+                self.alarm
+                    .set_alarm(self.alarm.now(), Ticks32::from(self.period));
+            }
+        }
+    }
+
+    #[test]
+    fn test_interleaved_and_rearmed_alarms() {
+        let clock = SimClock::new();
+        let periodic = SimAlarm::new(&clock);
+        let a = SimAlarm::new(&clock);
+        let b = SimAlarm::new(&clock);
+        periodic.setup();
+        a.setup();
+        b.setup();
+        let periodic_log = Log::new(&periodic, 30);
+        let a_log = Log::new(&a, 0);
+        let b_log = Log::new(&b, 0);
+        periodic.set_alarm_client(&periodic_log);
+        a.set_alarm_client(&a_log);
+        b.set_alarm_client(&b_log);
+
+        periodic.set_alarm(Ticks32::from(0), Ticks32::from(30));
+        b.set_alarm(Ticks32::from(0), Ticks32::from(50));
+        a.set_alarm(Ticks32::from(0), Ticks32::from(10));
+
+        assert_eq!(clock.advance_to(Ticks32::from(40)), 2);
+        assert_eq!(a_log.fired_at.get()[0], 10);
+        assert_eq!(periodic_log.fired_at.get()[0], 30);
+        assert_eq!(periodic.now().into_u32(), 40);
+
+        assert_eq!(clock.advance_to(Ticks32::from(100)), 3);
+        assert_eq!(b_log.fired_at.get()[0], 50);
+        assert_eq!(periodic_log.fired_at.get(), [30, 60, 90, 0]);
+        assert_eq!(periodic.get_alarm().into_u32(), 120);
+
+        // Nothing is due before 110.
+        assert_eq!(clock.advance_to(Ticks32::from(110)), 0);
+        assert!(!a.is_armed() && !b.is_armed());
+    }
+
+    #[test]
+    fn test_advance_past_32_bits() {
+        let clock = SimClock::<Ticks64>::new();
+        let alarm = SimAlarm::new(&clock);
+        alarm.setup();
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&client);
+
+        alarm.set_alarm(Ticks64::from(0u64), Ticks64::from(1u64 << 33));
+        assert_eq!(clock.advance_to(Ticks64::from(u32::MAX as u64 + 1)), 0);
+        assert_eq!(clock.advance_to(Ticks64::from(1u64 << 34)), 1);
+        assert_eq!(client.count(), 1);
+        assert_eq!(alarm.now(), Ticks64::from(1u64 << 34));
+    }
+}
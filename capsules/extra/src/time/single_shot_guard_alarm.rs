@@ -0,0 +1,188 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm wrapper which detects deadlines being silently replaced.
+//!
+//! `set_alarm` replaces any deadline which has not fired yet. Code which
+//! schedules a second deadline while expecting the first one to fire as
+//! well therefore loses a callback without any error. A
+//! `SingleShotGuardAlarm` tracks whether a deadline is outstanding, i.e.
+//! set and neither fired nor disarmed. Calling `set_alarm` while one is
+//! outstanding is a violation. Re-arming from within the callback is fine,
+//! as the alarm is disarmed when its callback runs, and so is calling
+//! `disarm` before setting a new deadline.
+//!
+//! Violations are counted and, while `set_strict` is enabled (the
+//! default), also trigger a `debug_assert!`. The new deadline still
+//! replaces the old one, as it would on the underlying alarm.
+//!
+//! The `SingleShotGuardAlarm` must be installed as the client of the
+//! underlying alarm.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Alarm that checks at most one deadline of `alarm` is outstanding.
+pub struct SingleShotGuardAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Whether a deadline was set and has neither fired nor been disarmed.
+    outstanding: Cell<bool>,
+    violations: Cell<u32>,
+    strict: Cell<bool>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> SingleShotGuardAlarm<'a, A> {
+    pub fn new(alarm: &'a A) -> SingleShotGuardAlarm<'a, A> {
+        SingleShotGuardAlarm {
+            alarm,
+            outstanding: Cell::new(false),
+            violations: Cell::new(0),
+            strict: Cell::new(true),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Number of calls to `set_alarm` which replaced an outstanding
+    /// deadline.
+    pub fn violations(&self) -> u32 {
+        self.violations.get()
+    }
+
+    /// Whether a violation also fails a `debug_assert!`. Enabled
+    /// initially; disable it to only count violations.
+    pub fn set_strict(&self, strict: bool) {
+        self.strict.set(strict);
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for SingleShotGuardAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for SingleShotGuardAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        if self.outstanding.get() {
+            self.violations.set(self.violations.get().saturating_add(1));
+            debug_assert!(
+                !self.strict.get(),
+                "alarm set while a previous deadline is outstanding"
+            );
+        }
+        self.outstanding.set(true);
+        self.alarm.set_alarm(reference, dt);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.alarm.get_alarm()
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()?;
+        self.outstanding.set(false);
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for SingleShotGuardAlarm<'a, A> {
+    fn alarm(&self) {
+        self.outstanding.set(false);
+        self.client.map(|client| client.alarm());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    /// Re-arms from its callback until it has fired `limit` times.
+    struct Periodic<'a> {
+        alarm: &'a SingleShotGuardAlarm<'a, MockAlarm<'a>>,
+        fired: Cell<usize>,
+        limit: usize,
+    }
+
+    impl AlarmClient for Periodic<'_> {
+        fn alarm(&self) {
+            self.fired.set(self.fired.get() + 1);
+            if self.fired.get() < self.limit {
+                self.alarm.set_alarm(self.alarm.now(), Ticks32::from(10));
+            }
+        }
+    }
+
+    #[test]
+    fn test_correct_use_is_quiet() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let guard = SingleShotGuardAlarm::new(&alarm);
+        alarm.set_alarm_client(&guard);
+        let client = Periodic {
+            alarm: &guard,
+            fired: Cell::new(0),
+            limit: 3,
+        };
+        guard.set_alarm_client(&client);
+
+        guard.set_alarm(Ticks32::from(0), Ticks32::from(10));
+        assert_eq!(alarm.run_for(100), 3);
+
+        // Replacing a deadline after disarming it is fine too.
+        guard.set_alarm(guard.now(), Ticks32::from(10));
+        guard.disarm().unwrap();
+        guard.set_alarm(guard.now(), Ticks32::from(20));
+        assert_eq!(guard.violations(), 0);
+    }
+
+    #[test]
+    fn test_counts_replaced_deadlines() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let guard = SingleShotGuardAlarm::new(&alarm);
+        guard.set_strict(false);
+        alarm.set_alarm_client(&guard);
+
+        guard.set_alarm(Ticks32::from(0), Ticks32::from(10));
+        guard.set_alarm(Ticks32::from(0), Ticks32::from(20));
+        assert_eq!(guard.violations(), 1);
+        assert_eq!(guard.get_alarm(), Ticks32::from(20));
+
+        alarm.fire();
+        guard.set_alarm(guard.now(), Ticks32::from(20));
+        assert_eq!(guard.violations(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "previous deadline is outstanding")]
+    fn test_strict() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let guard = SingleShotGuardAlarm::new(&alarm);
+        guard.set_alarm(Ticks32::from(0), Ticks32::from(10));
+        guard.set_alarm(Ticks32::from(0), Ticks32::from(20));
+    }
+}
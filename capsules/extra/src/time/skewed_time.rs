@@ -0,0 +1,99 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! `Time` corrected by a signed offset, for clock synchronization.
+//!
+//! Nodes taking part in a time synchronization protocol need to present
+//! a corrected clock to the layers above them without touching the
+//! hardware counter. A `SkewedTime` reports the `now()` of the underlying
+//! time source plus an offset in ticks, which the protocol updates as it
+//! learns the skew to the reference clock. The result wraps at the width
+//! of the tick type like the underlying value does.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Ticks, Time};
+
+/// `Time` reporting `time.now()` shifted by a signed offset.
+pub struct SkewedTime<'a, T: Time> {
+    time: &'a T,
+    /// Ticks added to the underlying time.
+    offset: Cell<i64>,
+}
+
+impl<'a, T: Time> SkewedTime<'a, T> {
+    pub fn new(time: &'a T) -> SkewedTime<'a, T> {
+        SkewedTime {
+            time,
+            offset: Cell::new(0),
+        }
+    }
+
+    /// Replace the offset applied to the underlying time.
+    pub fn set_offset(&self, offset: i64) {
+        self.offset.set(offset);
+    }
+
+    /// Add `delta` to the current offset.
+    pub fn adjust_offset(&self, delta: i64) {
+        self.offset.set(self.offset.get().wrapping_add(delta));
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.get()
+    }
+}
+
+impl<'a, T: Time> Time for SkewedTime<'a, T> {
+    type Frequency = T::Frequency;
+    type Ticks = T::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        // A negative offset in two's complement is the same as adding it
+        // modulo 2^64, and so modulo the (power of two) tick width too.
+        let offset = self.offset.get() as u64 & Self::Ticks::max_value().into_u64();
+        self.time
+            .now()
+            .wrapping_add(Self::Ticks::from_or_max(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::{Ticks16, Ticks32};
+
+    #[test]
+    fn test_offset() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let skewed = SkewedTime::new(&alarm);
+        alarm.set_now(1000);
+        assert_eq!(skewed.now(), Ticks32::from(1000u32));
+
+        skewed.set_offset(250);
+        assert_eq!(skewed.now(), Ticks32::from(1250u32));
+        skewed.adjust_offset(-500);
+        assert_eq!(skewed.offset(), -250);
+        assert_eq!(skewed.now(), Ticks32::from(750u32));
+    }
+
+    #[test]
+    fn test_offset_wraps() {
+        let alarm = MockAlarm::<Ticks16>::new();
+        let skewed = SkewedTime::new(&alarm);
+
+        alarm.set_now(0xFFF0);
+        skewed.set_offset(0x20);
+        assert_eq!(skewed.now(), Ticks16::from(0x10u16));
+
+        alarm.set_now(0x10);
+        skewed.set_offset(-0x20);
+        assert_eq!(skewed.now(), Ticks16::from(0xFFF0u16));
+
+        // Offsets larger than the tick width wrap too.
+        skewed.set_offset(0x1_0001);
+        assert_eq!(skewed.now(), Ticks16::from(0x11u16));
+    }
+}
@@ -0,0 +1,143 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Counter which keeps its logical time across deep sleep.
+//!
+//! On some chips the hardware counter stops, or is reset, while the chip
+//! is in deep sleep. A `SleepAwareCounter` adds an offset to the value of
+//! the underlying counter. Before sleeping, the power manager saves the
+//! logical time with `checkpoint`. After waking, it calls `restore` with
+//! that value and the time it estimates it slept for, e.g. from a
+//! low-power RTC or a wakeup timer. The offset is then adjusted so that
+//! `now` continues from the checkpoint plus the sleep duration.
+//!
+//! The logical time is only as accurate as the sleep estimate: any error
+//! in `slept` becomes a permanent offset from the real time, and errors
+//! of successive sleeps add up. Time which passes between `checkpoint`
+//! and the counter stopping, or between it restarting and `restore`, is
+//! also lost unless included in `slept`.
+//!
+//! Overflow callbacks are forwarded from the underlying counter, so once
+//! an offset has been applied they no longer coincide with the logical
+//! value wrapping to 0.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Counter, OverflowClient, Ticks, Time};
+use kernel::ErrorCode;
+
+/// `Counter` which adds a sleep-compensating offset to `counter`.
+pub struct SleepAwareCounter<'a, C: Counter<'a>> {
+    counter: &'a C,
+    offset: Cell<C::Ticks>,
+}
+
+impl<'a, C: Counter<'a>> SleepAwareCounter<'a, C> {
+    pub fn new(counter: &'a C) -> SleepAwareCounter<'a, C> {
+        SleepAwareCounter {
+            counter,
+            offset: Cell::new(C::Ticks::from(0)),
+        }
+    }
+
+    /// Returns the logical time, to be passed to `restore` after waking.
+    pub fn checkpoint(&self) -> C::Ticks {
+        self.now()
+    }
+
+    /// Moves the logical time to `saved + slept`, where `saved` was
+    /// returned by `checkpoint` before sleeping and `slept` is the
+    /// estimated time spent asleep. Returns `Err(ErrorCode::OFF)`, leaving
+    /// the logical time unchanged, if the underlying counter is not
+    /// running (again) yet.
+    pub fn restore(&self, saved: C::Ticks, slept: C::Ticks) -> Result<(), ErrorCode> {
+        if !self.counter.is_running() {
+            return Err(ErrorCode::OFF);
+        }
+        let logical = saved.wrapping_add(slept);
+        self.offset.set(logical.wrapping_sub(self.counter.now()));
+        Ok(())
+    }
+}
+
+impl<'a, C: Counter<'a>> Time for SleepAwareCounter<'a, C> {
+    type Frequency = C::Frequency;
+    type Ticks = C::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.counter.now().wrapping_add(self.offset.get())
+    }
+}
+
+impl<'a, C: Counter<'a>> Counter<'a> for SleepAwareCounter<'a, C> {
+    fn set_overflow_client(&self, client: &'a dyn OverflowClient) {
+        self.counter.set_overflow_client(client);
+    }
+
+    fn clear_overflow_client(&self) {
+        self.counter.clear_overflow_client();
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        self.counter.start()
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        self.counter.stop()
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        self.counter.reset()?;
+        self.offset.set(C::Ticks::from(0));
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.counter.is_running()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockCounter;
+    use kernel::hil::time::Ticks32;
+
+    #[test]
+    fn test_continuity_across_sleep() {
+        let counter = MockCounter::<Ticks32>::new();
+        let sleepy = SleepAwareCounter::new(&counter);
+        sleepy.start().unwrap();
+        counter.set_now(5_000);
+        assert_eq!(sleepy.now().into_u32(), 5_000);
+
+        // The hardware counter is stopped and reset in deep sleep.
+        let saved = sleepy.checkpoint();
+        sleepy.stop().unwrap();
+        counter.reset().unwrap();
+        assert_eq!(
+            sleepy.restore(saved, Ticks32::from(20_000)),
+            Err(ErrorCode::OFF)
+        );
+
+        // Woke up after 20000 ticks; the counter restarts from 0.
+        sleepy.start().unwrap();
+        counter.advance(10);
+        assert_eq!(sleepy.restore(saved, Ticks32::from(20_000)), Ok(()));
+        assert_eq!(sleepy.now().into_u32(), 25_000);
+        counter.advance(100);
+        assert_eq!(sleepy.now().into_u32(), 25_100);
+
+        // A second sleep, over which the logical time wraps.
+        let saved = sleepy.checkpoint();
+        counter.set_now(7);
+        sleepy.restore(saved, Ticks32::from(u32::MAX)).unwrap();
+        assert_eq!(sleepy.now().into_u32(), 25_099);
+        counter.advance(1);
+        assert_eq!(sleepy.now().into_u32(), 25_100);
+
+        sleepy.reset().unwrap();
+        assert_eq!(sleepy.now().into_u32(), 0);
+    }
+}
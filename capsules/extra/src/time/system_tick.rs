@@ -0,0 +1,98 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Millisecond tick on top of a `Time` of any frequency.
+//!
+//! Portable capsules which only need coarse timing are simpler to write
+//! against a fixed tick rate than against each board's counter
+//! frequency. A `SystemTick` presents `Time` at 1kHz with `Ticks32`. It
+//! accumulates the ticks of the underlying time source elapsed between
+//! calls to `now`, so that the underlying source wrapping does not make
+//! the millisecond count jump, and converts the total into milliseconds.
+//! The result never decreases, apart from wrapping at the width of
+//! `Ticks32` (about 49.7 days).
+//!
+//! The conversion rounds down, so a millisecond is only counted once all
+//! of its underlying ticks have elapsed, and the millisecond count is
+//! never ahead of real time. Underlying ticks are never lost, so the
+//! rounding error does not add up over time. `now` must be called at
+//! least once per wrap period of the underlying source, otherwise whole
+//! periods are missed.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Freq1KHz, Frequency, Ticks, Ticks32, Time};
+
+/// 1kHz `Time` derived from `time`.
+pub struct SystemTick<'a, T: Time> {
+    time: &'a T,
+    /// Underlying value at the last call to `now`.
+    last: Cell<T::Ticks>,
+    /// Underlying ticks elapsed up to `last`, counting from 0.
+    elapsed: Cell<u64>,
+}
+
+impl<'a, T: Time> SystemTick<'a, T> {
+    pub fn new(time: &'a T) -> SystemTick<'a, T> {
+        SystemTick {
+            time,
+            last: Cell::new(T::Ticks::from(0)),
+            elapsed: Cell::new(0),
+        }
+    }
+}
+
+impl<'a, T: Time> Time for SystemTick<'a, T> {
+    type Frequency = Freq1KHz;
+    type Ticks = Ticks32;
+
+    fn now(&self) -> Ticks32 {
+        let now = self.time.now();
+        let delta = now.wrapping_sub(self.last.get()).into_u64();
+        let elapsed = self.elapsed.get().wrapping_add(delta);
+        self.last.set(now);
+        self.elapsed.set(elapsed);
+        let ms = elapsed as u128 * 1_000 / T::Frequency::frequency() as u128;
+        // Truncating wraps the count at the width of `Ticks32`.
+        Ticks32::from(ms as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockCounter;
+    use kernel::hil::time::{Freq16MHz, Freq32KHz, Ticks16};
+
+    #[test]
+    fn test_one_tick_per_millisecond() {
+        let counter = MockCounter::<Ticks32, Freq16MHz>::new();
+        let tick = SystemTick::new(&counter);
+        assert_eq!(tick.now().into_u32(), 0);
+        counter.advance(15_999);
+        assert_eq!(tick.now().into_u32(), 0);
+        counter.advance(1);
+        assert_eq!(tick.now().into_u32(), 1);
+        for ms in 2..100 {
+            counter.advance(16_000);
+            assert_eq!(tick.now().into_u32(), ms);
+        }
+    }
+
+    #[test]
+    fn test_continues_across_underlying_wrap() {
+        // A 16-bit counter at 32768Hz wraps every 2 seconds.
+        let counter = MockCounter::<Ticks16, Freq32KHz>::new();
+        let tick = SystemTick::new(&counter);
+        let mut previous = 0;
+        for _ in 0..100 {
+            counter.advance(1_000);
+            let now = tick.now().into_u32();
+            assert!(now >= previous);
+            previous = now;
+        }
+        // 100000 ticks at 32768Hz is 3051.7ms.
+        assert_eq!(previous, 3_051);
+    }
+}
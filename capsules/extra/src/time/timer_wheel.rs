@@ -0,0 +1,343 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Fixed number of independent software timers on a single alarm.
+//!
+//! A `TimerWheel` holds up to `SLOTS` one-shot or repeating entries, each
+//! with its own `TimerClient`, and keeps the underlying alarm programmed
+//! for the soonest of them. Unlike `MuxTimer` and `VirtualTimer`, entries
+//! are not separate objects which need to be allocated and linked in:
+//! they are added and removed at runtime by index, which suits subsystems
+//! that need a handful of short-lived timers.
+//!
+//! Repeating entries are re-armed from their previous deadline, so they
+//! do not drift. If an entry is late by one or more whole intervals, the
+//! missed intervals are skipped rather than delivered as extra callbacks.
+//!
+//! The `TimerWheel` must be installed as the client of the underlying
+//! alarm.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let wheel = static_init!(
+//!     TimerWheel<'static, VirtualMuxAlarm<'static, nrf52::rtc::Rtc>, 4>,
+//!     TimerWheel::new(virtual_alarm)
+//! );
+//! virtual_alarm.set_alarm_client(wheel);
+//! let id = wheel.add(client, 1000u32.into(), true)?;
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time, TimerClient};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Disabled,
+    OneShot,
+    Repeating,
+}
+
+struct Entry<'a, T: Ticks> {
+    /// Start of the current interval; the entry expires at
+    /// `reference + interval`.
+    reference: Cell<T>,
+    interval: Cell<T>,
+    mode: Cell<Mode>,
+    client: OptionalCell<&'a dyn TimerClient>,
+}
+
+impl<T: Ticks> Entry<'_, T> {
+    fn deadline(&self) -> T {
+        self.reference.get().wrapping_add(self.interval.get())
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.mode.get() != Mode::Disabled
+    }
+
+    fn has_expired(&self, now: T) -> bool {
+        !now.within_range(self.reference.get(), self.deadline())
+    }
+}
+
+/// Up to `SLOTS` independent timers multiplexed over `alarm`.
+pub struct TimerWheel<'a, A: Alarm<'a>, const SLOTS: usize> {
+    alarm: &'a A,
+    entries: [Entry<'a, A::Ticks>; SLOTS],
+}
+
+impl<'a, A: Alarm<'a>, const SLOTS: usize> TimerWheel<'a, A, SLOTS> {
+    pub fn new(alarm: &'a A) -> TimerWheel<'a, A, SLOTS> {
+        TimerWheel {
+            alarm,
+            entries: core::array::from_fn(|_| Entry {
+                reference: Cell::new(A::Ticks::from(0)),
+                interval: Cell::new(A::Ticks::from(0)),
+                mode: Cell::new(Mode::Disabled),
+                client: OptionalCell::empty(),
+            }),
+        }
+    }
+
+    /// Start a timer which calls `client` after `interval` ticks, and
+    /// then every `interval` ticks if `repeating` is true. Returns the
+    /// index identifying the entry, `Err(ErrorCode::NOMEM)` if all
+    /// `SLOTS` entries are in use, or `Err(ErrorCode::INVAL)` for a
+    /// repeating timer with an `interval` of 0, which would expire again
+    /// immediately every time.
+    pub fn add(
+        &self,
+        client: &'a dyn TimerClient,
+        interval: A::Ticks,
+        repeating: bool,
+    ) -> Result<usize, ErrorCode> {
+        if repeating && interval.into_u64() == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        let (id, entry) = self
+            .entries
+            .iter()
+            .enumerate()
+            .find(|(_, entry)| !entry.is_enabled())
+            .ok_or(ErrorCode::NOMEM)?;
+        entry.reference.set(self.alarm.now());
+        entry.interval.set(interval);
+        entry.mode.set(if repeating {
+            Mode::Repeating
+        } else {
+            Mode::OneShot
+        });
+        entry.client.set(client);
+        self.schedule();
+        Ok(id)
+    }
+
+    /// Stop the timer with index `id`. Returns `Err(ErrorCode::INVAL)` if
+    /// there is no such timer, e.g. because it was a one-shot timer which
+    /// already fired.
+    pub fn remove(&self, id: usize) -> Result<(), ErrorCode> {
+        let entry = self
+            .entries
+            .get(id)
+            .filter(|entry| entry.is_enabled())
+            .ok_or(ErrorCode::INVAL)?;
+        entry.mode.set(Mode::Disabled);
+        entry.client.clear();
+        self.schedule();
+        Ok(())
+    }
+
+    /// Return how many ticks are remaining until the next callback of the
+    /// timer with index `id`, or `None` if there is no such timer.
+    pub fn time_remaining(&self, id: usize) -> Option<A::Ticks> {
+        let entry = self.entries.get(id).filter(|entry| entry.is_enabled())?;
+        let now = self.alarm.now();
+        Some(if entry.has_expired(now) {
+            A::Ticks::from(0)
+        } else {
+            entry.deadline().wrapping_sub(now)
+        })
+    }
+
+    /// Program the underlying alarm for the soonest enabled entry, or
+    /// disarm it if there is none.
+    fn schedule(&self) {
+        let now = self.alarm.now();
+        let next = self
+            .entries
+            .iter()
+            .filter(|entry| entry.is_enabled())
+            .min_by_key(|entry| {
+                if entry.has_expired(now) {
+                    A::Ticks::from(0)
+                } else {
+                    entry.deadline().wrapping_sub(now)
+                }
+            });
+        match next {
+            Some(entry) => self
+                .alarm
+                .set_alarm(entry.reference.get(), entry.interval.get()),
+            None => {
+                let _ = self.alarm.disarm();
+            }
+        }
+    }
+
+    /// Disable or re-arm an expired entry, before its client is called.
+    fn expire(&self, entry: &Entry<'a, A::Ticks>, now: A::Ticks) {
+        if entry.mode.get() == Mode::OneShot {
+            entry.mode.set(Mode::Disabled);
+            return;
+        }
+        // Skip whole intervals which were missed because of latency, so
+        // that the next deadline is after `now`.
+        // `add` rejects repeating entries with an interval of 0.
+        let deadline = entry.deadline();
+        let interval = entry.interval.get().into_u64();
+        let skipped = now.wrapping_sub(deadline).into_u64() / interval * interval;
+        entry
+            .reference
+            .set(deadline.wrapping_add(A::Ticks::from_or_max(skipped)));
+    }
+}
+
+impl<'a, A: Alarm<'a>, const SLOTS: usize> Time for TimerWheel<'a, A, SLOTS> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>, const SLOTS: usize> AlarmClient for TimerWheel<'a, A, SLOTS> {
+    fn alarm(&self) {
+        let now = self.alarm.now();
+        // Fire expired entries in order of their deadlines, i.e. the most
+        // overdue first. Each one is disabled or, having a nonzero
+        // interval, moved past `now` before its client is called, so the
+        // loop terminates.
+        while let Some(entry) = self
+            .entries
+            .iter()
+            .filter(|entry| entry.is_enabled() && entry.has_expired(now))
+            .max_by_key(|entry| now.wrapping_sub(entry.deadline()))
+        {
+            let client = entry.client.get();
+            self.expire(entry, now);
+            client.map(|client| client.timer());
+        }
+        self.schedule();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    /// Records the order in which timers fire.
+    struct Recorder<'a> {
+        sequence: &'a Cell<usize>,
+        fired: Cell<[usize; 4]>,
+        count: Cell<usize>,
+    }
+
+    impl<'a> Recorder<'a> {
+        fn new(sequence: &'a Cell<usize>) -> Self {
+            Recorder {
+                sequence,
+                fired: Cell::new([0; 4]),
+                count: Cell::new(0),
+            }
+        }
+    }
+
+    impl TimerClient for Recorder<'_> {
+        fn timer(&self) {
+            let mut fired = self.fired.get();
+            fired[self.count.get()] = self.sequence.get();
+            self.fired.set(fired);
+            self.count.set(self.count.get() + 1);
+            self.sequence.set(self.sequence.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_staggered_oneshots() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let wheel = TimerWheel::<_, 3>::new(&alarm);
+        alarm.set_alarm_client(&wheel);
+        let sequence = Cell::new(0);
+        let a = Recorder::new(&sequence);
+        let b = Recorder::new(&sequence);
+        let c = Recorder::new(&sequence);
+
+        assert_eq!(wheel.add(&a, 300u32.into(), false), Ok(0));
+        assert_eq!(wheel.add(&b, 100u32.into(), false), Ok(1));
+        alarm.advance(50);
+        assert_eq!(wheel.add(&c, 150u32.into(), false), Ok(2));
+        assert_eq!(wheel.add(&c, 1u32.into(), false), Err(ErrorCode::NOMEM));
+        assert_eq!(alarm.get_alarm(), Ticks32::from(100u32));
+        assert_eq!(wheel.time_remaining(2), Some(Ticks32::from(150u32)));
+
+        assert_eq!(alarm.run_for(1000), 3);
+        assert_eq!(b.fired.get()[0], 0);
+        assert_eq!(c.fired.get()[0], 1);
+        assert_eq!(a.fired.get()[0], 2);
+        assert_eq!(sequence.get(), 3);
+        assert_eq!(wheel.time_remaining(0), None);
+        assert!(!alarm.is_armed());
+    }
+
+    #[test]
+    fn test_late_fires_in_deadline_order() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let wheel = TimerWheel::<_, 2>::new(&alarm);
+        alarm.set_alarm_client(&wheel);
+        let sequence = Cell::new(0);
+        let a = Recorder::new(&sequence);
+        let b = Recorder::new(&sequence);
+
+        wheel.add(&a, 200u32.into(), false).unwrap();
+        wheel.add(&b, 100u32.into(), false).unwrap();
+        alarm.fire_late(150);
+        assert_eq!(b.fired.get()[0], 0);
+        assert_eq!(a.fired.get()[0], 1);
+    }
+
+    #[test]
+    fn test_repeating() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let wheel = TimerWheel::<_, 2>::new(&alarm);
+        alarm.set_alarm_client(&wheel);
+        let sequence = Cell::new(0);
+        let fast = Recorder::new(&sequence);
+        let slow = Recorder::new(&sequence);
+
+        let fast_id = wheel.add(&fast, 100u32.into(), true).unwrap();
+        wheel.add(&slow, 250u32.into(), false).unwrap();
+
+        assert_eq!(alarm.run_for(350), 4);
+        assert_eq!(fast.fired.get()[..3], [0, 1, 3]);
+        assert_eq!(slow.fired.get()[0], 2);
+        assert_eq!(wheel.time_remaining(fast_id), Some(Ticks32::from(50u32)));
+
+        // Missed intervals are skipped without drifting.
+        alarm.fire_late(230);
+        assert_eq!(fast.count.get(), 4);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(700u32));
+
+        assert_eq!(wheel.remove(fast_id), Ok(()));
+        assert_eq!(wheel.remove(fast_id), Err(ErrorCode::INVAL));
+        assert_eq!(alarm.run_for(1000), 0);
+        assert_eq!(fast.count.get(), 4);
+    }
+
+    #[test]
+    fn test_zero_interval() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let wheel = TimerWheel::<_, 2>::new(&alarm);
+        alarm.set_alarm_client(&wheel);
+        let sequence = Cell::new(0);
+        let a = Recorder::new(&sequence);
+
+        // A repeating timer would expire forever within one callback.
+        assert_eq!(wheel.add(&a, 0u32.into(), true), Err(ErrorCode::INVAL));
+        assert!(!alarm.is_armed());
+
+        // A one-shot timer fires once.
+        assert_eq!(wheel.add(&a, 0u32.into(), false), Ok(0));
+        assert_eq!(alarm.run_for(100), 1);
+        assert_eq!(a.count.get(), 1);
+        assert!(!alarm.is_armed());
+    }
+}
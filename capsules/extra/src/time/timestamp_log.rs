@@ -0,0 +1,137 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Fixed-size log of recent timestamped events.
+//!
+//! Event tracing often needs the last few events together with when they
+//! happened. A `RingTimestampLog` keeps the last `N` `(id, timestamp)`
+//! pairs. `dump` visits them in chronological order. The order comes
+//! from comparing timestamps with `Ticks::cmp_wrapping`, so it is
+//! correct across counter wraps and for events pushed slightly out of
+//! order, e.g. from an interrupt handler.
+//!
+//! The comparison is only meaningful if all logged timestamps lie within
+//! half a counter period of each other. If events older than that are
+//! still in the log, they alias and may be reported in the wrong order.
+//! Logs on fast counters should be small, or be cleared periodically.
+
+use core::cell::Cell;
+use core::cmp::Ordering;
+
+use kernel::hil::time::Ticks;
+
+/// The last `N` events, each an id with the timestamp it was logged at.
+pub struct RingTimestampLog<T: Ticks, const N: usize> {
+    entries: [Cell<Option<(u32, T)>>; N],
+    /// Index of the slot the next event is written to.
+    next: Cell<usize>,
+}
+
+impl<T: Ticks, const N: usize> RingTimestampLog<T, N> {
+    pub fn new() -> RingTimestampLog<T, N> {
+        RingTimestampLog {
+            entries: core::array::from_fn(|_| Cell::new(None)),
+            next: Cell::new(0),
+        }
+    }
+
+    /// Log event `id` at time `now`, replacing the oldest entry if the
+    /// log is full.
+    pub fn push(&self, id: u32, now: T) {
+        if N == 0 {
+            return;
+        }
+        let next = self.next.get();
+        self.entries[next].set(Some((id, now)));
+        self.next.set((next + 1) % N);
+    }
+
+    /// Number of events in the log.
+    pub fn len(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.get().is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Call `f` with the id and timestamp of each logged event, oldest
+    /// first. Events with equal timestamps are visited in the order
+    /// they were pushed.
+    pub fn dump(&self, mut f: impl FnMut(u32, T)) {
+        let mut sorted: [Option<(u32, T)>; N] = core::array::from_fn(|_| None);
+        let next = self.next.get();
+        // Insertion sort, starting from the oldest slot: it is stable, so
+        // events with equal timestamps keep their push order.
+        let entries = self.entries[next..]
+            .iter()
+            .chain(self.entries[..next].iter());
+        for (len, (id, t)) in entries.filter_map(|entry| entry.get()).enumerate() {
+            let mut i = len;
+            while i > 0
+                && sorted[i - 1]
+                    .map_or(false, |(_, prev)| prev.cmp_wrapping(t) == Ordering::Greater)
+            {
+                sorted[i] = sorted[i - 1];
+                i -= 1;
+            }
+            sorted[i] = Some((id, t));
+        }
+        sorted.iter().flatten().for_each(|&(id, t)| f(id, t));
+    }
+
+    /// Discard all logged events.
+    pub fn clear(&self) {
+        self.entries.iter().for_each(|entry| entry.set(None));
+        self.next.set(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::hil::time::Ticks16;
+
+    fn ids<const N: usize>(log: &RingTimestampLog<Ticks16, N>) -> ([u32; 8], usize) {
+        let mut ids = [0; 8];
+        let mut len = 0;
+        log.dump(|id, _| {
+            ids[len] = id;
+            len += 1;
+        });
+        (ids, len)
+    }
+
+    #[test]
+    fn test_across_wrap() {
+        let log = RingTimestampLog::<Ticks16, 4>::new();
+        log.push(1, 0xFFE0u16.into());
+        log.push(2, 0xFFF0u16.into());
+        log.push(3, 0x0010u16.into());
+        assert_eq!(ids(&log), ([1, 2, 3, 0, 0, 0, 0, 0], 3));
+
+        // Overwrites the oldest entry.
+        log.push(4, 0x0020u16.into());
+        log.push(5, 0x0030u16.into());
+        assert_eq!(log.len(), 4);
+        assert_eq!(ids(&log).0[..4], [2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_out_of_order_push() {
+        let log = RingTimestampLog::<Ticks16, 4>::new();
+        log.push(2, 0x0005u16.into());
+        // Timestamped before the previous event, but pushed after it.
+        log.push(1, 0xFFFEu16.into());
+        log.push(3, 0x0005u16.into());
+        assert_eq!(ids(&log), ([1, 2, 3, 0, 0, 0, 0, 0], 3));
+
+        log.clear();
+        assert!(log.is_empty());
+        assert_eq!(ids(&log).1, 0);
+    }
+}
@@ -0,0 +1,282 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! `Time` and `Alarm` wrappers which record a trace of their use.
+//!
+//! Debugging timing races is much easier with a trace of when the clock
+//! was read and which deadlines were programmed. `TracingTime` and
+//! `TracingAlarm` forward every call to the wrapped implementation and
+//! record each value returned by `now()` and each `set_alarm(reference,
+//! dt)` call in a ring buffer holding the last `N` events. The trace can
+//! be read with `dump`, e.g. from a panic handler or a process console
+//! command.
+//!
+//! Boards opt into tracing by inserting a wrapper between a time source
+//! and the module being debugged, so there is no cost when the wrappers
+//! are not used and no cargo feature is needed. Recording can also be
+//! paused at runtime with `set_enabled`.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let tracing_alarm = static_init!(
+//!     TracingAlarm<'static, VirtualMuxAlarm<'static, nrf52::rtc::Rtc>, 16>,
+//!     TracingAlarm::new(virtual_alarm)
+//! );
+//! // ... give `tracing_alarm` to the capsule instead of `virtual_alarm`.
+//! tracing_alarm.dump(|event| debug!("{:?}", event));
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::ErrorCode;
+
+/// A recorded call to a traced time source.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TraceEvent<T: Ticks> {
+    /// `now()` returned this value.
+    Now(T),
+    /// `set_alarm` was called with these arguments.
+    SetAlarm { reference: T, dt: T },
+}
+
+/// Ring buffer of the last `N` events.
+struct TraceBuffer<T: Ticks, const N: usize> {
+    events: [Cell<Option<TraceEvent<T>>>; N],
+    /// Index of the slot the next event is written to.
+    next: Cell<usize>,
+    enabled: Cell<bool>,
+}
+
+impl<T: Ticks, const N: usize> TraceBuffer<T, N> {
+    fn new() -> Self {
+        TraceBuffer {
+            events: core::array::from_fn(|_| Cell::new(None)),
+            next: Cell::new(0),
+            enabled: Cell::new(true),
+        }
+    }
+
+    fn record(&self, event: TraceEvent<T>) {
+        if !self.enabled.get() || N == 0 {
+            return;
+        }
+        let next = self.next.get();
+        self.events[next].set(Some(event));
+        self.next.set((next + 1) % N);
+    }
+
+    fn dump(&self, mut f: impl FnMut(TraceEvent<T>)) {
+        let next = self.next.get();
+        // Starting at the slot to be overwritten next visits the oldest
+        // event first.
+        self.events[next..]
+            .iter()
+            .chain(self.events[..next].iter())
+            .filter_map(|event| event.get())
+            .for_each(|event| f(event));
+    }
+
+    fn clear(&self) {
+        self.events.iter().for_each(|event| event.set(None));
+        self.next.set(0);
+    }
+}
+
+/// `Time` which records the last `N` values returned by `time.now()`.
+pub struct TracingTime<'a, T: Time, const N: usize> {
+    time: &'a T,
+    trace: TraceBuffer<T::Ticks, N>,
+}
+
+impl<'a, T: Time, const N: usize> TracingTime<'a, T, N> {
+    pub fn new(time: &'a T) -> TracingTime<'a, T, N> {
+        TracingTime {
+            time,
+            trace: TraceBuffer::new(),
+        }
+    }
+
+    /// Pause or resume recording. Recording is enabled initially.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.trace.enabled.set(enabled);
+    }
+
+    /// Call `f` with each recorded event, oldest first.
+    pub fn dump(&self, f: impl FnMut(TraceEvent<T::Ticks>)) {
+        self.trace.dump(f);
+    }
+
+    /// Discard all recorded events.
+    pub fn clear(&self) {
+        self.trace.clear();
+    }
+}
+
+impl<'a, T: Time, const N: usize> Time for TracingTime<'a, T, N> {
+    type Frequency = T::Frequency;
+    type Ticks = T::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        let now = self.time.now();
+        self.trace.record(TraceEvent::Now(now));
+        now
+    }
+}
+
+/// `Alarm` which records the last `N` calls to `alarm.now()` and
+/// `alarm.set_alarm()`.
+pub struct TracingAlarm<'a, A: Alarm<'a>, const N: usize> {
+    alarm: &'a A,
+    trace: TraceBuffer<A::Ticks, N>,
+}
+
+impl<'a, A: Alarm<'a>, const N: usize> TracingAlarm<'a, A, N> {
+    pub fn new(alarm: &'a A) -> TracingAlarm<'a, A, N> {
+        TracingAlarm {
+            alarm,
+            trace: TraceBuffer::new(),
+        }
+    }
+
+    /// Pause or resume recording. Recording is enabled initially.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.trace.enabled.set(enabled);
+    }
+
+    /// Call `f` with each recorded event, oldest first.
+    pub fn dump(&self, f: impl FnMut(TraceEvent<A::Ticks>)) {
+        self.trace.dump(f);
+    }
+
+    /// Discard all recorded events.
+    pub fn clear(&self) {
+        self.trace.clear();
+    }
+}
+
+impl<'a, A: Alarm<'a>, const N: usize> Time for TracingAlarm<'a, A, N> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        let now = self.alarm.now();
+        self.trace.record(TraceEvent::Now(now));
+        now
+    }
+}
+
+impl<'a, A: Alarm<'a>, const N: usize> Alarm<'a> for TracingAlarm<'a, A, N> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.alarm.set_alarm_client(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.alarm.clear_alarm_client();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        self.trace.record(TraceEvent::SetAlarm { reference, dt });
+        self.alarm.set_alarm(reference, dt);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.alarm.get_alarm()
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()
+    }
+
+    fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    fn collect<'a, const N: usize>(
+        alarm: &TracingAlarm<'a, MockAlarm<'a, Ticks32>, N>,
+    ) -> ([Option<TraceEvent<Ticks32>>; 8], usize) {
+        let mut events = [None; 8];
+        let mut len = 0;
+        alarm.dump(|event| {
+            events[len] = Some(event);
+            len += 1;
+        });
+        (events, len)
+    }
+
+    #[test]
+    fn test_alarm_trace() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let tracing = TracingAlarm::<_, 4>::new(&alarm);
+
+        alarm.set_now(100);
+        let now = tracing.now();
+        tracing.set_alarm(now, 50u32.into());
+        alarm.set_now(120);
+        tracing.now();
+
+        let (events, len) = collect(&tracing);
+        assert_eq!(len, 3);
+        assert_eq!(events[0], Some(TraceEvent::Now(100u32.into())));
+        assert_eq!(
+            events[1],
+            Some(TraceEvent::SetAlarm {
+                reference: 100u32.into(),
+                dt: 50u32.into()
+            })
+        );
+        assert_eq!(events[2], Some(TraceEvent::Now(120u32.into())));
+        assert_eq!(alarm.get_alarm(), Ticks32::from(150u32));
+    }
+
+    #[test]
+    fn test_ring_wraps_and_disable() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let tracing = TracingAlarm::<_, 4>::new(&alarm);
+
+        for now in 1..=6 {
+            alarm.set_now(now);
+            tracing.now();
+        }
+        let (events, len) = collect(&tracing);
+        assert_eq!(len, 4);
+        assert_eq!(events[0], Some(TraceEvent::Now(3u32.into())));
+        assert_eq!(events[3], Some(TraceEvent::Now(6u32.into())));
+
+        tracing.set_enabled(false);
+        tracing.now();
+        assert_eq!(collect(&tracing).0, events);
+
+        tracing.clear();
+        assert_eq!(collect(&tracing).1, 0);
+    }
+
+    #[test]
+    fn test_time_trace() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let tracing = TracingTime::<_, 2>::new(&alarm);
+        alarm.set_now(7);
+        assert_eq!(tracing.now(), Ticks32::from(7u32));
+
+        let mut count = 0;
+        tracing.dump(|event| {
+            assert_eq!(event, TraceEvent::Now(7u32.into()));
+            count += 1;
+        });
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,181 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Monotonic 64-bit tick count since boot from a narrower `Counter`.
+//!
+//! Uptime reporting and log timestamps need a tick count which does not
+//! wrap within the lifetime of the device. A 32-bit counter cannot
+//! provide this on its own. `UptimeTime` counts the overflows of the
+//! underlying counter and combines them with its current value into a
+//! `u64`. It also implements `Time` with `Ticks64`.
+//!
+//! The counter may wrap before its overflow callback has been handled.
+//! A reading taken in that window would go backward. `UptimeTime`
+//! detects this by comparing with the previous reading and accounts for
+//! the pending overflow, so the values it returns never decrease. This
+//! requires `elapsed_since_boot` to be called at least once per period
+//! of the underlying counter while an overflow is pending, which is the
+//! case in practice since the callback is handled long before that.
+//!
+//! The `UptimeTime` must be installed as the overflow client of the
+//! underlying counter by calling `setup`. The counter must not be reset
+//! directly: that zeroes its value while keeping the overflow count, so
+//! the uptime would stall until the counter caught up with the last
+//! reading. Use `reset_preserving_overflow` instead.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Counter, OverflowClient, Ticks, Ticks64, Time};
+use kernel::ErrorCode;
+
+/// 64-bit monotonic time built from `counter` and its overflows.
+pub struct UptimeTime<'a, C: Counter<'a>> {
+    counter: &'a C,
+    /// Ticks which elapsed before the last `reset_preserving_overflow`.
+    base: Cell<u64>,
+    overflows: Cell<u64>,
+    /// Largest value returned so far.
+    last: Cell<u64>,
+}
+
+impl<'a, C: Counter<'a>> UptimeTime<'a, C> {
+    pub fn new(counter: &'a C) -> UptimeTime<'a, C> {
+        UptimeTime {
+            counter,
+            base: Cell::new(0),
+            overflows: Cell::new(0),
+            last: Cell::new(0),
+        }
+    }
+
+    /// Install this as the overflow client of the underlying counter.
+    pub fn setup(&'a self) {
+        self.counter.set_overflow_client(self);
+    }
+
+    /// Ticks of the underlying counter since it started, including all
+    /// of its overflows. Never less than the previously returned value.
+    pub fn elapsed_since_boot(&self) -> u64 {
+        let width_max = C::Ticks::max_value().into_u64();
+        let low = self.counter.now().into_u64();
+        if width_max == u64::MAX {
+            // A 64-bit counter will not wrap.
+            return self.base.get() + low;
+        }
+        let period = width_max + 1;
+        let mut elapsed = self.base.get() + self.overflows.get() * period + low;
+        if elapsed < self.last.get() {
+            // The counter wrapped but the overflow callback has not been
+            // handled yet.
+            elapsed += period;
+        }
+        let elapsed = core::cmp::max(elapsed, self.last.get());
+        self.last.set(elapsed);
+        elapsed
+    }
+
+    /// Resets the underlying counter to 0 without a discontinuity in the
+    /// uptime. Unlike calling `reset` on the counter, the ticks elapsed so
+    /// far, including those from overflows, are carried over, and the
+    /// uptime continues from its current value as the counter counts up
+    /// from 0 again. Ticks between reading the counter and resetting it
+    /// are lost.
+    ///
+    /// This must not be called while an overflow callback is pending, as
+    /// the late callback would then count a period twice.
+    pub fn reset_preserving_overflow(&self) -> Result<(), ErrorCode> {
+        let elapsed = self.elapsed_since_boot();
+        self.counter.reset()?;
+        self.base.set(elapsed);
+        self.overflows.set(0);
+        Ok(())
+    }
+}
+
+impl<'a, C: Counter<'a>> Time for UptimeTime<'a, C> {
+    type Frequency = C::Frequency;
+    type Ticks = Ticks64;
+
+    fn now(&self) -> Ticks64 {
+        Ticks64::from(self.elapsed_since_boot())
+    }
+}
+
+impl<'a, C: Counter<'a>> OverflowClient for UptimeTime<'a, C> {
+    fn overflow(&self) {
+        self.overflows.set(self.overflows.get() + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockCounter;
+    use kernel::hil::time::Ticks16;
+
+    const PERIOD: u64 = 0x1_0000;
+
+    #[test]
+    fn test_multiple_overflows() {
+        let counter = MockCounter::<Ticks16>::new();
+        let uptime = UptimeTime::new(&counter);
+        uptime.setup();
+
+        let mut previous = 0;
+        for _ in 0..10 {
+            counter.advance(0x7000);
+            let elapsed = uptime.elapsed_since_boot();
+            assert!(elapsed > previous);
+            previous = elapsed;
+        }
+        assert_eq!(previous, 10 * 0x7000);
+
+        counter.advance(5 * PERIOD);
+        assert_eq!(uptime.now().into_u64(), 10 * 0x7000 + 5 * PERIOD);
+    }
+
+    #[test]
+    fn test_pending_overflow() {
+        let counter = MockCounter::<Ticks16>::new();
+        let uptime = UptimeTime::new(&counter);
+        uptime.setup();
+
+        counter.set_now(0xFFF0);
+        assert_eq!(uptime.elapsed_since_boot(), 0xFFF0);
+
+        // The counter wraps, but its overflow callback is delayed.
+        counter.set_now(0x10);
+        assert_eq!(uptime.elapsed_since_boot(), PERIOD + 0x10);
+        counter.set_now(0x20);
+        assert_eq!(uptime.elapsed_since_boot(), PERIOD + 0x20);
+
+        // Once it is delivered the value stays consistent.
+        uptime.overflow();
+        assert_eq!(uptime.elapsed_since_boot(), PERIOD + 0x20);
+    }
+
+    #[test]
+    fn test_reset_preserving_overflow() {
+        let counter = MockCounter::<Ticks16>::new();
+        let uptime = UptimeTime::new(&counter);
+        uptime.setup();
+
+        counter.advance(3 * PERIOD + 0x1234);
+        let before = uptime.elapsed_since_boot();
+        assert_eq!(before, 3 * PERIOD + 0x1234);
+
+        uptime.reset_preserving_overflow().unwrap();
+        assert_eq!(counter.now().into_u32(), 0);
+        assert_eq!(uptime.elapsed_since_boot(), before);
+
+        let mut previous = before;
+        for _ in 0..10 {
+            counter.advance(0x7000);
+            let elapsed = uptime.elapsed_since_boot();
+            assert!(elapsed > previous);
+            previous = elapsed;
+        }
+        assert_eq!(previous, before + 10 * 0x7000);
+    }
+}
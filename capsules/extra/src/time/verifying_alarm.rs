@@ -0,0 +1,153 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Alarm wrapper which checks that the underlying alarm follows the
+//! `AlarmClient` contract.
+//!
+//! An `Alarm` MUST be disarmed when it calls `AlarmClient::alarm`, and
+//! must not call it before its deadline; implementations getting this
+//! wrong cause double or early callbacks which are hard to track down. A
+//! `VerifyingAlarm` sits between an alarm and its client and, before
+//! forwarding each callback, checks with `debug_assert!` that:
+//!
+//! - the underlying alarm is no longer armed, and
+//! - `now()` has reached the deadline returned by `get_alarm()`.
+//!
+//! This is a testing aid for alarm implementations: install it in
+//! integration tests or debug builds, the checks compile away in release
+//! builds. The `VerifyingAlarm` must be installed as the client of the
+//! underlying alarm.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Alarm that checks the callbacks of `alarm` before forwarding them.
+pub struct VerifyingAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    /// Reference of the last `set_alarm` call.
+    reference: Cell<A::Ticks>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a, A: Alarm<'a>> VerifyingAlarm<'a, A> {
+    pub fn new(alarm: &'a A) -> VerifyingAlarm<'a, A> {
+        VerifyingAlarm {
+            alarm,
+            reference: Cell::new(A::Ticks::from(0)),
+            client: OptionalCell::empty(),
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for VerifyingAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Alarm<'a> for VerifyingAlarm<'a, A> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
+    fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        self.reference.set(reference);
+        self.alarm.set_alarm(reference, dt);
+    }
+
+    fn get_alarm(&self) -> Self::Ticks {
+        self.alarm.get_alarm()
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.alarm.disarm()
+    }
+
+    fn is_armed(&self) -> bool {
+        self.alarm.is_armed()
+    }
+
+    fn minimum_dt(&self) -> Self::Ticks {
+        self.alarm.minimum_dt()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for VerifyingAlarm<'a, A> {
+    fn alarm(&self) {
+        debug_assert!(
+            !self.alarm.is_armed(),
+            "alarm is still armed when calling its client"
+        );
+        // `get_alarm` rather than the requested deadline, as the alarm may
+        // have moved it later to respect `minimum_dt`.
+        debug_assert!(
+            !self
+                .alarm
+                .now()
+                .within_range(self.reference.get(), self.alarm.get_alarm()),
+            "alarm called its client before its deadline"
+        );
+        self.client.map(|client| client.alarm());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::{ClientCounter, MockAlarm};
+    use kernel::hil::time::Ticks32;
+
+    #[test]
+    fn test_correct_alarm() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        alarm.set_minimum_dt(20);
+        let verifying = VerifyingAlarm::new(&alarm);
+        let client = ClientCounter::new();
+        alarm.set_alarm_client(&verifying);
+        verifying.set_alarm_client(&client);
+
+        verifying.set_alarm(0u32.into(), 10u32.into());
+        assert_eq!(alarm.run_for(100), 1);
+        verifying.set_alarm(alarm.now(), 100u32.into());
+        alarm.fire_late(50);
+        assert_eq!(client.count(), 2);
+    }
+
+    // The mock alarm disarms itself before calling its client; calling the
+    // client directly behaves like a buggy alarm which does not.
+    #[test]
+    #[should_panic(expected = "still armed")]
+    fn test_stays_armed() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let verifying = VerifyingAlarm::new(&alarm);
+        alarm.set_alarm_client(&verifying);
+
+        verifying.set_alarm(0u32.into(), 10u32.into());
+        alarm.set_now(10);
+        verifying.alarm();
+    }
+
+    #[test]
+    #[should_panic(expected = "before its deadline")]
+    fn test_fires_early() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let verifying = VerifyingAlarm::new(&alarm);
+        alarm.set_alarm_client(&verifying);
+
+        verifying.set_alarm(0u32.into(), 10u32.into());
+        alarm.set_now(5);
+        let _ = alarm.disarm();
+        verifying.alarm();
+    }
+}
@@ -0,0 +1,191 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Software watchdog layered on an `Alarm`.
+//!
+//! Once started, a `WatchdogAlarm` must be fed at least once every
+//! `timeout` ticks. Each `feed` pushes the deadline back to `timeout`
+//! ticks from now; if the deadline passes without a feed, the
+//! `WatchdogExpiredClient` is called once, e.g. to reset the system.
+//!
+//! The watchdog stops when it expires. Feeding a stopped watchdog,
+//! including from within the expiry callback, fails with
+//! `ErrorCode::OFF`: it has to be restarted explicitly with `start`.
+//!
+//! The `WatchdogAlarm` must be installed as the client of the underlying
+//! alarm.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let watchdog = static_init!(
+//!     WatchdogAlarm<'static, VirtualMuxAlarm<'static, nrf52::rtc::Rtc>>,
+//!     WatchdogAlarm::new(virtual_alarm)
+//! );
+//! virtual_alarm.set_alarm_client(watchdog);
+//! watchdog.set_expired_client(reset_handler);
+//! watchdog.start(virtual_alarm.ticks_from_ms(500));
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Called when a `WatchdogAlarm` was not fed in time.
+pub trait WatchdogExpiredClient {
+    fn expired(&self);
+}
+
+/// Watchdog which calls its client if it is not fed every `timeout`
+/// ticks.
+pub struct WatchdogAlarm<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    timeout: Cell<A::Ticks>,
+    running: Cell<bool>,
+    client: OptionalCell<&'a dyn WatchdogExpiredClient>,
+}
+
+impl<'a, A: Alarm<'a>> WatchdogAlarm<'a, A> {
+    pub fn new(alarm: &'a A) -> WatchdogAlarm<'a, A> {
+        WatchdogAlarm {
+            alarm,
+            timeout: Cell::new(A::Ticks::from(0)),
+            running: Cell::new(false),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_expired_client(&self, client: &'a dyn WatchdogExpiredClient) {
+        self.client.set(client);
+    }
+
+    /// Start the watchdog, or restart it with a new timeout. It expires
+    /// unless fed within `timeout` ticks from now.
+    pub fn start(&self, timeout: A::Ticks) {
+        self.timeout.set(timeout);
+        self.running.set(true);
+        self.alarm.set_alarm(self.alarm.now(), timeout);
+    }
+
+    /// Push the deadline back to `timeout` ticks from now. Returns
+    /// `Err(ErrorCode::OFF)` if the watchdog is not running, either
+    /// because it was never started, was stopped, or already expired.
+    pub fn feed(&self) -> Result<(), ErrorCode> {
+        if !self.running.get() {
+            return Err(ErrorCode::OFF);
+        }
+        self.alarm.set_alarm(self.alarm.now(), self.timeout.get());
+        Ok(())
+    }
+
+    /// Stop the watchdog without it expiring.
+    pub fn stop(&self) -> Result<(), ErrorCode> {
+        self.running.set(false);
+        self.alarm.disarm()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.get()
+    }
+}
+
+impl<'a, A: Alarm<'a>> Time for WatchdogAlarm<'a, A> {
+    type Frequency = A::Frequency;
+    type Ticks = A::Ticks;
+
+    fn now(&self) -> Self::Ticks {
+        self.alarm.now()
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for WatchdogAlarm<'a, A> {
+    fn alarm(&self) {
+        // Stopped after the underlying alarm already fired.
+        if !self.running.get() {
+            return;
+        }
+        // Stop before calling the client, so that it cannot feed the
+        // watchdog it is handling the expiry of.
+        self.running.set(false);
+        self.client.map(|client| client.expired());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mock::MockAlarm;
+    use kernel::hil::time::Ticks32;
+
+    struct ExpiredCounter<'a> {
+        count: Cell<usize>,
+        /// Result of feeding the watchdog from the callback.
+        feed: Cell<Option<Result<(), ErrorCode>>>,
+        watchdog: OptionalCell<&'a WatchdogAlarm<'a, MockAlarm<'a, Ticks32>>>,
+    }
+
+    impl<'a> ExpiredCounter<'a> {
+        fn new() -> Self {
+            ExpiredCounter {
+                count: Cell::new(0),
+                feed: Cell::new(None),
+                watchdog: OptionalCell::empty(),
+            }
+        }
+    }
+
+    impl WatchdogExpiredClient for ExpiredCounter<'_> {
+        fn expired(&self) {
+            self.count.set(self.count.get() + 1);
+            self.watchdog
+                .map(|watchdog| self.feed.set(Some(watchdog.feed())));
+        }
+    }
+
+    #[test]
+    fn test_fed_watchdog_does_not_expire() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let watchdog = WatchdogAlarm::new(&alarm);
+        let client = ExpiredCounter::new();
+        alarm.set_alarm_client(&watchdog);
+        watchdog.set_expired_client(&client);
+
+        assert_eq!(watchdog.feed(), Err(ErrorCode::OFF));
+        watchdog.start(100u32.into());
+        for _ in 0..10 {
+            assert_eq!(alarm.run_for(90), 0);
+            assert_eq!(watchdog.feed(), Ok(()));
+        }
+        assert_eq!(client.count.get(), 0);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1000u32));
+
+        assert_eq!(watchdog.stop(), Ok(()));
+        assert_eq!(alarm.run_for(1000), 0);
+        assert_eq!(client.count.get(), 0);
+    }
+
+    #[test]
+    fn test_expires_once() {
+        let alarm = MockAlarm::<Ticks32>::new();
+        let watchdog = WatchdogAlarm::new(&alarm);
+        let client = ExpiredCounter::new();
+        client.watchdog.set(&watchdog);
+        alarm.set_alarm_client(&watchdog);
+        watchdog.set_expired_client(&client);
+
+        watchdog.start(100u32.into());
+        assert_eq!(alarm.run_for(50), 0);
+        assert_eq!(watchdog.feed(), Ok(()));
+        assert_eq!(alarm.run_for(1000), 1);
+        assert_eq!(client.count.get(), 1);
+        assert!(!watchdog.is_running());
+
+        // Feeding from the expiry callback is rejected.
+        assert_eq!(client.feed.get(), Some(Err(ErrorCode::OFF)));
+        assert!(!alarm.is_armed());
+    }
+}
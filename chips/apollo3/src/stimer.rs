@@ -142,6 +142,8 @@ impl<'a> Counter<'a> for STimer<'a> {
         //self.overflow_client.set(client);
     }
 
+    fn clear_overflow_client(&self) {}
+
     fn start(&self) -> Result<(), ErrorCode> {
         // Set the clock source
         self.registers.stcfg.write(STCFG::CLKSEL::XTAL_DIV2);
@@ -167,6 +169,10 @@ impl<'a> Alarm<'a> for STimer<'a> {
         self.client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         let regs = self.registers;
         let now = self.now();
@@ -115,6 +115,10 @@ impl<'a, CFG: EarlGreyConfig> time::Counter<'a> for RvTimer<'a, CFG> {
         self.overflow_client.set(client);
     }
 
+    fn clear_overflow_client(&self) {
+        self.overflow_client.clear();
+    }
+
     fn start(&self) -> Result<(), ErrorCode> {
         Ok(())
     }
@@ -139,6 +143,10 @@ impl<'a, CFG: EarlGreyConfig> time::Alarm<'a> for RvTimer<'a, CFG> {
         self.alarm_client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.alarm_client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         self.registers.intr_enable.write(intr::timer0::SET);
 
@@ -197,9 +197,7 @@ impl<F: time::Frequency, const C3: bool> time::Time for TimG<'_, F, C3> {
         // a write (of any value) to T0UPDATE stores the
         // current counter value to T0LO and T0HI
         self.registers.t0update.set(0xABC);
-        Self::Ticks::from(
-            self.registers.t0lo.get() as u64 + ((self.registers.t0hi.get() as u64) << 32),
-        )
+        Ticks64::from_parts(self.registers.t0hi.get(), self.registers.t0lo.get())
     }
 }
 
@@ -208,6 +206,8 @@ impl<'a, F: time::Frequency, const C3: bool> Counter<'a> for TimG<'a, F, C3> {
         // We have no way to know when this happens
     }
 
+    fn clear_overflow_client(&self) {}
+
     fn start(&self) -> Result<(), ErrorCode> {
         self.registers.t0config.write(CONFIG::EN::SET);
 
@@ -234,6 +234,10 @@ impl<'a, F: time::Frequency, const C3: bool> Alarm<'a> for TimG<'a, F, C3> {
         self.alarm_client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.alarm_client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         let now = self.now();
         let mut expire = reference.wrapping_add(dt);
@@ -257,9 +261,7 @@ impl<'a, F: time::Frequency, const C3: bool> Alarm<'a> for TimG<'a, F, C3> {
                 .modify(CONFIG::EDGE_INT_EN_OR_DIVIDER_RST::SET);
         }
 
-        let val = expire.into_u64();
-        let high = (val >> 32) as u32;
-        let low = (val & 0xffffffff) as u32;
+        let (high, low) = expire.split();
 
         self.registers.t0alarmlo.set(0xFFFF_FFFF);
         self.registers.t0alarmhi.set(high);
@@ -277,8 +279,9 @@ impl<'a, F: time::Frequency, const C3: bool> Alarm<'a> for TimG<'a, F, C3> {
     }
 
     fn get_alarm(&self) -> Self::Ticks {
-        Self::Ticks::from(
-            self.registers.t0alarmlo.get() as u64 + ((self.registers.t0alarmhi.get() as u64) << 32),
+        Ticks64::from_parts(
+            self.registers.t0alarmhi.get(),
+            self.registers.t0alarmlo.get(),
         )
     }
 
@@ -372,6 +372,10 @@ impl<'a, F: hil::time::Frequency> hil::time::Alarm<'a> for Gpt<'a, F> {
         self.client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         let mut expire = reference.wrapping_add(dt);
         let now = self.now();
@@ -163,6 +163,8 @@ pub struct LiteXTimer<'a, R: LiteXSoCRegisterConfiguration, F: Frequency> {
     /// Variable to store whether an interval has been set at least
     /// once (e.g. the timer has been started once)
     interval_set: Cell<bool>,
+    /// Number of intervals elapsed at the last callback
+    fire_count: Cell<u32>,
     _frequency: PhantomData<F>,
 }
 
@@ -172,6 +174,7 @@ impl<R: LiteXSoCRegisterConfiguration, F: Frequency> LiteXTimer<'_, R, F> {
             registers: base,
             client: OptionalCell::empty(),
             interval_set: Cell::new(false),
+            fire_count: Cell::new(0),
             _frequency: PhantomData,
         }
     }
@@ -214,6 +217,11 @@ impl<R: LiteXSoCRegisterConfiguration, F: Frequency> LiteXTimer<'_, R, F> {
                 self.registers.ev().clear_event(EVENT_MANAGER_INDEX);
             }
 
+            // The hardware does not report how many times it has
+            // reloaded since the last interrupt was acknowledged, so
+            // this is always assumed to be a single interval
+            self.fire_count.set(1);
+
             // In any case, perform a callback to the client
             self.client.map(|client| {
                 client.timer();
@@ -258,6 +266,7 @@ impl<R: LiteXSoCRegisterConfiguration, F: Frequency> LiteXTimer<'_, R, F> {
 
         // The timer has been started at least once by now
         self.interval_set.set(true);
+        self.fire_count.set(0);
 
         // Start the timer
         WriteRegWrapper::wrap(&self.registers.en).write(en::enable::SET);
@@ -340,6 +349,10 @@ impl<'a, R: LiteXSoCRegisterConfiguration, F: Frequency> Timer<'a> for LiteXTime
 
         Ok(())
     }
+
+    fn fire_count(&self) -> u32 {
+        self.fire_count.get()
+    }
 }
 
 /// LiteX alarm implementation, based on [`LiteXTimer`] and
@@ -445,6 +458,10 @@ impl<'t, 'c, R: LiteXSoCRegisterConfiguration, F: Frequency> Alarm<'c>
         self.alarm_client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.alarm_client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         // Cancel any pending alarm
         if self.is_armed() {
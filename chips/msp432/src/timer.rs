@@ -347,6 +347,8 @@ impl<'a> Time for TimerA<'a> {
 impl<'a> Counter<'a> for TimerA<'a> {
     fn set_overflow_client(&self, _client: &'a dyn OverflowClient) {}
 
+    fn clear_overflow_client(&self) {}
+
     fn start(&self) -> Result<(), ErrorCode> {
         self.setup_for_alarm();
         Ok(())
@@ -372,6 +374,10 @@ impl<'a> Alarm<'a> for TimerA<'a> {
         self.alarm_client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.alarm_client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         if self.mode.get() != TimerMode::Alarm {
             self.setup_for_alarm();
@@ -135,6 +135,11 @@ impl<'a> time::Counter<'a> for Rtc<'a> {
         self.registers.intenset.write(Inte::OVRFLW::SET);
     }
 
+    fn clear_overflow_client(&self) {
+        self.registers.intenclr.write(Inte::OVRFLW::SET);
+        self.overflow_client.clear();
+    }
+
     fn start(&self) -> Result<(), ErrorCode> {
         self.registers.prescaler.write(Prescaler::PRESCALER.val(0));
         self.registers.tasks_start.write(Task::ENABLE::SET);
@@ -164,6 +169,10 @@ impl<'a> Alarm<'a> for Rtc<'a> {
         self.alarm_client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.alarm_client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         const SYNC_TICS: u32 = 2;
         let regs = &*self.registers;
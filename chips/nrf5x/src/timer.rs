@@ -326,6 +326,10 @@ impl<'a> Alarm<'a> for TimerAlarm<'a> {
         self.client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         self.disable_interrupts();
 
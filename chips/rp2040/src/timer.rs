@@ -237,6 +237,10 @@ impl<'a> Alarm<'a> for RPTimer<'a> {
         self.client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         let mut expire = reference.wrapping_add(dt);
         let now = self.now();
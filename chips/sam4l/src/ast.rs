@@ -323,6 +323,8 @@ impl time::Time for Ast<'_> {
 impl<'a> time::Counter<'a> for Ast<'a> {
     fn set_overflow_client(&self, _client: &'a dyn time::OverflowClient) {}
 
+    fn clear_overflow_client(&self) {}
+
     fn start(&self) -> Result<(), ErrorCode> {
         self.enable();
         Ok(())
@@ -348,6 +350,10 @@ impl<'a> time::Alarm<'a> for Ast<'a> {
         self.callback.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.callback.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         let now = Self::Ticks::from(self.get_counter());
         let mut expire = reference.wrapping_add(dt);
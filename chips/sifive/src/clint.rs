@@ -77,6 +77,10 @@ impl<'a, F: Frequency> time::Alarm<'a> for Clint<'a, F> {
         self.client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         self.mtimer.set_alarm(reference, dt)
     }
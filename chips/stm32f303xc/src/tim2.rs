@@ -379,6 +379,8 @@ impl Time for Tim2<'_> {
 impl<'a> Counter<'a> for Tim2<'a> {
     fn set_overflow_client(&self, _client: &'a dyn OverflowClient) {}
 
+    fn clear_overflow_client(&self) {}
+
     // starts the timer
     fn start(&self) -> Result<(), ErrorCode> {
         self.start_counter();
@@ -407,6 +409,10 @@ impl<'a> Alarm<'a> for Tim2<'a> {
         self.client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         let mut expire = reference.wrapping_add(dt);
         let now = self.now();
@@ -93,6 +93,8 @@ impl<'a> Counter<'a> for Timer<'a> {
         // We have no way to know when this happens
     }
 
+    fn clear_overflow_client(&self) {}
+
     fn start(&self) -> Result<(), ErrorCode> {
         match self.number {
             TimerNumber::ZERO => self.mitctl0.modify(MITCTL::ENABLE::SET),
@@ -130,6 +132,10 @@ impl<'a> Alarm<'a> for Timer<'a> {
         self.alarm_client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.alarm_client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         // Start the counter
         if !self.is_running() {
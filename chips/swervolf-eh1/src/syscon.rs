@@ -119,6 +119,10 @@ impl<'a> time::Counter<'a> for SysCon<'a> {
         self.overflow_client.set(client);
     }
 
+    fn clear_overflow_client(&self) {
+        self.overflow_client.clear();
+    }
+
     fn start(&self) -> Result<(), ErrorCode> {
         Ok(())
     }
@@ -143,6 +147,10 @@ impl<'a> time::Alarm<'a> for SysCon<'a> {
         self.alarm_client.set(client);
     }
 
+    fn clear_alarm_client(&self) {
+        self.alarm_client.clear();
+    }
+
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
         self.mtimer.set_alarm(reference, dt);
 
@@ -16,13 +16,25 @@
 //! into these more general ones.
 
 use crate::ErrorCode;
+use core::cell::Cell;
 use core::cmp::{Eq, Ord, Ordering, PartialOrd};
 use core::fmt;
+use core::marker::PhantomData;
+use core::time::Duration;
 
 /// An integer type defining the width of a time value, which allows
 /// clients to know when wraparound will occur.
 
 pub trait Ticks: Clone + Copy + From<u32> + fmt::Debug + Ord + PartialOrd + Eq {
+    /// The value 0, usable in const contexts unlike `From<u32>`.
+    const ZERO: Self;
+
+    /// The value 1.
+    const ONE: Self;
+
+    /// The largest value of the type, equal to `max_value()`.
+    const MAX: Self;
+
     /// Converts the type into a `usize`, stripping the higher bits
     /// it if it is larger than `usize` and filling the higher bits
     /// with 0 if it is smaller than `usize`.
@@ -35,6 +47,10 @@ pub trait Ticks: Clone + Copy + From<u32> + fmt::Debug + Ord + PartialOrd + Eq {
     /// are 32 bits.
     fn into_u32(self) -> u32;
 
+    /// Converts the type into a `u64`, filling the higher bits with 0.
+    /// Unlike `into_u32`, this is lossless for every width.
+    fn into_u64(self) -> u64;
+
     /// Add two values, wrapping around on overflow using standard
     /// unsigned arithmetic.
     fn wrapping_add(self, other: Self) -> Self;
@@ -62,6 +78,245 @@ pub trait Ticks: Clone + Copy + From<u32> + fmt::Debug + Ord + PartialOrd + Eq {
     /// Scales the ticks by the specified numerator and denominator. If the resulting value would
     /// be greater than u32,`u32::MAX` is returned instead
     fn saturating_scale(self, numerator: u32, denominator: u32) -> u32;
+
+    /// Returns the number of leading zeros in the value, counted from
+    /// the most significant bit of the width of the type (e.g., 24 bits
+    /// for `Ticks24`) rather than of the underlying integer.
+    fn leading_zeros(self) -> u32;
+
+    /// Returns the number of bits needed to represent the value, i.e.,
+    /// the width of the type minus `leading_zeros()`. Zero needs 0 bits.
+    fn significant_bits(self) -> u32;
+
+    /// Returns the number of one bits in the value, counting only the
+    /// bits within the width of the type.
+    fn count_ones(self) -> u32;
+
+    /// Returns the number of trailing zero bits in the value. Zero has as
+    /// many trailing zeros as the width of the type (e.g., 24 for
+    /// `Ticks24`).
+    fn trailing_zeros(self) -> u32;
+
+    /// Returns the remainder of dividing the value by `period`, e.g. the
+    /// phase of a timestamp within a repeating cycle, computed on the
+    /// bits within the width of the type. Returns `self` if `period` is 0.
+    fn wrapping_mod(self, period: Self) -> Self;
+
+    /// Returns the value with any bits above the width of the type
+    /// cleared, e.g. the top 8 bits of a `Ticks24`, so that equal tick
+    /// values always have equal representations. Constructors already
+    /// canonicalize their result; this is a no-op for types whose width
+    /// matches the underlying integer.
+    fn canonicalize(self) -> Self;
+
+    /// Writes the value into `out` in little-endian order, using exactly
+    /// the byte width of the type (e.g., 3 bytes for `Ticks24`). Returns
+    /// the number of bytes written, or 0 without writing anything if
+    /// `out` is shorter than the width of the type.
+    fn to_le_bytes(self, out: &mut [u8]) -> usize;
+
+    /// Reads a value written by `to_le_bytes` from the first bytes of
+    /// `bytes`. Bytes beyond the width of the type are ignored; if
+    /// `bytes` is shorter than the width, the missing high bytes are
+    /// taken to be 0.
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+
+    /// Multiplies the value by `factor`, returning `None` if the product
+    /// does not fit in the width of the type (e.g., 24 bits for
+    /// `Ticks24`), rather than silently wrapping.
+    fn checked_mul(self, factor: u32) -> Option<Self>;
+
+    /// Compares two values considering wraparound, rather than by
+    /// absolute magnitude as `Ord` does. `self` is `Greater` than
+    /// `other` if it is less than half the range of the type ahead of
+    /// `other` (i.e., `self.wrapping_sub(other) < half_max_value()`)
+    /// and `Less` otherwise. For example, for `Ticks16`, `0x0000` is
+    /// `Greater` than `0xFFFF` since it is one tick ahead of it.
+    ///
+    /// This is the comparison to use for deadlines and other values
+    /// which may have wrapped, as long as the two values are known to
+    /// be within half the range of each other. Use `Ord` for absolute
+    /// values such as durations.
+    fn cmp_wrapping(self, other: Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else if self.wrapping_sub(other) < Self::half_max_value() {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        }
+    }
+
+    /// Returns the absolute difference between two values, i.e., the
+    /// larger minus the smaller as ordered by `Ord`. This is intended for
+    /// absolute values such as timestamps in a region known not to wrap;
+    /// it does not consider wraparound, so for deadlines which may have
+    /// wrapped use `wrapping_sub` from the earlier value instead.
+    fn abs_difference(self, other: Self) -> Self {
+        if self >= other {
+            self.wrapping_sub(other)
+        } else {
+            other.wrapping_sub(self)
+        }
+    }
+
+    /// Moves a value from one frame of reference to another, preserving
+    /// its offset relative to the reference. Returns
+    /// `self - old_ref + new_ref` in wrapping arithmetic, e.g., for
+    /// re-anchoring a deadline when the counter it was computed against
+    /// has been reset.
+    fn rebase(self, old_ref: Self, new_ref: Self) -> Self {
+        self.wrapping_sub(old_ref).wrapping_add(new_ref)
+    }
+
+    /// Returns the value halfway along the forward arc from `self` to
+    /// `other`, i.e. `self + (other - self) / 2` in wrapping arithmetic,
+    /// rounded towards `self`. Unlike `(self + other) / 2`, this cannot
+    /// overflow and handles arcs which cross the wrap point: for
+    /// `Ticks16`, the midpoint of `0xFFF0` and `0x0010` is `0x0000`.
+    fn midpoint(self, other: Self) -> Self {
+        self.wrapping_add(Self::from_or_max(other.wrapping_sub(self).into_u64() / 2))
+    }
+
+    /// Returns the number of ticks in the range `[start, end)`, i.e.
+    /// `end.wrapping_sub(start)`: the forward distance from `start` to
+    /// `end`, assuming `end` is ahead of `start`. The range may cross the
+    /// wrap point. If `end` is actually behind `start`, this is the length
+    /// of the complementary range, wrapping past the maximum value.
+    fn range_len(start: Self, end: Self) -> Self {
+        end.wrapping_sub(start)
+    }
+
+    /// Returns the candidate nearest to `target`, e.g. to correlate an
+    /// event with the closest of several timestamps. Values are compared
+    /// with their absolute `Ord` ordering rather than around the wrap
+    /// point, so they should all be close together and far from a wrap.
+    /// If several candidates are equally near, the first of them is
+    /// returned. Returns `None` if `candidates` is empty.
+    fn closest_to(target: Self, candidates: &[Self]) -> Option<Self> {
+        candidates.iter().copied().min_by_key(|&candidate| {
+            core::cmp::max(candidate, target).wrapping_sub(core::cmp::min(candidate, target))
+        })
+    }
+
+    /// Encodes the value as its forward distance from `prev`, i.e.
+    /// `self - prev` in wrapping arithmetic, e.g. to store closely spaced
+    /// timestamps compactly. This assumes `self` is at most one period of
+    /// the type ahead of `prev`, and for `Ticks64` also that the distance
+    /// fits in 32 bits; a larger distance saturates at `u32::MAX`, which
+    /// `delta_decode` cannot restore.
+    fn delta_encode(self, prev: Self) -> u32 {
+        u32::try_from(self.wrapping_sub(prev).into_u64()).unwrap_or(u32::MAX)
+    }
+
+    /// Decodes a value encoded with `delta_encode` against `prev`.
+    fn delta_decode(prev: Self, delta: u32) -> Self {
+        prev.wrapping_add(Self::from_or_max(
+            delta as u64 & Self::max_value().into_u64(),
+        ))
+    }
+
+    /// Returns the point `numerator / denominator` of the way from `self`
+    /// forward to `other`, i.e. `self + (other - self) * numerator /
+    /// denominator` in wrapping arithmetic, rounded towards `self`. The
+    /// arc may cross the wrap point. A fraction above 1 extrapolates past
+    /// `other`, wrapping around at the width of the type. Returns `self`
+    /// if `denominator` is 0.
+    fn interpolate(self, other: Self, numerator: u32, denominator: u32) -> Self {
+        if denominator == 0 {
+            return self;
+        }
+        let distance = other.wrapping_sub(self).into_u64() as u128;
+        let offset = distance * numerator as u128 / denominator as u128;
+        let offset = offset as u64 & Self::max_value().into_u64();
+        self.wrapping_add(Self::from_or_max(offset))
+    }
+
+    /// Adds a signed offset, wrapping around at the width of the type in
+    /// either direction, e.g. to move a deadline earlier or later for
+    /// drift correction. Any `i32`, including `i32::MIN`, is accepted.
+    fn wrapping_add_signed(self, delta: i32) -> Self {
+        if delta >= 0 {
+            self.wrapping_add(Self::from(delta as u32))
+        } else {
+            self.wrapping_sub(Self::from(delta.unsigned_abs()))
+        }
+    }
+
+    /// Rounds the value up to the next multiple of `granule`, e.g. to
+    /// align a deadline to a coarser grid. Returns `self` if `granule` is
+    /// 0, and `max_value()` if the rounded value does not fit.
+    fn round_up_to(self, granule: Self) -> Self {
+        let (val, granule) = (self.into_u64(), granule.into_u64());
+        match val.checked_rem(granule) {
+            None | Some(0) => self,
+            Some(rem) => val
+                .checked_add(granule - rem)
+                .map_or(Self::max_value(), Self::from_or_max),
+        }
+    }
+
+    /// Rounds the value down to the previous multiple of `granule`.
+    /// Returns `self` if `granule` is 0.
+    fn round_down_to(self, granule: Self) -> Self {
+        let val = self.into_u64();
+        match val.checked_rem(granule.into_u64()) {
+            None => self,
+            Some(rem) => Self::from_or_max(val - rem),
+        }
+    }
+
+    /// Returns whether the value, within the width of the type, is a power
+    /// of two. Zero is not.
+    fn is_power_of_two(self) -> bool {
+        self.count_ones() == 1
+    }
+
+    /// Returns the smallest power of two greater than or equal to the
+    /// value within the width of the type, e.g. to pick a prescaler
+    /// divisor. Zero rounds up to 1. Saturates at `max_value()` if the
+    /// next power of two does not fit in the width of the type.
+    fn next_power_of_two(self) -> Self {
+        let max = Self::max_value().into_u64();
+        match (self.into_u64() & max).checked_next_power_of_two() {
+            Some(power) if power <= max => Self::from_or_max(power),
+            _ => Self::max_value(),
+        }
+    }
+
+    /// Returns the value as a `usize`, or `Err(ErrorCode::SIZE)` if it is
+    /// too large to fit, e.g. a `Ticks64` on a 32-bit platform, rather
+    /// than truncating it like `into_usize`.
+    fn try_into_usize(self) -> Result<usize, ErrorCode> {
+        usize::try_from(self.into_u64()).map_err(|_| ErrorCode::SIZE)
+    }
+
+    /// Returns the value as a `u16`, or `Err(ErrorCode::SIZE)` if it is
+    /// too large to fit, rather than truncating it like `into_u32`.
+    fn try_into_u16(self) -> Result<u16, ErrorCode> {
+        u16::try_from(self.into_u64()).map_err(|_| ErrorCode::SIZE)
+    }
+
+    /// Returns the value as a `u8`, or `Err(ErrorCode::SIZE)` if it is
+    /// too large to fit.
+    fn try_into_u8(self) -> Result<u8, ErrorCode> {
+        u8::try_from(self.into_u64()).map_err(|_| ErrorCode::SIZE)
+    }
+
+    /// Returns the value, as ticks of a clock with frequency `F`, in
+    /// milliseconds, e.g. for a capsule API which takes milliseconds.
+    /// Rounds down, and saturates at `u32::MAX`, like
+    /// `ConvertTicks::ticks_to_ms`.
+    fn to_millis<F: Frequency>(self) -> u32 {
+        self.saturating_scale(1_000, F::frequency())
+    }
+
+    /// Returns the number of ticks of a clock with frequency `F` in `ms`
+    /// milliseconds. Rounds down, and saturates at `max_value()`, like
+    /// `ConvertTicks::ticks_from_ms`.
+    fn from_millis<F: Frequency>(ms: u32) -> Self {
+        ticks_from_scaled(F::frequency(), ms, 1_000)
+    }
 }
 
 /// Represents a clock's frequency in Hz, allowing code to transform
@@ -70,6 +325,35 @@ pub trait Ticks: Clone + Copy + From<u32> + fmt::Debug + Ord + PartialOrd + Eq {
 pub trait Frequency {
     /// Returns frequency in Hz.
     fn frequency() -> u32;
+
+    /// Returns the period of one tick in nanoseconds, rounded down
+    /// (e.g., 62 for 16MHz).
+    fn period_ns() -> u64 {
+        1_000_000_000 / Self::frequency() as u64
+    }
+}
+
+/// Tick counts of a `Frequency` which are known at compile time, so they
+/// can be used in const contexts (e.g., `F::TICKS_PER_MS`) instead of
+/// being divided out at runtime. Values are rounded down, so they are 0
+/// for frequencies below 1 tick per unit.
+pub trait FrequencyConsts: Frequency {
+    /// Number of ticks in one millisecond.
+    const TICKS_PER_MS: u32;
+    /// Number of ticks in one microsecond.
+    const TICKS_PER_US: u32;
+}
+
+/// Returns the number of ticks per millisecond at `hz`, rounded down.
+/// Usable to implement `FrequencyConsts` for other frequencies.
+pub const fn ticks_per_ms(hz: u32) -> u32 {
+    hz / 1_000
+}
+
+/// Returns the number of ticks per microsecond at `hz`, rounded down.
+/// Usable to implement `FrequencyConsts` for other frequencies.
+pub const fn ticks_per_us(hz: u32) -> u32 {
+    hz / 1_000_000
 }
 
 /// Represents a moment in time, obtained by calling `now`.
@@ -85,6 +369,45 @@ pub trait Time {
     /// it being constant or changing it should use `Timestamp`
     /// or `Counter`.
     fn now(&self) -> Self::Ticks;
+
+    /// Returns the longest interval that can safely be used with this
+    /// time source, which is half the range of `Self::Ticks`.
+    ///
+    /// Wraparound-aware comparisons such as `Ticks::within_range` can
+    /// only tell whether a deadline has passed if it is less than one
+    /// period away, and delays between an alarm firing and its handling
+    /// eat into that period. An alarm with a `dt` larger than
+    /// `max_interval()` may therefore appear to be in the past, or fire
+    /// a full period late.
+    fn max_interval(&self) -> Self::Ticks {
+        Self::Ticks::half_max_value()
+    }
+
+    /// Returns the frequency of this time source in Hz. Unlike
+    /// `Self::Frequency::frequency()`, this can be called through a
+    /// `&dyn Time` without naming the associated type.
+    fn frequency_hz(&self) -> u32 {
+        Self::Frequency::frequency()
+    }
+
+    /// Returns the current time as a `u64`, zero-extended from the width
+    /// of `Self::Ticks`. This gives a timestamp comparable across time
+    /// sources of different widths, including through a `&dyn Time`.
+    fn now_u64(&self) -> u64 {
+        self.now().into_u64()
+    }
+
+    /// Returns the current time as whole seconds and the remaining
+    /// nanoseconds, as used by filesystem timestamps. Fractions of a
+    /// nanosecond are rounded down, so the result is exact only if the
+    /// frequency divides 10^9. Seconds saturate at `u32::MAX`.
+    fn now_seconds_nanos(&self) -> (u32, u32) {
+        let now = ticks_to_duration::<Self::Frequency, _>(self.now());
+        (
+            u32::try_from(now.as_secs()).unwrap_or(u32::MAX),
+            now.subsec_nanos(),
+        )
+    }
 }
 
 pub trait ConvertTicks<T: Ticks> {
@@ -120,21 +443,28 @@ pub trait ConvertTicks<T: Ticks> {
     fn ticks_to_us(&self, tick: T) -> u32;
 }
 
+/// Converts `value` units of `1 / units_per_second` seconds into ticks of
+/// a clock running at `hz`. Saturates at `T::max_value()` if the result
+/// does not fit in `T`, or if computing it overflows `u64`.
+#[inline]
+fn ticks_from_scaled<T: Ticks>(hz: u32, value: u32, units_per_second: u64) -> T {
+    (hz as u64)
+        .checked_mul(value as u64)
+        .map_or(T::max_value(), |val| T::from_or_max(val / units_per_second))
+}
+
 impl<T: Time + ?Sized> ConvertTicks<<T as Time>::Ticks> for T {
     #[inline]
     fn ticks_from_seconds(&self, s: u32) -> <T as Time>::Ticks {
-        let val = <T as Time>::Frequency::frequency() as u64 * s as u64;
-        <T as Time>::Ticks::from_or_max(val)
+        ticks_from_scaled(<T as Time>::Frequency::frequency(), s, 1)
     }
     #[inline]
     fn ticks_from_ms(&self, ms: u32) -> <T as Time>::Ticks {
-        let val = <T as Time>::Frequency::frequency() as u64 * ms as u64;
-        <T as Time>::Ticks::from_or_max(val / 1_000)
+        ticks_from_scaled(<T as Time>::Frequency::frequency(), ms, 1_000)
     }
     #[inline]
     fn ticks_from_us(&self, us: u32) -> <T as Time>::Ticks {
-        let val = <T as Time>::Frequency::frequency() as u64 * us as u64;
-        <T as Time>::Ticks::from_or_max(val / 1_000_000)
+        ticks_from_scaled(<T as Time>::Frequency::frequency(), us, 1_000_000)
     }
 
     #[inline]
@@ -151,6 +481,402 @@ impl<T: Time + ?Sized> ConvertTicks<<T as Time>::Ticks> for T {
     }
 }
 
+/// A clock frequency which is only known at runtime, e.g. read from the
+/// clock configuration registers during initialization, for use with
+/// `RuntimeTime`. A frequency of 0 is stored as 1Hz, so that conversions
+/// never divide by zero.
+pub struct RuntimeFrequency {
+    hz: Cell<u32>,
+}
+
+impl RuntimeFrequency {
+    pub const fn new(hz: u32) -> Self {
+        RuntimeFrequency {
+            hz: Cell::new(if hz == 0 { 1 } else { hz }),
+        }
+    }
+
+    pub fn set(&self, hz: u32) {
+        self.hz.set(core::cmp::max(hz, 1));
+    }
+
+    pub fn get(&self) -> u32 {
+        self.hz.get()
+    }
+}
+
+/// A counter whose frequency is a `RuntimeFrequency` rather than a
+/// `Frequency` type. The conversions mirror those of `ConvertTicks`,
+/// with the same rounding and saturation, using the frequency at the
+/// time of the call.
+pub trait RuntimeTime {
+    type Ticks: Ticks;
+
+    /// Returns the current counter value.
+    fn now(&self) -> Self::Ticks;
+
+    /// Returns the frequency the counter currently runs at.
+    fn frequency(&self) -> &RuntimeFrequency;
+
+    fn ticks_from_seconds(&self, s: u32) -> Self::Ticks {
+        ticks_from_scaled(self.frequency().get(), s, 1)
+    }
+
+    fn ticks_from_ms(&self, ms: u32) -> Self::Ticks {
+        ticks_from_scaled(self.frequency().get(), ms, 1_000)
+    }
+
+    fn ticks_from_us(&self, us: u32) -> Self::Ticks {
+        ticks_from_scaled(self.frequency().get(), us, 1_000_000)
+    }
+
+    fn ticks_to_seconds(&self, tick: Self::Ticks) -> u32 {
+        tick.saturating_scale(1, self.frequency().get())
+    }
+
+    fn ticks_to_ms(&self, tick: Self::Ticks) -> u32 {
+        tick.saturating_scale(1_000, self.frequency().get())
+    }
+
+    fn ticks_to_us(&self, tick: Self::Ticks) -> u32 {
+        tick.saturating_scale(1_000_000, self.frequency().get())
+    }
+}
+
+/// Converts a number of ticks of a clock with frequency `F` into a
+/// `Duration`. Ticks which do not make up a whole second are converted
+/// into nanoseconds, rounding down any fraction of a nanosecond.
+pub fn ticks_to_duration<F: Frequency, T: Ticks>(ticks: T) -> Duration {
+    let ticks = ticks.into_u64();
+    let hz = F::frequency() as u64;
+    // The remainder is less than `hz`, so this cannot overflow.
+    let nanos = (ticks % hz) * 1_000_000_000 / hz;
+    Duration::new(ticks / hz, nanos as u32)
+}
+
+/// Converts a `Duration` into ticks of a clock running at `hz`, without
+/// needing a `Time` instance. Fractions of a tick are rounded down, and
+/// the result saturates at `T::max_value()`.
+pub fn ticks_from_hz<T: Ticks>(hz: u32, d: Duration) -> T {
+    let hz = hz as u64;
+    // Less than 10^9 * 2^32, so this cannot overflow.
+    let fraction = d.subsec_nanos() as u64 * hz / 1_000_000_000;
+    d.as_secs()
+        .checked_mul(hz)
+        .and_then(|whole| whole.checked_add(fraction))
+        .map_or(T::max_value(), T::from_or_max)
+}
+
+/// Builds intervals in ticks of a clock with frequency `F`, naming the
+/// frequency at the call site, e.g. `At::<Self::Frequency>::ms(5)`.
+/// Fractions of a tick are rounded down, and the results saturate at
+/// `T::max_value()`.
+pub struct At<F: Frequency>(PhantomData<F>);
+
+impl<F: Frequency> At<F> {
+    pub fn secs<T: Ticks>(s: u32) -> T {
+        ticks_from_scaled(F::frequency(), s, 1)
+    }
+
+    pub fn ms<T: Ticks>(ms: u32) -> T {
+        ticks_from_scaled(F::frequency(), ms, 1_000)
+    }
+
+    pub fn us<T: Ticks>(us: u32) -> T {
+        ticks_from_scaled(F::frequency(), us, 1_000_000)
+    }
+
+    pub fn ns<T: Ticks>(ns: u32) -> T {
+        ticks_from_scaled(F::frequency(), ns, 1_000_000_000)
+    }
+}
+
+/// Converts a rate in units per second (e.g. bytes per second) into a
+/// number of whole units per tick of a clock with frequency `F`, without
+/// floating point. The increment per tick is stored as a Q16.16 fixed
+/// point number, and `accumulate` carries the fractional part over from
+/// one tick to the next. The increment is rounded down to a multiple of
+/// 2^-16 units, so rates below `F::frequency() / 65536` produce nothing,
+/// and saturates at just under 65536 units per tick.
+pub struct RatePerTick<F: Frequency> {
+    /// Units per tick, in Q16.16.
+    increment: u32,
+    /// Accumulated fraction of a unit, in Q16.16.
+    fraction: u32,
+    _frequency: PhantomData<F>,
+}
+
+impl<F: Frequency> RatePerTick<F> {
+    pub fn new(units_per_second: u32) -> Self {
+        let increment = ((units_per_second as u64) << 16) / F::frequency() as u64;
+        RatePerTick {
+            increment: u32::try_from(increment).unwrap_or(u32::MAX),
+            fraction: 0,
+            _frequency: PhantomData,
+        }
+    }
+
+    /// Returns the increment per tick in Q16.16.
+    pub fn increment(&self) -> u32 {
+        self.increment
+    }
+
+    /// Adds one tick's increment and returns the number of whole units
+    /// produced by it.
+    pub fn accumulate(&mut self) -> u32 {
+        let total = self.fraction as u64 + self.increment as u64;
+        self.fraction = (total & 0xFFFF) as u32;
+        (total >> 16) as u32
+    }
+}
+
+/// Parses an interval such as `"5ms"` into ticks of a clock with
+/// frequency `F`, e.g. for a debug console command. The interval is an
+/// unsigned decimal `u32` followed by one of the units `ns`, `us`, `ms`,
+/// `s` or `ticks`, without whitespace in between. Conversions follow
+/// `At`: fractions of a tick are rounded down and the result saturates at
+/// `T::max_value()`. Returns `Err(ErrorCode::INVAL)` if `s` is malformed.
+pub fn parse_interval<F: Frequency, T: Ticks>(s: &str) -> Result<T, ErrorCode> {
+    let units: [(&str, fn(u32) -> T); 5] = [
+        ("ticks", |n| T::from_or_max(n as u64)),
+        ("ns", At::<F>::ns),
+        ("us", At::<F>::us),
+        ("ms", At::<F>::ms),
+        ("s", At::<F>::secs),
+    ];
+    units
+        .iter()
+        .find_map(|(suffix, convert)| s.strip_suffix(suffix).map(|number| (number, convert)))
+        .and_then(|(number, convert)| {
+            // `u32::from_str` accepts a leading `+`, which is not a digit.
+            if number.bytes().all(|b| b.is_ascii_digit()) {
+                number.parse().ok().map(convert)
+            } else {
+                None
+            }
+        })
+        .ok_or(ErrorCode::INVAL)
+}
+
+/// Returns the interval in ticks of a clock with frequency `F` between
+/// samples taken at `sample_hz`, e.g. to drive an ADC from a timer. The
+/// interval is rounded down, so the actual rate is at least `sample_hz`,
+/// and saturates at `T::max_value()` if it does not fit in the width of
+/// `T`. Returns `Err(ErrorCode::INVAL)` if `sample_hz` is 0 or higher
+/// than the frequency of the clock.
+pub fn interval_for_rate<F: Frequency, T: Ticks>(sample_hz: u32) -> Result<T, ErrorCode> {
+    if sample_hz == 0 || sample_hz > F::frequency() {
+        return Err(ErrorCode::INVAL);
+    }
+    Ok(T::from_or_max((F::frequency() / sample_hz) as u64))
+}
+
+/// Displays a number of ticks of a clock with frequency `F` as a
+/// duration in the most appropriate unit, e.g. `1.000 ms` rather than
+/// `16000` for a 16MHz clock. Durations below a microsecond are shown in
+/// whole nanoseconds, longer ones with three fractional digits of
+/// microseconds, milliseconds or seconds, rounded down. No floating point
+/// is used.
+pub struct TicksDisplay<F: Frequency, T: Ticks>(pub T, PhantomData<F>);
+
+impl<F: Frequency, T: Ticks> TicksDisplay<F, T> {
+    pub fn new(ticks: T) -> Self {
+        TicksDisplay(ticks, PhantomData)
+    }
+}
+
+impl<F: Frequency, T: Ticks> fmt::Display for TicksDisplay<F, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nanos = self.0.into_u64() as u128 * 1_000_000_000 / F::frequency() as u128;
+        let (unit_nanos, unit) = match nanos {
+            0..=999 => return write!(f, "{} ns", nanos),
+            1_000..=999_999 => (1_000, "us"),
+            1_000_000..=999_999_999 => (1_000_000, "ms"),
+            _ => (1_000_000_000, "s"),
+        };
+        write!(
+            f,
+            "{}.{:03} {}",
+            nanos / unit_nanos,
+            nanos % unit_nanos * 1_000 / unit_nanos,
+            unit
+        )
+    }
+}
+
+/// A tick value which is expected to stay within `[min, max]`, compared
+/// as plain integers. This is a bringup aid for catching misconfigured
+/// timers early: constructing a value outside the band, or leaving it
+/// through arithmetic, fails a `debug_assert!`. Release builds do not
+/// check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundedTicks<T: Ticks> {
+    value: T,
+    min: T,
+    max: T,
+}
+
+impl<T: Ticks> BoundedTicks<T> {
+    pub fn new(value: T, min: T, max: T) -> Self {
+        let bounded = BoundedTicks { value, min, max };
+        bounded.check();
+        bounded
+    }
+
+    pub fn value(self) -> T {
+        self.value
+    }
+
+    pub fn min(self) -> T {
+        self.min
+    }
+
+    pub fn max(self) -> T {
+        self.max
+    }
+
+    /// `Ticks::wrapping_add` of the inner value, keeping the same band.
+    pub fn wrapping_add(self, other: T) -> Self {
+        Self::new(self.value.wrapping_add(other), self.min, self.max)
+    }
+
+    /// `Ticks::wrapping_sub` of the inner value, keeping the same band.
+    pub fn wrapping_sub(self, other: T) -> Self {
+        Self::new(self.value.wrapping_sub(other), self.min, self.max)
+    }
+
+    fn check(&self) {
+        debug_assert!(
+            self.min <= self.value && self.value <= self.max,
+            "tick value {} outside of [{}, {}]",
+            self.value.into_u64(),
+            self.min.into_u64(),
+            self.max.into_u64()
+        );
+    }
+}
+
+/// Returns an iterator over `start`, `start + step`, `start + 2 * step`,
+/// ... for every value in the wraparound-aware range `[start, end)`, in
+/// the same sense as `Ticks::within_range`. The range may cross the
+/// wrap point of `T`. If `step` is 0 only `start` is returned, and if
+/// `start == end` the range is empty.
+pub fn tick_range<T: Ticks>(start: T, end: T, step: T) -> impl Iterator<Item = T> {
+    let len = T::range_len(start, end).into_u64();
+    let step = step.into_u64();
+    let count = match (len, step) {
+        (0, _) => 0,
+        (_, 0) => 1,
+        (len, step) => (len - 1) / step + 1,
+    };
+    // `i * step < len`, so the offset fits in `T`.
+    (0..count).map(move |i| start.wrapping_add(T::from_or_max(i * step)))
+}
+
+/// Returns the deadline which comes first after `now`, i.e. the one with
+/// the smallest forward distance `deadline - now` in wrapping arithmetic,
+/// or `None` if `deadlines` is empty. A deadline equal to `now` is the
+/// earliest possible. A deadline which has already passed is a whole
+/// period minus its lateness ahead, so expired deadlines should be
+/// handled before calling this.
+pub fn earliest_deadline<T: Ticks>(now: T, deadlines: &[T]) -> Option<T> {
+    deadlines
+        .iter()
+        .copied()
+        .min_by_key(|deadline| deadline.wrapping_sub(now))
+}
+
+/// Returns the smallest of `vals`, or `None` if it is empty. Values are
+/// compared as plain integers rather than with wraparound, as they are
+/// intervals (e.g. deltas between events), not timestamps.
+pub fn ticks_min<T: Ticks>(vals: &[T]) -> Option<T> {
+    vals.iter().copied().min()
+}
+
+/// Returns the largest of `vals`, or `None` if it is empty. Values are
+/// compared as plain integers, like in `ticks_min`.
+pub fn ticks_max<T: Ticks>(vals: &[T]) -> Option<T> {
+    vals.iter().copied().max()
+}
+
+/// Returns the median of `vals`, or `None` if it is empty. Values are
+/// compared as plain integers, like in `ticks_min`. For an even number of
+/// values this is the mean of the two middle ones, rounded down. `vals`
+/// is not reordered, so this takes time quadratic in its length.
+pub fn ticks_median<T: Ticks>(vals: &[T]) -> Option<T> {
+    if vals.is_empty() {
+        return None;
+    }
+    let upper = nth_smallest(vals, vals.len() / 2).into_u64();
+    let lower = nth_smallest(vals, (vals.len() - 1) / 2).into_u64();
+    // Both halves fit in `T`, and so does their sum.
+    Some(T::from_or_max(
+        lower / 2 + upper / 2 + (lower % 2 + upper % 2) / 2,
+    ))
+}
+
+/// Returns the value which would be at index `n` of `vals` if it were
+/// sorted. `n` must be less than `vals.len()`.
+fn nth_smallest<T: Ticks>(vals: &[T], n: usize) -> T {
+    vals.iter()
+        .copied()
+        .find(|&val| {
+            let less = vals.iter().filter(|&&other| other < val).count();
+            let equal = vals.iter().filter(|&&other| other == val).count();
+            less <= n && n < less + equal
+        })
+        .unwrap_or(T::ZERO)
+}
+
+/// Adds up a sequence of intervals, e.g. the lengths of the phases of a
+/// schedule. Returns `None` if the total does not fit in the width of
+/// `T`, rather than wrapping like `Ticks::wrapping_add`. The sum of an
+/// empty sequence is 0.
+pub fn sum_ticks<T: Ticks, I: Iterator<Item = T>>(mut iter: I) -> Option<T> {
+    let max = T::max_value().into_u64();
+    iter.try_fold(0u64, |sum, ticks| {
+        sum.checked_add(ticks.into_u64()).filter(|&sum| sum <= max)
+    })
+    .map(T::from_or_max)
+}
+
+/// Returns the greatest common divisor of the frequencies `A` and `B`,
+/// i.e. the highest rate at which both clocks complete a whole number of
+/// ticks.
+pub fn frequency_gcd<A: Frequency, B: Frequency>() -> u32 {
+    let (mut a, mut b) = (A::frequency(), B::frequency());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Returns the least common multiple of the frequencies `A` and `B`,
+/// i.e. the lowest rate of a clock whose ticks include every tick of both,
+/// saturating at `u32::MAX`. Returns 0 if either frequency is 0.
+pub fn frequency_lcm<A: Frequency, B: Frequency>() -> u32 {
+    match frequency_gcd::<A, B>() {
+        0 => 0,
+        gcd => (A::frequency() / gcd)
+            .checked_mul(B::frequency())
+            .unwrap_or(u32::MAX),
+    }
+}
+
+/// Floating point conversions of `Ticks`, for host-side analysis such as
+/// plotting. This is an extension trait rather than part of `Ticks`, and
+/// its methods are generic, so no floating point code is generated unless
+/// they are used.
+pub trait TicksToFloat: Ticks {
+    /// Converts ticks of a clock with frequency `F` into seconds. Values
+    /// with more than 24 significant bits lose precision.
+    fn as_f32_seconds<F: Frequency>(self) -> f32 {
+        self.into_u64() as f32 / F::frequency() as f32
+    }
+}
+
+impl<T: Ticks> TicksToFloat for T {}
+
 /// Represents a static moment in time, that does not change over
 /// repeated calls to `Time::now`.
 pub trait Timestamp: Time {}
@@ -162,12 +888,20 @@ pub trait OverflowClient {
 }
 
 /// Represents a free-running hardware counter that can be started and stopped.
+///
+/// `Counter` is object safe. Code holding a `&dyn Counter` can query its
+/// rate with the supertrait method `Time::frequency_hz`.
 pub trait Counter<'a>: Time {
     /// Specify the callback for when the counter overflows its maximum
     /// value (defined by `Ticks`). If there was a previously registered
     /// callback this call replaces it.
     fn set_overflow_client(&self, client: &'a dyn OverflowClient);
 
+    /// Remove the overflow callback, if one is registered. After this call
+    /// returns, the previous client MUST NOT be called again, even for an
+    /// overflow which occurred or was pending before it was cleared.
+    fn clear_overflow_client(&self);
+
     /// Starts the free-running hardware counter. Valid `Result<(), ErrorCode>` values are:
     ///   - `Ok(())`: the counter is now running
     ///   - `Err(ErrorCode::OFF)`: underlying clocks or other hardware resources
@@ -196,6 +930,73 @@ pub trait Counter<'a>: Time {
 
     /// Returns whether the counter is currently running.
     fn is_running(&self) -> bool;
+
+    /// Returns the number of ticks between two overflows, i.e., how often
+    /// the overflow client is called while the counter runs. A counter
+    /// overflows once per full count, so this is `Ticks::max_value()`
+    /// (the period is one tick more, which this does not represent).
+    fn overflow_period(&self) -> Self::Ticks {
+        Self::Ticks::max_value()
+    }
+
+    /// Returns the time between two overflows in microseconds, counting
+    /// the full `max_value() + 1` ticks and rounding down. Saturates at
+    /// `u64::MAX`.
+    fn overflow_period_us(&self) -> u64 {
+        let period = self.overflow_period().into_u64() as u128 + 1;
+        let us = period * 1_000_000 / Self::Frequency::frequency() as u128;
+        u64::try_from(us).unwrap_or(u64::MAX)
+    }
+}
+
+/// A `Counter` which also keeps count of its overflows, e.g. in software
+/// from its overflow interrupt, to extend its range.
+pub trait CounterWithOverflow<'a>: Counter<'a> {
+    /// Returns the number of times the counter has overflowed.
+    fn overflow_count(&self) -> u64;
+
+    /// Returns the counter value together with the overflow count at the
+    /// moment it was read, so that `count * (max_value() + 1) + value` is
+    /// a consistent extended reading.
+    ///
+    /// Reading the value and the count separately is racy: if the counter
+    /// overflows, and the count is updated, between the two reads, the
+    /// pair is off by a whole period. This reads the count, then the
+    /// value, then the count again, and retries until both counts are
+    /// equal, i.e. until no overflow was counted while the value was
+    /// read. Since a counter overflows once per period, this retries at
+    /// most once in practice.
+    ///
+    /// An overflow is only noticed once `overflow_count` reflects it, so
+    /// this must not be called while the overflow is pending but cannot
+    /// be counted yet (e.g. with its interrupt masked).
+    fn now_with_overflow(&self) -> (Self::Ticks, u64) {
+        loop {
+            let before = self.overflow_count();
+            let now = self.now();
+            if self.overflow_count() == before {
+                return (now, before);
+            }
+        }
+    }
+}
+
+/// Returns a seed for a pseudo-random number generator, derived from the
+/// current value of a free-running counter, e.g. to jitter retries.
+/// The value is mixed with the MurmurHash3 finalizer so that readings
+/// which differ only in their low bits give unrelated seeds.
+///
+/// This is NOT a source of cryptographic randomness: the counter value is
+/// predictable by anyone who knows roughly when it was sampled. Use the
+/// `hil::entropy` traits for anything security-related.
+pub fn counter_entropy<'a, C: Counter<'a>>(counter: &C) -> u32 {
+    let now = counter.now().into_u64();
+    let mut x = (now ^ (now >> 32)) as u32;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x85EB_CA6B);
+    x ^= x >> 13;
+    x = x.wrapping_mul(0xC2B2_AE35);
+    x ^ (x >> 16)
 }
 
 /// Callback handler for when an Alarm fires (a `Counter` reaches a specific
@@ -222,6 +1023,12 @@ pub trait Alarm<'a>: Time {
     /// replaces it.
     fn set_alarm_client(&self, client: &'a dyn AlarmClient);
 
+    /// Remove the alarm callback, if one is registered. This does not
+    /// disarm the alarm, but after this call returns the previous client
+    /// MUST NOT be called again, even if the alarm already expired and its
+    /// callback is pending.
+    fn clear_alarm_client(&self);
+
     /// Specify when the callback should be called and enable it. The
     /// callback will be enqueued when `Time::now() == reference + dt`. The
     /// callback itself may not run exactly at this time, due to delays.
@@ -232,6 +1039,43 @@ pub trait Alarm<'a>: Time {
     /// passed and those in the far far future (see #1651).
     fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks);
 
+    /// Like `set_alarm`, but returns the `dt` actually programmed: `dt`
+    /// increased to `minimum_dt()` if it was smaller. The alarm fires at
+    /// `reference` plus the returned value.
+    fn set_alarm_checked(&self, reference: Self::Ticks, dt: Self::Ticks) -> Self::Ticks {
+        let dt = core::cmp::max(dt, self.minimum_dt());
+        self.set_alarm(reference, dt);
+        dt
+    }
+
+    /// Like `set_alarm`, but treats a `dt` of more than
+    /// `half_max_value()` as a deadline which has already passed and sets
+    /// the alarm to fire as soon as possible, `minimum_dt()` from now,
+    /// instead. Such a `dt` typically comes from computing
+    /// `deadline.wrapping_sub(now)` for a deadline just behind `now`,
+    /// which would otherwise schedule the alarm almost a whole period
+    /// later. The cost of this heuristic is that deadlines genuinely more
+    /// than half a period away cannot be set with this method.
+    fn set_alarm_clamped(&self, reference: Self::Ticks, dt: Self::Ticks) {
+        if dt > Self::Ticks::half_max_value() {
+            self.set_alarm(self.now(), self.minimum_dt());
+        } else {
+            self.set_alarm(reference, dt);
+        }
+    }
+
+    /// Set the alarm to fire at the absolute counter value `deadline`.
+    /// This is `set_alarm(now, deadline - now)` with `now()` read once.
+    /// Because `deadline` is interpreted relative to the current time it
+    /// is always taken to be in the future, up to one full period of
+    /// `Self::Ticks` away: a deadline which has just passed is scheduled
+    /// almost a whole period later rather than firing immediately. Use
+    /// `set_alarm` when the deadline may already have passed.
+    fn set_alarm_at(&self, deadline: Self::Ticks) {
+        let now = self.now();
+        self.set_alarm(now, deadline.wrapping_sub(now));
+    }
+
     /// Return the current alarm value. This is undefined at boot and
     /// otherwise returns `now + dt` from the last call to `set_alarm`.
     fn get_alarm(&self) -> Self::Ticks;
@@ -255,6 +1099,24 @@ pub trait Alarm<'a>: Time {
     /// Return the minimum dt value that is supported. Any dt smaller than
     /// this will automatically be increased to this minimum value.
     fn minimum_dt(&self) -> Self::Ticks;
+
+    /// Returns whether `now()` has reached or passed the deadline
+    /// returned by `get_alarm()`, considering wraparound. This allows
+    /// polling for an alarm without relying on the callback. The result
+    /// is only meaningful while the alarm is armed and the deadline is
+    /// less than half the range of `Self::Ticks` away; if the alarm is
+    /// not armed this returns `false`.
+    fn is_expired_now(&self) -> bool {
+        self.is_armed() && self.now().cmp_wrapping(self.get_alarm()) != Ordering::Less
+    }
+
+    /// Returns the duration of one tick of the underlying counter in
+    /// nanoseconds, i.e. the granularity at which deadlines can be
+    /// expressed. This is independent of `minimum_dt`, which bounds how
+    /// soon an alarm can be set rather than how precisely.
+    fn tick_resolution_ns(&self) -> u64 {
+        Self::Frequency::period_ns()
+    }
 }
 
 /// Callback handler for when a timer fires.
@@ -262,6 +1124,22 @@ pub trait TimerClient {
     fn timer(&self);
 }
 
+/// What a timer was doing when it was cancelled with
+/// `Timer::cancel_and_status`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CancelStatus {
+    /// The timer was running and its interval had not elapsed yet. No
+    /// callback is delivered for it.
+    Cancelled,
+    /// The interval had already elapsed. For a one-shot timer this means
+    /// its callback has been delivered. If the callback was due but not
+    /// delivered yet, it is not delivered after the cancellation.
+    Expired,
+    /// The timer was not running and had not fired since it was last
+    /// started, e.g. because it was never started or already cancelled.
+    NotRunning,
+}
+
 /// Interface for controlling callbacks when an interval has passed.
 /// This interface is intended for software that requires repeated
 /// and/or one-shot timers and is willing to experience some jitter or
@@ -287,6 +1165,11 @@ pub trait Timer<'a>: Time {
     /// pending, calling this cancels that previous timer.
     /// Returns the actual interval for the timer that was registered.
     /// This MUST NOT be smaller than `interval` but MAY be larger.
+    ///
+    /// If a callback is delayed past one or more later deadlines, those
+    /// intervals are not delivered as extra catch-up callbacks: the next
+    /// callback is at the first deadline after the delayed one, and
+    /// `fire_count` reports how many intervals had elapsed.
     fn repeating(&self, interval: Self::Ticks) -> Self::Ticks;
 
     /// Return the interval of the last requested timer.
@@ -307,6 +1190,15 @@ pub trait Timer<'a>: Time {
     /// calculated it right before the call to start the timer.
     fn time_remaining(&self) -> Option<Self::Ticks>;
 
+    /// Return the absolute counter value at which the next callback is
+    /// expected, or None if the timer is disabled. This is `now()` plus
+    /// `time_remaining()`, so for a timer whose callback is overdue it is
+    /// the current time rather than the missed deadline.
+    fn next_deadline(&self) -> Option<Self::Ticks> {
+        self.time_remaining()
+            .map(|remaining| self.now().wrapping_add(remaining))
+    }
+
     /// Returns whether there is currently a timer enabled and so a callback
     /// will be expected in the future. If `is_enabled` returns false then
     /// the implementation MUST NOT invoke a callback until a call to `oneshot`
@@ -318,6 +1210,28 @@ pub trait Timer<'a>: Time {
     ///  - `Err(ErrorCode::FAIL)`: the timer could not be cancelled and a callback
     ///  will be invoked in the future.
     fn cancel(&self) -> Result<(), ErrorCode>;
+
+    /// Cancel the current timer like `cancel`, and report whether its
+    /// interval had already elapsed, so that a client can tell whether it
+    /// has already handled (or missed) the expiry.
+    fn cancel_and_status(&self) -> Result<CancelStatus, ErrorCode> {
+        let status = match self.time_remaining() {
+            None if self.fire_count() > 0 => CancelStatus::Expired,
+            None => CancelStatus::NotRunning,
+            Some(remaining) if remaining == Self::Ticks::from(0) => CancelStatus::Expired,
+            Some(_) => CancelStatus::Cancelled,
+        };
+        self.cancel()?;
+        Ok(status)
+    }
+
+    /// Return the number of intervals that had elapsed when the last
+    /// callback was invoked. This is normally 1, but if the callback of
+    /// a repeating timer was delayed by more than one interval, the
+    /// missed intervals are reported here rather than through additional
+    /// callbacks. Returns 0 if there has been no callback since the timer
+    /// was last started with `oneshot` or `repeating`.
+    fn fire_count(&self) -> u32;
 }
 
 // The following "frequencies" are represented as variant-less enums. Because
@@ -332,6 +1246,10 @@ impl Frequency for Freq100MHz {
         100_000_000
     }
 }
+impl FrequencyConsts for Freq100MHz {
+    const TICKS_PER_MS: u32 = ticks_per_ms(100_000_000);
+    const TICKS_PER_US: u32 = ticks_per_us(100_000_000);
+}
 
 /// 16MHz `Frequency`
 #[derive(Debug)]
@@ -341,6 +1259,10 @@ impl Frequency for Freq16MHz {
         16_000_000
     }
 }
+impl FrequencyConsts for Freq16MHz {
+    const TICKS_PER_MS: u32 = ticks_per_ms(16_000_000);
+    const TICKS_PER_US: u32 = ticks_per_us(16_000_000);
+}
 
 /// 10MHz `Frequency`
 pub enum Freq10MHz {}
@@ -349,6 +1271,10 @@ impl Frequency for Freq10MHz {
         10_000_000
     }
 }
+impl FrequencyConsts for Freq10MHz {
+    const TICKS_PER_MS: u32 = ticks_per_ms(10_000_000);
+    const TICKS_PER_US: u32 = ticks_per_us(10_000_000);
+}
 
 /// 1MHz `Frequency`
 #[derive(Debug)]
@@ -358,6 +1284,10 @@ impl Frequency for Freq1MHz {
         1_000_000
     }
 }
+impl FrequencyConsts for Freq1MHz {
+    const TICKS_PER_MS: u32 = ticks_per_ms(1_000_000);
+    const TICKS_PER_US: u32 = ticks_per_us(1_000_000);
+}
 
 /// 32.768KHz `Frequency`
 #[derive(Debug)]
@@ -367,6 +1297,10 @@ impl Frequency for Freq32KHz {
         32_768
     }
 }
+impl FrequencyConsts for Freq32KHz {
+    const TICKS_PER_MS: u32 = ticks_per_ms(32_768);
+    const TICKS_PER_US: u32 = ticks_per_us(32_768);
+}
 
 /// 16KHz `Frequency`
 #[derive(Debug)]
@@ -376,6 +1310,10 @@ impl Frequency for Freq16KHz {
         16_000
     }
 }
+impl FrequencyConsts for Freq16KHz {
+    const TICKS_PER_MS: u32 = ticks_per_ms(16_000);
+    const TICKS_PER_US: u32 = ticks_per_us(16_000);
+}
 
 /// 1KHz `Frequency`
 #[derive(Debug)]
@@ -385,6 +1323,57 @@ impl Frequency for Freq1KHz {
         1_000
     }
 }
+impl FrequencyConsts for Freq1KHz {
+    const TICKS_PER_MS: u32 = ticks_per_ms(1_000);
+    const TICKS_PER_US: u32 = ticks_per_us(1_000);
+}
+
+/// `Frequency` of `F` divided by `DIV`, rounding down, e.g. for a clock
+/// behind a prescaler: `DividedFreq<Freq16MHz, 8>` is 2MHz. A `DIV` of 0
+/// is rejected at compile time.
+#[derive(Debug)]
+pub struct DividedFreq<F: Frequency, const DIV: u32>(PhantomData<F>);
+impl<F: Frequency, const DIV: u32> DividedFreq<F, DIV> {
+    /// `DIV`, checked to be nonzero when the constant is evaluated.
+    const DIVISOR: u32 = {
+        assert!(DIV != 0, "DividedFreq divisor must not be 0");
+        DIV
+    };
+}
+impl<F: Frequency, const DIV: u32> Frequency for DividedFreq<F, DIV> {
+    fn frequency() -> u32 {
+        F::frequency() / Self::DIVISOR
+    }
+}
+
+/// `Frequency` of `F` multiplied by `MUL`, e.g. for a PLL output. The
+/// result saturates at `u32::MAX` Hz.
+#[derive(Debug)]
+pub struct MultipliedFreq<F: Frequency, const MUL: u32>(PhantomData<F>);
+impl<F: Frequency, const MUL: u32> Frequency for MultipliedFreq<F, MUL> {
+    fn frequency() -> u32 {
+        F::frequency().saturating_mul(MUL)
+    }
+}
+
+/// Copies `bytes` to the start of `out` if it fits, returning the number
+/// of bytes copied.
+fn write_le_bytes(bytes: &[u8], out: &mut [u8]) -> usize {
+    match out.get_mut(..bytes.len()) {
+        Some(dst) => {
+            dst.copy_from_slice(bytes);
+            bytes.len()
+        }
+        None => 0,
+    }
+}
+
+/// Fills `buf` from the start of `bytes`, leaving bytes of `buf` beyond
+/// the end of `bytes` unchanged.
+fn read_le_bytes(bytes: &[u8], buf: &mut [u8]) {
+    let len = core::cmp::min(bytes.len(), buf.len());
+    buf[..len].copy_from_slice(&bytes[..len]);
+}
 
 /// u32 `Ticks`
 #[derive(Clone, Copy, Debug)]
@@ -397,6 +1386,10 @@ impl From<u32> for Ticks32 {
 }
 
 impl Ticks for Ticks32 {
+    const ZERO: Self = Ticks32(0);
+    const ONE: Self = Ticks32(1);
+    const MAX: Self = Ticks32(u32::MAX);
+
     fn into_usize(self) -> usize {
         self.0 as usize
     }
@@ -405,6 +1398,10 @@ impl Ticks for Ticks32 {
         self.0
     }
 
+    fn into_u64(self) -> u64 {
+        self.0 as u64
+    }
+
     fn wrapping_add(self, other: Self) -> Self {
         Ticks32(self.0.wrapping_add(other.0))
     }
@@ -445,6 +1442,44 @@ impl Ticks for Ticks32 {
             u32::MAX
         }
     }
+
+    fn leading_zeros(self) -> u32 {
+        self.0.leading_zeros()
+    }
+
+    fn significant_bits(self) -> u32 {
+        32 - self.leading_zeros()
+    }
+
+    fn count_ones(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    fn trailing_zeros(self) -> u32 {
+        self.0.trailing_zeros()
+    }
+
+    fn wrapping_mod(self, period: Self) -> Self {
+        Self(self.0.checked_rem(period.0).unwrap_or(self.0))
+    }
+
+    fn canonicalize(self) -> Self {
+        self
+    }
+
+    fn to_le_bytes(self, out: &mut [u8]) -> usize {
+        write_le_bytes(&self.0.to_le_bytes(), out)
+    }
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        let mut buf = [0; 4];
+        read_le_bytes(bytes, &mut buf);
+        Ticks32(u32::from_le_bytes(buf))
+    }
+
+    fn checked_mul(self, factor: u32) -> Option<Self> {
+        self.0.checked_mul(factor).map(Ticks32)
+    }
 }
 
 impl PartialOrd for Ticks32 {
@@ -473,11 +1508,15 @@ pub struct Ticks24(u32);
 
 impl From<u32> for Ticks24 {
     fn from(val: u32) -> Self {
-        Ticks24(val)
+        Ticks24(val).canonicalize()
     }
 }
 
 impl Ticks for Ticks24 {
+    const ZERO: Self = Ticks24(0);
+    const ONE: Self = Ticks24(1);
+    const MAX: Self = Ticks24(0x00FF_FFFF);
+
     fn into_usize(self) -> usize {
         self.0 as usize
     }
@@ -486,6 +1525,10 @@ impl Ticks for Ticks24 {
         self.0
     }
 
+    fn into_u64(self) -> u64 {
+        self.0 as u64
+    }
+
     fn wrapping_add(self, other: Self) -> Self {
         Ticks24(self.0.wrapping_add(other.0) & 0x00FFFFFF)
     }
@@ -526,6 +1569,50 @@ impl Ticks for Ticks24 {
             u32::MAX
         }
     }
+
+    fn leading_zeros(self) -> u32 {
+        (self.0 & 0x00FFFFFF).leading_zeros() - 8
+    }
+
+    fn significant_bits(self) -> u32 {
+        24 - self.leading_zeros()
+    }
+
+    fn count_ones(self) -> u32 {
+        (self.0 & 0x00FFFFFF).count_ones()
+    }
+
+    fn trailing_zeros(self) -> u32 {
+        core::cmp::min((self.0 & 0x00FFFFFF).trailing_zeros(), 24)
+    }
+
+    fn wrapping_mod(self, period: Self) -> Self {
+        let val = self.0 & 0x00FFFFFF;
+        Self(val.checked_rem(period.0 & 0x00FFFFFF).unwrap_or(val))
+    }
+
+    fn canonicalize(self) -> Self {
+        Ticks24(self.0 & 0x00FFFFFF)
+    }
+
+    fn to_le_bytes(self, out: &mut [u8]) -> usize {
+        write_le_bytes(&(self.0 & 0x00FFFFFF).to_le_bytes()[..3], out)
+    }
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        let mut buf = [0; 4];
+        read_le_bytes(bytes, &mut buf[..3]);
+        Ticks24(u32::from_le_bytes(buf)).canonicalize()
+    }
+
+    fn checked_mul(self, factor: u32) -> Option<Self> {
+        let product = self.0 as u64 * factor as u64;
+        if product <= Self::max_value().0 as u64 {
+            Some(Ticks24(product as u32))
+        } else {
+            None
+        }
+    }
 }
 
 impl PartialOrd for Ticks24 {
@@ -571,6 +1658,10 @@ impl Ticks16 {
 }
 
 impl Ticks for Ticks16 {
+    const ZERO: Self = Ticks16(0);
+    const ONE: Self = Ticks16(1);
+    const MAX: Self = Ticks16(u16::MAX);
+
     fn into_usize(self) -> usize {
         self.0 as usize
     }
@@ -579,6 +1670,10 @@ impl Ticks for Ticks16 {
         self.0 as u32
     }
 
+    fn into_u64(self) -> u64 {
+        self.0 as u64
+    }
+
     fn wrapping_add(self, other: Self) -> Self {
         Ticks16(self.0.wrapping_add(other.0))
     }
@@ -619,11 +1714,52 @@ impl Ticks for Ticks16 {
             u32::MAX
         }
     }
-}
 
-impl PartialOrd for Ticks16 {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    fn leading_zeros(self) -> u32 {
+        self.0.leading_zeros()
+    }
+
+    fn significant_bits(self) -> u32 {
+        16 - self.leading_zeros()
+    }
+
+    fn count_ones(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    fn trailing_zeros(self) -> u32 {
+        self.0.trailing_zeros()
+    }
+
+    fn wrapping_mod(self, period: Self) -> Self {
+        Self(self.0.checked_rem(period.0).unwrap_or(self.0))
+    }
+
+    fn canonicalize(self) -> Self {
+        self
+    }
+
+    fn to_le_bytes(self, out: &mut [u8]) -> usize {
+        write_le_bytes(&self.0.to_le_bytes(), out)
+    }
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        let mut buf = [0; 2];
+        read_le_bytes(bytes, &mut buf);
+        Ticks16(u16::from_le_bytes(buf))
+    }
+
+    fn checked_mul(self, factor: u32) -> Option<Self> {
+        (self.0 as u32)
+            .checked_mul(factor)
+            .and_then(|product| u16::try_from(product).ok())
+            .map(Ticks16)
+    }
+}
+
+impl PartialOrd for Ticks16 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -645,12 +1781,6 @@ impl Eq for Ticks16 {}
 #[derive(Clone, Copy, Debug)]
 pub struct Ticks64(u64);
 
-impl Ticks64 {
-    pub fn into_u64(self) -> u64 {
-        self.0
-    }
-}
-
 impl From<u32> for Ticks64 {
     fn from(val: u32) -> Self {
         Ticks64(val as u64)
@@ -663,7 +1793,24 @@ impl From<u64> for Ticks64 {
     }
 }
 
+impl Ticks64 {
+    /// Builds a value from its high and low 32-bit words, e.g. from a
+    /// 64-bit timestamp stored as two words.
+    pub fn from_parts(high: u32, low: u32) -> Ticks64 {
+        Ticks64(((high as u64) << 32) | low as u64)
+    }
+
+    /// Returns the high and low 32-bit words of the value.
+    pub fn split(self) -> (u32, u32) {
+        ((self.0 >> 32) as u32, self.0 as u32)
+    }
+}
+
 impl Ticks for Ticks64 {
+    const ZERO: Self = Ticks64(0);
+    const ONE: Self = Ticks64(1);
+    const MAX: Self = Ticks64(u64::MAX);
+
     fn into_usize(self) -> usize {
         self.0 as usize
     }
@@ -672,6 +1819,10 @@ impl Ticks for Ticks64 {
         self.0 as u32
     }
 
+    fn into_u64(self) -> u64 {
+        self.0
+    }
+
     fn wrapping_add(self, other: Self) -> Self {
         Ticks64(self.0.wrapping_add(other.0))
     }
@@ -708,6 +1859,44 @@ impl Ticks for Ticks64 {
             u32::MAX
         }
     }
+
+    fn leading_zeros(self) -> u32 {
+        self.0.leading_zeros()
+    }
+
+    fn significant_bits(self) -> u32 {
+        64 - self.leading_zeros()
+    }
+
+    fn count_ones(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    fn trailing_zeros(self) -> u32 {
+        self.0.trailing_zeros()
+    }
+
+    fn wrapping_mod(self, period: Self) -> Self {
+        Self(self.0.checked_rem(period.0).unwrap_or(self.0))
+    }
+
+    fn canonicalize(self) -> Self {
+        self
+    }
+
+    fn to_le_bytes(self, out: &mut [u8]) -> usize {
+        write_le_bytes(&self.0.to_le_bytes(), out)
+    }
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        let mut buf = [0; 8];
+        read_le_bytes(bytes, &mut buf);
+        Ticks64(u64::from_le_bytes(buf))
+    }
+
+    fn checked_mul(self, factor: u32) -> Option<Self> {
+        self.0.checked_mul(factor as u64).map(Ticks64)
+    }
 }
 
 impl PartialOrd for Ticks64 {
@@ -730,9 +1919,35 @@ impl PartialEq for Ticks64 {
 
 impl Eq for Ticks64 {}
 
+/// Wrapper which orders `Ticks` values considering wraparound, so that
+/// deadlines can be compared with operators, e.g. `Wrapping(deadline) >
+/// Wrapping(now)`. The ordering is `Ticks::cmp_wrapping`: a value is
+/// greater than another if it is less than half the range of the type
+/// ahead of it.
+///
+/// This is only meaningful for values known to be within half the range
+/// of the type of each other, such as a deadline and the current time.
+/// Across a wider set of values the ordering is not transitive, so
+/// `Wrapping` must not be used to sort or to key ordered collections.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Wrapping<T: Ticks>(pub T);
+
+impl<T: Ticks> PartialOrd for Wrapping<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ticks> Ord for Wrapping<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp_wrapping(other.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utilities::cells::OptionalCell;
 
     struct Test1MHz64();
     impl Time for Test1MHz64 {
@@ -780,6 +1995,38 @@ mod tests {
         assert_eq!(t.into_u64(), 1_000_000u64 << 31);
     }
 
+    #[derive(Debug, Eq, PartialEq)]
+    struct FreqMax;
+    impl Frequency for FreqMax {
+        fn frequency() -> u32 {
+            u32::MAX
+        }
+    }
+
+    struct TestMaxHz<T: Ticks>(PhantomData<T>);
+    impl<T: Ticks> Time for TestMaxHz<T> {
+        type Frequency = FreqMax;
+        type Ticks = T;
+
+        fn now(&self) -> Self::Ticks {
+            0u32.into()
+        }
+    }
+
+    #[test]
+    fn test_to_ticks_high_frequency() {
+        let time = TestMaxHz::<Ticks32>(PhantomData);
+        assert_eq!(time.ticks_from_seconds(u32::MAX), Ticks32::max_value());
+        assert_eq!(time.ticks_from_ms(u32::MAX), Ticks32::max_value());
+        assert_eq!(time.ticks_from_us(u32::MAX), Ticks32::max_value());
+        assert_eq!(time.ticks_from_us(1), Ticks32::from(4294u32));
+
+        let time = TestMaxHz::<Ticks64>(PhantomData);
+        let max = u32::MAX as u64;
+        assert_eq!(time.ticks_from_seconds(u32::MAX).into_u64(), max * max);
+        assert_eq!(time.ticks_from_ms(u32::MAX).into_u64(), max * max / 1_000);
+    }
+
     struct Test1KHz16();
     impl Time for Test1KHz16 {
         type Frequency = Freq1KHz;
@@ -858,4 +2105,1264 @@ mod tests {
         let us = time.ticks_to_us(5_000_000u32.into());
         assert_eq!(us, u32::MAX);
     }
+
+    #[test]
+    fn test_cmp_wrapping() {
+        let zero = Ticks16::from(0x0000u16);
+        let max = Ticks16::from(0xFFFFu16);
+
+        // Absolute comparison is unchanged.
+        assert_eq!(zero.cmp(&max), Ordering::Less);
+
+        // 0x0000 is one tick after 0xFFFF.
+        assert_eq!(zero.cmp_wrapping(max), Ordering::Greater);
+        assert_eq!(max.cmp_wrapping(zero), Ordering::Less);
+        assert_eq!(zero.cmp_wrapping(zero), Ordering::Equal);
+
+        let a = Ticks32::from(100u32);
+        let b = Ticks32::from(200u32);
+        assert_eq!(a.cmp_wrapping(b), Ordering::Less);
+        assert_eq!(b.cmp_wrapping(a), Ordering::Greater);
+    }
+
+    struct Test1KHz32();
+    impl Time for Test1KHz32 {
+        type Frequency = Freq1KHz;
+        type Ticks = Ticks32;
+
+        fn now(&self) -> Self::Ticks {
+            0u32.into()
+        }
+    }
+
+    #[test]
+    fn test_max_interval() {
+        assert_eq!(Test1KHz32().max_interval().into_u32(), 0x8000_0000);
+        assert_eq!(Test1KHz24().max_interval().into_u32(), 0x0080_0000);
+    }
+
+    #[test]
+    fn test_abs_difference() {
+        let (a, b) = (Ticks16::from(10u16), Ticks16::from(0xFFF0u16));
+        assert_eq!(a.abs_difference(b).into_u32(), 0xFFE6);
+        assert_eq!(b.abs_difference(a).into_u32(), 0xFFE6);
+        assert_eq!(a.abs_difference(a).into_u32(), 0);
+
+        let (a, b) = (Ticks24::from(5u32), Ticks24::from(0x00FF_FFFFu32));
+        assert_eq!(a.abs_difference(b).into_u32(), 0x00FF_FFFA);
+        assert_eq!(b.abs_difference(a).into_u32(), 0x00FF_FFFA);
+        assert_eq!(b.abs_difference(b).into_u32(), 0);
+
+        let (a, b) = (Ticks32::from(1_000u32), Ticks32::from(250u32));
+        assert_eq!(a.abs_difference(b).into_u32(), 750);
+        assert_eq!(b.abs_difference(a).into_u32(), 750);
+        assert_eq!(a.abs_difference(a).into_u32(), 0);
+
+        let (a, b) = (Ticks64::from(1u64 << 40), Ticks64::from(1u64));
+        assert_eq!(a.abs_difference(b).into_u64(), (1u64 << 40) - 1);
+        assert_eq!(b.abs_difference(a).into_u64(), (1u64 << 40) - 1);
+        assert_eq!(b.abs_difference(b).into_u64(), 0);
+    }
+
+    #[test]
+    fn test_ticks_to_duration() {
+        let d = ticks_to_duration::<Freq32KHz, _>(Ticks32::from(32_768u32));
+        assert_eq!(d, Duration::from_secs(1));
+
+        let d = ticks_to_duration::<Freq32KHz, _>(Ticks32::from(32_768u32 * 3 / 2));
+        assert_eq!(d, Duration::from_millis(1_500));
+
+        // 1 tick is 30517.578125ns, rounded down.
+        let d = ticks_to_duration::<Freq32KHz, _>(Ticks16::from(1u16));
+        assert_eq!(d, Duration::from_nanos(30_517));
+
+        let d = ticks_to_duration::<Freq100MHz, _>(Ticks32::from(1u32));
+        assert_eq!(d, Duration::from_nanos(10));
+
+        let d = ticks_to_duration::<Freq100MHz, _>(Ticks32::from(150u32));
+        assert_eq!(d, Duration::from_nanos(1_500));
+
+        let d = ticks_to_duration::<Freq100MHz, _>(Ticks64::from(250_000_005u64));
+        assert_eq!(d, Duration::new(2, 500_000_050));
+
+        // Past `u32::MAX` seconds.
+        let d = ticks_to_duration::<Freq1KHz, _>(Ticks64::from(5_000_000_000_123u64));
+        assert_eq!(d, Duration::new(5_000_000_000, 123_000_000));
+    }
+
+    #[test]
+    fn test_ticks_from_hz() {
+        let t: Ticks32 = ticks_from_hz(1_000_000, Duration::from_millis(1500));
+        assert_eq!(t, Ticks32::from(1_500_000u32));
+        let t: Ticks32 = ticks_from_hz(32_768, Duration::from_micros(1));
+        assert_eq!(t, Ticks32::from(0u32));
+        let t: Ticks32 = ticks_from_hz(32_768, Duration::from_millis(1));
+        assert_eq!(t, Ticks32::from(32u32));
+
+        // Clamped to the width of the type.
+        let t: Ticks16 = ticks_from_hz(1_000, Duration::from_secs(66));
+        assert_eq!(t, Ticks16::max_value());
+        let t: Ticks32 = ticks_from_hz(u32::MAX, Duration::MAX);
+        assert_eq!(t, Ticks32::max_value());
+        let t: Ticks64 = ticks_from_hz(u32::MAX, Duration::MAX);
+        assert_eq!(t, Ticks64::max_value());
+    }
+
+    #[test]
+    fn test_earliest_deadline() {
+        let now = Ticks16::from(0xFFF0u16);
+        let deadlines = [0x0020u16, 0xFFF8, 0x0005].map(Ticks16::from);
+        assert_eq!(
+            earliest_deadline(now, &deadlines),
+            Some(Ticks16::from(0xFFF8u16))
+        );
+        let deadlines = [0x0020u16, 0x0005, 0x8000].map(Ticks16::from);
+        assert_eq!(
+            earliest_deadline(now, &deadlines),
+            Some(Ticks16::from(0x0005u16))
+        );
+        assert_eq!(earliest_deadline(now, &[now, 0u16.into()]), Some(now));
+        assert_eq!(earliest_deadline::<Ticks16>(now, &[]), None);
+    }
+
+    #[test]
+    fn test_sum_ticks() {
+        let phases = [0x4000u16, 0x2000, 0x1000].map(Ticks16::from);
+        assert_eq!(
+            sum_ticks(phases.into_iter()),
+            Some(Ticks16::from(0x7000u16))
+        );
+        let phases = [0x8000u16, 0x7FFF].map(Ticks16::from);
+        assert_eq!(sum_ticks(phases.into_iter()), Some(Ticks16::max_value()));
+        let phases = [0x8000u16, 0x7FFF, 1].map(Ticks16::from);
+        assert_eq!(sum_ticks(phases.into_iter()), None);
+        assert_eq!(
+            sum_ticks(core::iter::empty::<Ticks16>()),
+            Some(Ticks16::from(0u16))
+        );
+        let phases = [u64::MAX, 1].map(Ticks64::from);
+        assert_eq!(sum_ticks(phases.into_iter()), None);
+    }
+
+    /// Fixed-size buffer for checking `Display` output.
+    struct Buffer {
+        bytes: [u8; 32],
+        len: usize,
+    }
+
+    impl Buffer {
+        fn format(args: fmt::Arguments) -> Self {
+            let mut buffer = Buffer {
+                bytes: [0; 32],
+                len: 0,
+            };
+            fmt::write(&mut buffer, args).unwrap();
+            buffer
+        }
+
+        fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.bytes[..self.len]).unwrap()
+        }
+    }
+
+    impl fmt::Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.len + s.len();
+            self.bytes
+                .get_mut(self.len..end)
+                .ok_or(fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_ticks_display() {
+        let display = TicksDisplay::<Freq16MHz, _>::new(Ticks32::from(16000u32));
+        assert_eq!(
+            Buffer::format(format_args!("{}", display)).as_str(),
+            "1.000 ms"
+        );
+
+        let display = TicksDisplay::<Freq16MHz, _>::new(Ticks32::from(10u32));
+        assert_eq!(
+            Buffer::format(format_args!("{}", display)).as_str(),
+            "625 ns"
+        );
+        let display = TicksDisplay::<Freq32KHz, _>::new(Ticks32::from(1u32));
+        assert_eq!(
+            Buffer::format(format_args!("{}", display)).as_str(),
+            "30.517 us"
+        );
+        let display = TicksDisplay::<Freq1KHz, _>::new(Ticks64::from(90_061_500u64));
+        assert_eq!(
+            Buffer::format(format_args!("{}", display)).as_str(),
+            "90061.500 s"
+        );
+    }
+
+    #[test]
+    fn test_tick_range() {
+        let mut range = tick_range(Ticks16::from(0xFFF0u16), 0x10u16.into(), 10u16.into());
+        for expected in [0xFFF0u16, 0xFFFA, 0x4, 0xE] {
+            assert_eq!(range.next(), Some(Ticks16::from(expected)));
+        }
+        assert_eq!(range.next(), None);
+
+        // `end` itself is excluded.
+        assert_eq!(
+            tick_range(Ticks16::from(0u16), 30u16.into(), 10u16.into()).count(),
+            3
+        );
+        assert_eq!(
+            tick_range(Ticks16::from(5u16), 5u16.into(), 1u16.into()).count(),
+            0
+        );
+        let mut zero_step = tick_range(Ticks16::from(5u16), 50u16.into(), 0u16.into());
+        assert_eq!(zero_step.next(), Some(Ticks16::from(5u16)));
+        assert_eq!(zero_step.next(), None);
+    }
+
+    /// Counter whose first read overflows, as if the overflow interrupt
+    /// was handled right after the hardware value was sampled.
+    struct RacyCounter {
+        value: Cell<u32>,
+        overflows: Cell<u64>,
+        race: Cell<bool>,
+    }
+
+    impl Time for RacyCounter {
+        type Frequency = Freq1KHz;
+        type Ticks = Ticks32;
+
+        fn now(&self) -> Ticks32 {
+            let now = self.value.get();
+            if self.race.replace(false) {
+                self.value.set(now.wrapping_add(4));
+                self.overflows.set(self.overflows.get() + 1);
+            }
+            now.into()
+        }
+    }
+
+    impl<'a> Counter<'a> for RacyCounter {
+        fn set_overflow_client(&self, _client: &'a dyn OverflowClient) {}
+        fn clear_overflow_client(&self) {}
+        fn start(&self) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+        fn stop(&self) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+        fn reset(&self) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+        fn is_running(&self) -> bool {
+            true
+        }
+    }
+
+    impl<'a> CounterWithOverflow<'a> for RacyCounter {
+        fn overflow_count(&self) -> u64 {
+            self.overflows.get()
+        }
+    }
+
+    #[test]
+    fn test_now_with_overflow() {
+        let counter = RacyCounter {
+            value: Cell::new(0xFFFF_FFFE),
+            overflows: Cell::new(7),
+            race: Cell::new(false),
+        };
+        assert_eq!(counter.now_with_overflow(), (0xFFFF_FFFEu32.into(), 7));
+
+        // Pairing 0xFFFF_FFFE with the count after the overflow would be
+        // a whole period ahead; the read is retried instead.
+        counter.race.set(true);
+        assert_eq!(counter.now_with_overflow(), (2u32.into(), 8));
+    }
+
+    #[test]
+    fn test_counter_entropy() {
+        let counter = RacyCounter {
+            value: Cell::new(1000),
+            overflows: Cell::new(0),
+            race: Cell::new(false),
+        };
+        let mut seeds = [0; 8];
+        for seed in seeds.iter_mut() {
+            *seed = counter_entropy(&counter);
+            counter.value.set(counter.value.get() + 1);
+        }
+        for (i, seed) in seeds.iter().enumerate() {
+            assert!(seeds[i + 1..].iter().all(|other| other != seed));
+            // Consecutive readings differ in many bits, not just the low
+            // ones.
+            if let Some(next) = seeds.get(i + 1) {
+                assert!((seed ^ next).count_ones() > 4);
+            }
+        }
+    }
+
+    struct RuntimeClock {
+        now: Cell<u32>,
+        frequency: RuntimeFrequency,
+    }
+
+    impl RuntimeTime for RuntimeClock {
+        type Ticks = Ticks32;
+
+        fn now(&self) -> Ticks32 {
+            self.now.get().into()
+        }
+
+        fn frequency(&self) -> &RuntimeFrequency {
+            &self.frequency
+        }
+    }
+
+    #[test]
+    fn test_runtime_time() {
+        let clock = RuntimeClock {
+            now: Cell::new(0),
+            frequency: RuntimeFrequency::new(16_000_000),
+        };
+        assert_eq!(clock.ticks_from_ms(5).into_u32(), 80_000);
+        assert_eq!(clock.ticks_to_us(48u32.into()), 3);
+
+        // Reconfigured at runtime.
+        clock.frequency.set(32_768);
+        assert_eq!(clock.frequency.get(), 32_768);
+        assert_eq!(clock.ticks_from_seconds(2).into_u32(), 65_536);
+        assert_eq!(clock.ticks_to_ms(32_768u32.into()), 1_000);
+
+        // Same saturation as the static conversions.
+        clock.frequency.set(1_000_000);
+        assert_eq!(clock.ticks_from_seconds(u32::MAX), Ticks32::max_value());
+        assert_eq!(clock.ticks_to_us(Ticks32::max_value()), u32::MAX);
+        assert_eq!(
+            clock.ticks_from_us(1_000),
+            ConvertTicks::ticks_from_us(&MockAlarm::<Ticks32, Freq1MHz>::new(0), 1_000)
+        );
+
+        clock.frequency.set(0);
+        assert_eq!(clock.ticks_to_seconds(5u32.into()), 5);
+        clock.now.set(3);
+        assert_eq!(RuntimeTime::now(&clock).into_u32(), 3);
+    }
+
+    struct MockAlarm<'a, T: Ticks, F: Frequency = Freq1KHz> {
+        now: Cell<T>,
+        reference: Cell<T>,
+        dt: Cell<T>,
+        armed: Cell<bool>,
+        minimum_dt: Cell<T>,
+        client: OptionalCell<&'a dyn AlarmClient>,
+        _frequency: PhantomData<F>,
+    }
+
+    impl<T: Ticks, F: Frequency> MockAlarm<'_, T, F> {
+        fn new(now: u32) -> Self {
+            Self {
+                now: Cell::new(now.into()),
+                reference: Cell::new(0u32.into()),
+                dt: Cell::new(0u32.into()),
+                armed: Cell::new(false),
+                minimum_dt: Cell::new(0u32.into()),
+                client: OptionalCell::empty(),
+                _frequency: PhantomData,
+            }
+        }
+    }
+
+    impl<T: Ticks, F: Frequency> Time for MockAlarm<'_, T, F> {
+        type Frequency = F;
+        type Ticks = T;
+
+        fn now(&self) -> Self::Ticks {
+            self.now.get()
+        }
+    }
+
+    impl<'a, T: Ticks, F: Frequency> Alarm<'a> for MockAlarm<'a, T, F> {
+        fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+            self.client.set(client);
+        }
+
+        fn clear_alarm_client(&self) {
+            self.client.clear();
+        }
+
+        fn set_alarm(&self, reference: Self::Ticks, dt: Self::Ticks) {
+            self.reference.set(reference);
+            self.dt.set(dt);
+            self.armed.set(true);
+        }
+
+        fn get_alarm(&self) -> Self::Ticks {
+            self.reference.get().wrapping_add(self.dt.get())
+        }
+
+        fn disarm(&self) -> Result<(), ErrorCode> {
+            self.armed.set(false);
+            Ok(())
+        }
+
+        fn is_armed(&self) -> bool {
+            self.armed.get()
+        }
+
+        fn minimum_dt(&self) -> Self::Ticks {
+            self.minimum_dt.get()
+        }
+    }
+
+    #[test]
+    fn test_frequency_consts() {
+        assert_eq!(Freq16MHz::TICKS_PER_MS, 16_000);
+        assert_eq!(Freq16MHz::TICKS_PER_US, 16);
+        assert_eq!(Freq32KHz::TICKS_PER_MS, 32);
+        assert_eq!(Freq32KHz::TICKS_PER_US, 0);
+        assert_eq!(Freq1KHz::TICKS_PER_MS, 1);
+
+        // Usable in const contexts.
+        const TEN_MS: u32 = 10 * Freq1MHz::TICKS_PER_MS;
+        assert_eq!(TEN_MS, 10_000);
+        assert_eq!(
+            Freq100MHz::TICKS_PER_MS,
+            ticks_per_ms(Freq100MHz::frequency())
+        );
+    }
+
+    #[test]
+    fn test_frequency_combinators() {
+        assert_eq!(DividedFreq::<Freq16MHz, 8>::frequency(), 2_000_000);
+        assert_eq!(DividedFreq::<Freq32KHz, 3>::frequency(), 10_922);
+        assert_eq!(MultipliedFreq::<Freq1MHz, 64>::frequency(), 64_000_000);
+        assert_eq!(
+            MultipliedFreq::<DividedFreq<Freq16MHz, 16>, 100>::frequency(),
+            Freq100MHz::frequency()
+        );
+        assert_eq!(MultipliedFreq::<Freq100MHz, 100>::frequency(), u32::MAX);
+    }
+
+    #[test]
+    fn test_tick_resolution_ns() {
+        let alarm = MockAlarm::<Ticks32, Freq16MHz>::new(0);
+        assert_eq!(alarm.tick_resolution_ns(), 62);
+        let alarm = MockAlarm::<Ticks32, Freq32KHz>::new(0);
+        assert_eq!(alarm.tick_resolution_ns(), 30_517);
+        assert_eq!(Freq1KHz::period_ns(), 1_000_000);
+    }
+
+    #[test]
+    fn test_set_alarm_checked() {
+        let alarm = MockAlarm::<Ticks32>::new(1_000);
+        alarm.minimum_dt.set(50u32.into());
+
+        let dt = alarm.set_alarm_checked(alarm.now(), 10u32.into());
+        assert_eq!(dt, Ticks32::from(50u32));
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1_050u32));
+
+        let dt = alarm.set_alarm_checked(alarm.now(), 80u32.into());
+        assert_eq!(dt, Ticks32::from(80u32));
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1_080u32));
+    }
+
+    #[test]
+    fn test_set_alarm_clamped() {
+        let alarm = MockAlarm::<Ticks32>::new(1_000);
+        alarm.minimum_dt.set(5u32.into());
+
+        // The desired deadline is already 100 ticks in the past.
+        let dt = Ticks32::from(900u32).wrapping_sub(alarm.now());
+        alarm.set_alarm_clamped(alarm.now(), dt);
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1_005u32));
+        alarm.now.set(1_005u32.into());
+        assert!(alarm.is_expired_now());
+
+        // A deadline in the future is set unchanged.
+        alarm.set_alarm_clamped(alarm.now(), 300u32.into());
+        assert_eq!(alarm.get_alarm(), Ticks32::from(1_305u32));
+
+        let alarm = MockAlarm::<Ticks16>::new(0x0010);
+        alarm.minimum_dt.set(2u16.into());
+        let dt = Ticks16::from(0xFFF0u16).wrapping_sub(alarm.now());
+        alarm.set_alarm_clamped(alarm.now(), dt);
+        assert_eq!(alarm.get_alarm(), Ticks16::from(0x0012u16));
+        let dt = Ticks16::half_max_value();
+        alarm.set_alarm_clamped(alarm.now(), dt);
+        assert_eq!(alarm.get_alarm(), Ticks16::from(0x8010u16));
+    }
+
+    #[test]
+    fn test_set_alarm_at() {
+        let alarm = MockAlarm::<Ticks16>::new(0xFFF0);
+        alarm.set_alarm_at(0x20u16.into());
+        assert!(alarm.is_armed());
+        assert_eq!(alarm.get_alarm(), Ticks16::from(0x20u16));
+        assert_eq!(alarm.dt.get(), Ticks16::from(0x30u16));
+
+        alarm.set_alarm_at(0xFFF8u16.into());
+        assert_eq!(alarm.get_alarm(), Ticks16::from(0xFFF8u16));
+        assert_eq!(alarm.dt.get(), Ticks16::from(8u16));
+    }
+
+    #[test]
+    fn test_is_expired_now() {
+        let alarm = MockAlarm::<Ticks32>::new(1_000);
+        assert!(!alarm.is_expired_now());
+
+        alarm.set_alarm(alarm.now(), 100u32.into());
+        assert!(!alarm.is_expired_now());
+        alarm.now.set(1_099u32.into());
+        assert!(!alarm.is_expired_now());
+        alarm.now.set(1_100u32.into());
+        assert!(alarm.is_expired_now());
+        alarm.now.set(1_200u32.into());
+        assert!(alarm.is_expired_now());
+
+        // Disarmed alarms never report expiry.
+        let _ = alarm.disarm();
+        assert!(!alarm.is_expired_now());
+    }
+
+    #[test]
+    fn test_is_expired_now_across_wrap() {
+        let alarm = MockAlarm::<Ticks16>::new(0xFFF0);
+        alarm.set_alarm(alarm.now(), 0x20u32.into());
+        assert_eq!(alarm.get_alarm().into_u32(), 0x10);
+
+        alarm.now.set(0xFFFFu32.into());
+        assert!(!alarm.is_expired_now());
+        alarm.now.set(0x000Fu32.into());
+        assert!(!alarm.is_expired_now());
+        alarm.now.set(0x0010u32.into());
+        assert!(alarm.is_expired_now());
+        alarm.now.set(0x0100u32.into());
+        assert!(alarm.is_expired_now());
+    }
+
+    #[test]
+    fn test_rebase() {
+        // The value is 0x20 ticks after the old reference, which itself
+        // is close to the top of the range.
+        let old_ref = Ticks24::from(0x00FF_FFF0u32);
+        let value = Ticks24::from(0x0000_0010u32);
+
+        let rebased = value.rebase(old_ref, Ticks24::from(0x100u32));
+        assert_eq!(rebased.into_u32(), 0x120);
+
+        // Moving the reference the other way crosses the wrap again.
+        let back = rebased.rebase(Ticks24::from(0x100u32), old_ref);
+        assert_eq!(back, value);
+
+        let rebased = value.rebase(old_ref, Ticks24::from(0x00FF_FFF8u32));
+        assert_eq!(rebased.into_u32(), 0x18);
+    }
+
+    #[test]
+    fn test_frequency_hz_dyn() {
+        let time: &dyn Time<Frequency = Freq1KHz, Ticks = Ticks24> = &Test1KHz24();
+        assert_eq!(time.frequency_hz(), 1_000);
+
+        let time: &dyn Time<Frequency = Freq1MHz, Ticks = Ticks64> = &Test1MHz64();
+        assert_eq!(time.frequency_hz(), 1_000_000);
+    }
+
+    #[test]
+    fn test_now_u64() {
+        let alarm = MockAlarm::<Ticks16>::new(0xFFFF);
+        let time: &dyn Time<Frequency = Freq1KHz, Ticks = Ticks16> = &alarm;
+        assert_eq!(time.now_u64(), 0xFFFF);
+
+        let alarm = MockAlarm::<Ticks16>::new(0x1_0001);
+        assert_eq!(alarm.now_u64(), 1);
+
+        assert_eq!(Ticks64::max_value().into_u64(), u64::MAX);
+        assert_eq!(Ticks24::max_value().into_u64(), 0x00FF_FFFF);
+    }
+
+    #[test]
+    fn test_leading_zeros() {
+        assert_eq!(Ticks24::from(0x00FF_FFFFu32).leading_zeros(), 0);
+        assert_eq!(Ticks24::from(0x00FF_FFFFu32).significant_bits(), 24);
+        assert_eq!(Ticks24::from(0x0080_0000u32).leading_zeros(), 0);
+        assert_eq!(Ticks24::from(0x007F_FFFFu32).leading_zeros(), 1);
+        assert_eq!(Ticks24::from(1u32).leading_zeros(), 23);
+        assert_eq!(Ticks24::from(1u32).significant_bits(), 1);
+        assert_eq!(Ticks24::from(0u32).leading_zeros(), 24);
+        assert_eq!(Ticks24::from(0u32).significant_bits(), 0);
+
+        assert_eq!(Ticks16::from(0x0100u16).leading_zeros(), 7);
+        assert_eq!(Ticks16::from(0x0100u16).significant_bits(), 9);
+        assert_eq!(Ticks32::from(0u32).leading_zeros(), 32);
+        assert_eq!(Ticks32::from(u32::MAX).significant_bits(), 32);
+        assert_eq!(Ticks64::from(1u64 << 40).leading_zeros(), 23);
+        assert_eq!(Ticks64::from(1u64 << 40).significant_bits(), 41);
+    }
+
+    #[test]
+    fn test_count_ones_trailing_zeros() {
+        // Bits above the width of `Ticks24` are not counted.
+        assert_eq!(Ticks24::from(0xFF00_00F0u32).count_ones(), 4);
+        assert_eq!(Ticks24::from(0xFF00_00F0u32).trailing_zeros(), 4);
+        assert_eq!(Ticks24::from(0xFF00_0000u32).trailing_zeros(), 24);
+        assert_eq!(Ticks24::from(0u32).trailing_zeros(), 24);
+        assert_eq!(Ticks24::max_value().count_ones(), 24);
+
+        assert_eq!(Ticks16::from(0x8000u16).trailing_zeros(), 15);
+        assert_eq!(Ticks16::from(0u16).trailing_zeros(), 16);
+        assert_eq!(Ticks16::max_value().count_ones(), 16);
+        assert_eq!(Ticks32::from(0x0001_0100u32).count_ones(), 2);
+        assert_eq!(Ticks32::from(0u32).trailing_zeros(), 32);
+        assert_eq!(Ticks64::from(1u64 << 40).trailing_zeros(), 40);
+        assert_eq!(Ticks64::from(0u64).trailing_zeros(), 64);
+        assert_eq!(Ticks64::max_value().count_ones(), 64);
+    }
+
+    #[test]
+    fn test_power_of_two() {
+        assert!(Ticks16::from(0x0400u16).is_power_of_two());
+        assert!(!Ticks16::from(0x0401u16).is_power_of_two());
+        assert!(!Ticks16::from(0u16).is_power_of_two());
+        assert_eq!(Ticks16::from(0u16).next_power_of_two(), Ticks16::from(1u16));
+        assert_eq!(
+            Ticks16::from(0x0400u16).next_power_of_two(),
+            Ticks16::from(0x0400u16)
+        );
+        assert_eq!(
+            Ticks16::from(0x0401u16).next_power_of_two(),
+            Ticks16::from(0x0800u16)
+        );
+        assert_eq!(
+            Ticks16::from(0x8001u16).next_power_of_two(),
+            Ticks16::max_value()
+        );
+
+        // Bits above the width of `Ticks24` are ignored.
+        assert!(Ticks24::from(0xFF80_0000u32).is_power_of_two());
+        assert_eq!(
+            Ticks24::from(0xFF00_0003u32).next_power_of_two().into_u32(),
+            4
+        );
+        assert_eq!(
+            Ticks24::from(0x0080_0000u32).next_power_of_two().into_u32(),
+            0x0080_0000
+        );
+        assert_eq!(
+            Ticks24::from(0x0080_0001u32).next_power_of_two(),
+            Ticks24::max_value()
+        );
+
+        assert!(Ticks32::from(1u32).is_power_of_two());
+        assert_eq!(Ticks32::from(1u32).next_power_of_two().into_u32(), 1);
+        assert_eq!(
+            Ticks32::from(1_000u32).next_power_of_two().into_u32(),
+            1_024
+        );
+        assert_eq!(
+            Ticks32::from(0x8000_0001u32).next_power_of_two(),
+            Ticks32::max_value()
+        );
+
+        assert!(Ticks64::from(1u64 << 63).is_power_of_two());
+        assert!(!Ticks64::max_value().is_power_of_two());
+        assert_eq!(
+            Ticks64::from((1u64 << 40) + 1)
+                .next_power_of_two()
+                .into_u64(),
+            1 << 41
+        );
+        assert_eq!(
+            Ticks64::from(1u64 << 63).next_power_of_two().into_u64(),
+            1 << 63
+        );
+        assert_eq!(
+            Ticks64::from((1u64 << 63) + 1).next_power_of_two(),
+            Ticks64::max_value()
+        );
+    }
+
+    #[test]
+    fn test_closest_to() {
+        let candidates = [100u32, 250, 180, 400].map(Ticks32::from);
+        let closest = |target: u32| Ticks32::closest_to(Ticks32::from(target), &candidates);
+        assert_eq!(closest(200), Some(Ticks32::from(180)));
+        assert_eq!(closest(0), Some(Ticks32::from(100)));
+        assert_eq!(closest(1_000), Some(Ticks32::from(400)));
+        assert_eq!(closest(250), Some(Ticks32::from(250)));
+        // 215 is 35 from both 250 and 180; the first listed wins.
+        assert_eq!(closest(215), Some(Ticks32::from(250)));
+        assert_eq!(Ticks32::closest_to(Ticks32::from(215), &[]), None);
+
+        let candidates = [Ticks16::from(0xFFFFu16), Ticks16::from(10u16)];
+        // Distances are absolute: 0xFFFF is not 3 ticks before 2.
+        assert_eq!(
+            Ticks16::closest_to(Ticks16::from(2u16), &candidates),
+            Some(Ticks16::from(10u16))
+        );
+        assert_eq!(
+            Ticks64::closest_to(Ticks64::from(1u64 << 40), &[Ticks64::from(0u64)]),
+            Some(Ticks64::from(0u64))
+        );
+    }
+
+    #[test]
+    fn test_millis() {
+        // At 1kHz a tick is a millisecond, so the conversion is lossless.
+        for ms in [0, 1, 999, 65_535, 1_000_000, u32::MAX] {
+            let ticks = Ticks32::from_millis::<Freq1KHz>(ms);
+            assert_eq!(ticks.into_u32(), ms);
+            assert_eq!(ticks.to_millis::<Freq1KHz>(), ms);
+            assert_eq!(
+                Ticks64::from_millis::<Freq1KHz>(ms).to_millis::<Freq1KHz>(),
+                ms
+            );
+        }
+        assert_eq!(
+            Ticks16::from_millis::<Freq1KHz>(65_535).to_millis::<Freq1KHz>(),
+            65_535
+        );
+        // Beyond the width of the ticks type, `from_millis` saturates.
+        assert_eq!(
+            Ticks16::from_millis::<Freq1KHz>(70_000),
+            Ticks16::max_value()
+        );
+
+        assert_eq!(Ticks32::from_millis::<Freq16MHz>(5).into_u32(), 80_000);
+        assert_eq!(Ticks32::from(80_000u32).to_millis::<Freq16MHz>(), 5);
+        // Fractions of a millisecond are rounded down.
+        assert_eq!(Ticks32::from(79_999u32).to_millis::<Freq16MHz>(), 4);
+        // Beyond `u32::MAX` milliseconds, `to_millis` saturates.
+        assert_eq!(Ticks64::from(u64::MAX).to_millis::<Freq1KHz>(), u32::MAX);
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        // Constructed directly, bypassing the masking in `From<u32>`.
+        let dirty = Ticks24(0xAB00_1234);
+        let clean = Ticks24::from(0x0000_1234u32);
+        assert_ne!(dirty, clean);
+        assert_eq!(dirty.canonicalize(), clean);
+        assert_eq!(dirty.canonicalize().into_u32(), 0x1234);
+        assert_eq!(clean.canonicalize(), clean);
+
+        // Constructors canonicalize.
+        assert_eq!(Ticks24::from(0xAB00_1234u32), clean);
+        assert_eq!(Ticks24::from(0xFFFF_FFFFu32), Ticks24::max_value());
+
+        assert_eq!(
+            Ticks16::from(0xFFFFu16).canonicalize(),
+            Ticks16::max_value()
+        );
+        assert_eq!(Ticks32::from(u32::MAX).canonicalize(), Ticks32::max_value());
+        assert_eq!(Ticks64::from(u64::MAX).canonicalize(), Ticks64::max_value());
+    }
+
+    #[test]
+    fn test_at() {
+        assert_eq!(At::<Freq16MHz>::ms::<Ticks32>(5).into_u32(), 80_000);
+        assert_eq!(At::<Freq16MHz>::us::<Ticks32>(3).into_u32(), 48);
+        assert_eq!(At::<Freq16MHz>::ns::<Ticks32>(125).into_u32(), 2);
+        assert_eq!(At::<Freq32KHz>::secs::<Ticks32>(2).into_u32(), 65_536);
+        // Fractions of a tick are rounded down.
+        assert_eq!(At::<Freq1KHz>::us::<Ticks32>(999).into_u32(), 0);
+        // Saturates rather than wrapping.
+        assert_eq!(At::<Freq1MHz>::secs::<Ticks16>(1), Ticks16::max_value());
+        assert_eq!(At::<Freq16MHz>::secs::<Ticks24>(2).into_u32(), 0x00FF_FFFF);
+        assert_eq!(
+            At::<Freq16MHz>::secs::<Ticks64>(u32::MAX).into_u64(),
+            16_000_000 * u32::MAX as u64
+        );
+    }
+
+    #[test]
+    fn test_interval_for_rate() {
+        assert_eq!(
+            interval_for_rate::<Freq16MHz, Ticks32>(1_000),
+            Ok(Ticks32::from(16_000u32))
+        );
+        assert_eq!(
+            interval_for_rate::<Freq16MHz, Ticks32>(16_000_000),
+            Ok(Ticks32::from(1u32))
+        );
+        // 32768 / 1000 is rounded down.
+        assert_eq!(
+            interval_for_rate::<Freq32KHz, Ticks32>(1_000),
+            Ok(Ticks32::from(32u32))
+        );
+        // 16000000 ticks do not fit in 16 bits.
+        assert_eq!(
+            interval_for_rate::<Freq16MHz, Ticks16>(1),
+            Ok(Ticks16::max_value())
+        );
+        assert_eq!(
+            interval_for_rate::<Freq16MHz, Ticks32>(0),
+            Err(ErrorCode::INVAL)
+        );
+        assert_eq!(
+            interval_for_rate::<Freq1KHz, Ticks32>(1_001),
+            Err(ErrorCode::INVAL)
+        );
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        let parse = parse_interval::<Freq16MHz, Ticks32>;
+        assert_eq!(parse("5ms"), Ok(Ticks32::from(80_000u32)));
+        assert_eq!(parse("100us"), Ok(Ticks32::from(1_600u32)));
+        assert_eq!(parse("1000ticks"), Ok(Ticks32::from(1_000u32)));
+        assert_eq!(parse("250ns"), Ok(Ticks32::from(4u32)));
+        assert_eq!(parse("2s"), Ok(Ticks32::from(32_000_000u32)));
+        assert_eq!(parse("0ms"), Ok(Ticks32::from(0u32)));
+        assert_eq!(
+            parse_interval::<Freq16MHz, Ticks16>("1s"),
+            Ok(Ticks16::max_value())
+        );
+
+        for malformed in [
+            "",
+            "5",
+            "ms",
+            "5 ms",
+            " 5ms",
+            "+5ms",
+            "-5ms",
+            "5.5ms",
+            "5MS",
+            "5min",
+            "5000000000ms",
+        ] {
+            assert_eq!(parse(malformed), Err(ErrorCode::INVAL), "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn test_as_f32_seconds() {
+        let close = |a: f32, b: f32| (a - b).abs() < f32::EPSILON;
+        assert!(close(
+            Ticks32::from(500_000u32).as_f32_seconds::<Freq1MHz>(),
+            0.5
+        ));
+        assert!(close(Ticks16::from(0u16).as_f32_seconds::<Freq1MHz>(), 0.0));
+        assert!(close(
+            Ticks64::from(3_000u64).as_f32_seconds::<Freq1KHz>(),
+            3.0
+        ));
+    }
+
+    #[test]
+    fn test_ticks_min_max_median() {
+        let vals = [40u32, 10, 0xFFFF_FFF0, 30, 10].map(Ticks32::from);
+        // Absolute ordering: a large delta is not "before" a small one.
+        assert_eq!(ticks_min(&vals), Some(Ticks32::from(10u32)));
+        assert_eq!(ticks_max(&vals), Some(Ticks32::from(0xFFFF_FFF0u32)));
+        assert_eq!(ticks_median(&vals), Some(Ticks32::from(30u32)));
+        assert_eq!(ticks_median(&vals[..4]), Some(Ticks32::from(35u32)));
+        assert_eq!(ticks_median(&vals[..2]), Some(Ticks32::from(25u32)));
+        assert_eq!(ticks_median(&vals[1..2]), Some(Ticks32::from(10u32)));
+
+        // Rounding down, without overflowing.
+        let large = [u32::MAX, u32::MAX - 2].map(Ticks32::from);
+        assert_eq!(ticks_median(&large), Some(Ticks32::from(u32::MAX - 1)));
+        let odd = [1u32, 2].map(Ticks32::from);
+        assert_eq!(ticks_median(&odd), Some(Ticks32::from(1u32)));
+
+        let empty: [Ticks32; 0] = [];
+        assert_eq!(ticks_min(&empty), None);
+        assert_eq!(ticks_max(&empty), None);
+        assert_eq!(ticks_median(&empty), None);
+    }
+
+    #[test]
+    fn test_frequency_gcd_lcm() {
+        assert_eq!(frequency_gcd::<Freq16MHz, Freq32KHz>(), 1_024);
+        assert_eq!(frequency_lcm::<Freq16MHz, Freq32KHz>(), 512_000_000);
+        assert_eq!(frequency_gcd::<Freq32KHz, Freq16MHz>(), 1_024);
+        assert_eq!(frequency_gcd::<Freq1MHz, Freq1KHz>(), 1_000);
+        assert_eq!(frequency_lcm::<Freq1MHz, Freq1KHz>(), 1_000_000);
+        assert_eq!(frequency_gcd::<Freq16KHz, Freq16KHz>(), 16_000);
+        assert_eq!(frequency_lcm::<Freq16MHz, Freq10MHz>(), 80_000_000);
+        // 100MHz / 256 * 32768Hz does not fit.
+        assert_eq!(frequency_gcd::<Freq100MHz, Freq32KHz>(), 256);
+        assert_eq!(frequency_lcm::<Freq100MHz, Freq32KHz>(), u32::MAX);
+    }
+
+    #[test]
+    fn test_bounded_ticks() {
+        let min = Ticks32::from(100u32);
+        let max = Ticks32::from(200u32);
+        let bounded = BoundedTicks::new(Ticks32::from(150u32), min, max);
+        let bounded = bounded.wrapping_add(Ticks32::from(50u32));
+        assert_eq!(bounded.value(), max);
+        let bounded = bounded.wrapping_sub(Ticks32::from(100u32));
+        assert_eq!(bounded.value(), min);
+        assert_eq!((bounded.min(), bounded.max()), (min, max));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "tick value 99 outside of [100, 200]")]
+    fn test_bounded_ticks_out_of_band() {
+        BoundedTicks::new(Ticks32::from(99u32), 100u32.into(), 200u32.into());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "outside of")]
+    fn test_bounded_ticks_arithmetic_out_of_band() {
+        let bounded = BoundedTicks::new(Ticks32::from(150u32), 100u32.into(), 200u32.into());
+        // Wrapping below 0 leaves the band too.
+        bounded.wrapping_sub(Ticks32::from(151u32));
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let t0 = Ticks24::from(0x1000u32);
+        let t1 = Ticks24::from(0x3000u32);
+        assert_eq!(t0.interpolate(t1, 1, 2), Ticks24::from(0x2000u32));
+        assert_eq!(t0.interpolate(t1, 3, 10).into_u32(), 0x1999);
+        assert_eq!(t0.interpolate(t1, 0, 1), t0);
+        assert_eq!(t0.interpolate(t1, 1, 1), t1);
+        assert_eq!(t0.interpolate(t1, 1, 0), t0);
+
+        // Halfway across the wrap point.
+        let t0 = Ticks24::from(0x00FF_FF00u32);
+        let t1 = Ticks24::from(0x0000_0100u32);
+        assert_eq!(t0.interpolate(t1, 1, 2), Ticks24::from(0u32));
+        // Extrapolating wraps at 24 bits.
+        assert_eq!(t0.interpolate(t1, 3, 1).into_u32(), 0x500);
+
+        let t0 = Ticks64::from(0u64);
+        let t1 = Ticks64::from(u64::MAX);
+        assert_eq!(t0.interpolate(t1, u32::MAX, u32::MAX), t1);
+    }
+
+    #[test]
+    fn test_rate_per_tick() {
+        let mut rate = RatePerTick::<Freq1KHz>::new(1_000);
+        assert_eq!(rate.increment(), 1 << 16);
+        assert!((0..100).all(|_| rate.accumulate() == 1));
+
+        let mut rate = RatePerTick::<Freq1KHz>::new(250);
+        let units: u32 = (0..1_000).map(|_| rate.accumulate()).sum();
+        assert_eq!(units, 250);
+
+        // The increment is rounded down, so this falls slightly short.
+        let mut rate = RatePerTick::<Freq1KHz>::new(300);
+        let units: u32 = (0..1_000).map(|_| rate.accumulate()).sum();
+        assert_eq!(units, 299);
+
+        let mut rate = RatePerTick::<Freq1KHz>::new(2_500);
+        let units: [u32; 4] = core::array::from_fn(|_| rate.accumulate());
+        assert_eq!(units, [2, 3, 2, 3]);
+
+        assert_eq!(RatePerTick::<Freq1MHz>::new(10).increment(), 0);
+        assert_eq!(RatePerTick::<Freq1KHz>::new(u32::MAX).increment(), u32::MAX);
+    }
+
+    #[test]
+    fn test_range_len() {
+        let len = |start: u32, end: u32| Ticks24::range_len(start.into(), end.into()).into_u32();
+        assert_eq!(len(0x100, 0x180), 0x80);
+        assert_eq!(len(0x100, 0x100), 0);
+        // Across the wrap point.
+        assert_eq!(len(0x00FF_FFF0, 0x10), 0x20);
+        assert_eq!(len(0x00FF_FFFF, 0), 1);
+        // `end` behind `start`.
+        assert_eq!(len(0x10, 0x0F), 0x00FF_FFFF);
+    }
+
+    #[test]
+    fn test_try_into_usize() {
+        assert_eq!(Ticks16::from(0xFFFFu16).try_into_usize(), Ok(0xFFFF));
+        assert_eq!(Ticks24::max_value().try_into_usize(), Ok(0x00FF_FFFF));
+        // Fits on 64-bit hosts only.
+        let large = Ticks64::from(u64::MAX);
+        if usize::BITS < 64 {
+            assert_eq!(large.try_into_usize(), Err(ErrorCode::SIZE));
+        } else {
+            assert_eq!(large.try_into_usize(), Ok(usize::MAX));
+        }
+        // Where `usize` is narrower than 64 bits, one past its maximum is
+        // rejected.
+        if let Some(value) = (usize::MAX as u64).checked_add(1) {
+            assert_eq!(Ticks64::from(value).try_into_usize(), Err(ErrorCode::SIZE));
+        }
+    }
+
+    #[test]
+    fn test_delta_encoding() {
+        let stamps = [
+            0x00FF_FF00u32,
+            0x00FF_FFF0,
+            0x00FF_FFFF,
+            0x10,
+            0x2000,
+            0x00FF_0000,
+        ]
+        .map(Ticks24::from);
+        let mut prev = Ticks24::from(0u32);
+        for stamp in stamps {
+            let delta = stamp.delta_encode(prev);
+            assert!(delta <= 0x00FF_FFFF);
+            assert_eq!(Ticks24::delta_decode(prev, delta), stamp);
+            prev = stamp;
+        }
+        // Across the wrap.
+        let delta = Ticks24::from(0x10u32).delta_encode(Ticks24::from(0x00FF_FFF0u32));
+        assert_eq!(delta, 0x20);
+
+        // A distance which does not fit in 32 bits saturates.
+        let far = Ticks64::from(1u64 << 40);
+        assert_eq!(far.delta_encode(Ticks64::from(0u64)), u32::MAX);
+        let near = Ticks64::from(u64::MAX);
+        assert_eq!(Ticks64::delta_decode(near, 2), Ticks64::from(1u64));
+    }
+
+    #[test]
+    fn test_wrapping_mod() {
+        let period = Ticks32::from(1000u32);
+        for (value, phase) in [
+            (0u32, 0u32),
+            (999, 999),
+            (1000, 0),
+            (123_456, 456),
+            (u32::MAX, 295),
+        ] {
+            assert_eq!(
+                Ticks32::from(value).wrapping_mod(period),
+                Ticks32::from(phase)
+            );
+        }
+        assert_eq!(
+            Ticks32::from(42u32).wrapping_mod(Ticks32::from(0u32)),
+            Ticks32::from(42u32)
+        );
+
+        // Bits above the width of `Ticks24` are ignored.
+        let period = Ticks24::from(1000u32);
+        assert_eq!(
+            Ticks24::from(0xFF00_0000u32 | 1234)
+                .wrapping_mod(period)
+                .into_u32(),
+            234
+        );
+        assert_eq!(
+            Ticks24::from(5u32)
+                .wrapping_mod(Ticks24::from(0x0100_0000u32))
+                .into_u32(),
+            5
+        );
+        assert_eq!(
+            Ticks16::from(1234u16)
+                .wrapping_mod(Ticks16::from(1000u16))
+                .into_u32(),
+            234
+        );
+        assert_eq!(
+            Ticks64::from(1234u64)
+                .wrapping_mod(Ticks64::from(1000u64))
+                .into_u32(),
+            234
+        );
+    }
+
+    #[test]
+    fn test_now_seconds_nanos() {
+        // 40000 ticks at 32768Hz: 1s and 7232 ticks.
+        let alarm = MockAlarm::<Ticks32, Freq32KHz>::new(40_000);
+        assert_eq!(alarm.now_seconds_nanos(), (1, 220_703_125));
+        // 7 ticks at 333Hz are 21021021.02ns, rounded down.
+        let alarm = MockAlarm::<Ticks32, DividedFreq<Freq1KHz, 3>>::new(7);
+        assert_eq!(alarm.now_seconds_nanos(), (0, 21_021_021));
+        let alarm = MockAlarm::<Ticks32, Freq1KHz>::new(u32::MAX);
+        assert_eq!(alarm.now_seconds_nanos(), (4_294_967, 295_000_000));
+        let alarm = MockAlarm::<Ticks64, Freq1KHz>::new(0);
+        alarm.now.set(Ticks64::from(5_000_000_000_123u64));
+        assert_eq!(alarm.now_seconds_nanos(), (u32::MAX, 123_000_000));
+    }
+
+    fn round_trip<T: Ticks>(val: T, width: usize) {
+        let mut buf = [0xAA; 10];
+        assert_eq!(val.to_le_bytes(&mut buf), width);
+        assert!(buf[width..].iter().all(|&b| b == 0xAA));
+        assert_eq!(T::from_le_bytes(&buf[..width]), val);
+        // Trailing bytes are ignored.
+        assert_eq!(T::from_le_bytes(&buf), val);
+        // Too short a buffer is left untouched.
+        let mut short = [0xAA; 10];
+        assert_eq!(val.to_le_bytes(&mut short[..width - 1]), 0);
+        assert_eq!(short, [0xAA; 10]);
+    }
+
+    #[test]
+    fn test_ticks64_parts() {
+        let t = Ticks64::from_parts(0x0123_4567, 0x89AB_CDEF);
+        assert_eq!(t.into_u64(), 0x0123_4567_89AB_CDEF);
+        assert_eq!(t.split(), (0x0123_4567, 0x89AB_CDEF));
+        for (high, low) in [(0, 0), (0, u32::MAX), (u32::MAX, 0), (u32::MAX, u32::MAX)] {
+            assert_eq!(Ticks64::from_parts(high, low).split(), (high, low));
+        }
+    }
+
+    #[test]
+    fn test_le_bytes() {
+        round_trip(Ticks16::from(0xBEEFu16), 2);
+        round_trip(Ticks24::from(0x00AB_CDEFu32), 3);
+        round_trip(Ticks24::max_value(), 3);
+        round_trip(Ticks32::from(0xDEAD_BEEFu32), 4);
+        round_trip(Ticks64::from(0x0123_4567_89AB_CDEFu64), 8);
+
+        let mut buf = [0; 3];
+        Ticks24::from(0x00AB_CDEFu32).to_le_bytes(&mut buf);
+        assert_eq!(buf, [0xEF, 0xCD, 0xAB]);
+        assert_eq!(
+            Ticks32::from_le_bytes(&[0x34, 0x12]),
+            Ticks32::from(0x1234u32)
+        );
+    }
+
+    #[test]
+    fn test_checked_mul() {
+        assert_eq!(Ticks16::from(0x4000u16).checked_mul(4), None);
+        assert_eq!(
+            Ticks16::from(0x4000u16).checked_mul(3),
+            Some(Ticks16::from(0xC000u16))
+        );
+        assert_eq!(
+            Ticks16::from(0u16).checked_mul(u32::MAX),
+            Some(Ticks16::from(0u16))
+        );
+
+        assert_eq!(Ticks24::from(0x40_0000u32).checked_mul(4), None);
+        assert_eq!(
+            Ticks24::from(0x40_0000u32).checked_mul(3),
+            Some(Ticks24::from(0xC0_0000u32))
+        );
+        assert_eq!(Ticks32::from(0x4000_0000u32).checked_mul(4), None);
+        assert_eq!(
+            Ticks64::from(0x4000_0000u32).checked_mul(4),
+            Some(Ticks64::from(0x1_0000_0000u64))
+        );
+    }
+
+    #[test]
+    fn test_consts() {
+        const TABLE: [Ticks24; 3] = [Ticks24::ZERO, Ticks24::ONE, Ticks24::MAX];
+        assert_eq!(TABLE[0], Ticks24::from(0u32));
+        assert_eq!(TABLE[1], Ticks24::from(1u32));
+        assert_eq!(Ticks24::MAX, Ticks24::max_value());
+        assert_eq!(Ticks16::MAX, Ticks16::max_value());
+        assert_eq!(Ticks32::MAX, Ticks32::max_value());
+        assert_eq!(Ticks64::MAX, Ticks64::max_value());
+        assert_eq!(Ticks64::ONE.wrapping_sub(Ticks64::ONE), Ticks64::ZERO);
+    }
+
+    #[test]
+    fn test_round_to() {
+        let t = Ticks32::from(1500u32);
+        assert_eq!(t.round_up_to(1000u32.into()), Ticks32::from(2000u32));
+        assert_eq!(t.round_down_to(1000u32.into()), Ticks32::from(1000u32));
+        assert_eq!(t.round_up_to(500u32.into()), t);
+        assert_eq!(t.round_down_to(500u32.into()), t);
+        assert_eq!(t.round_up_to(0u32.into()), t);
+        assert_eq!(t.round_down_to(0u32.into()), t);
+
+        // Rounding up past the width saturates.
+        let t = Ticks16::from(0xFFF0u16);
+        assert_eq!(t.round_up_to(0x100u16.into()), Ticks16::max_value());
+        assert_eq!(t.round_down_to(0x100u16.into()), Ticks16::from(0xFF00u16));
+        let t = Ticks64::from(u64::MAX - 1);
+        assert_eq!(t.round_up_to(16u32.into()), Ticks64::max_value());
+    }
+
+    #[test]
+    fn test_try_into_narrow() {
+        assert_eq!(Ticks32::from(0xFFFFu32).try_into_u16(), Ok(0xFFFF));
+        assert_eq!(
+            Ticks32::from(0x1_0000u32).try_into_u16(),
+            Err(ErrorCode::SIZE)
+        );
+        assert_eq!(Ticks24::from(0xFFu32).try_into_u8(), Ok(0xFF));
+        assert_eq!(Ticks24::from(0x100u32).try_into_u8(), Err(ErrorCode::SIZE));
+        assert_eq!(Ticks16::from(0xFFFFu16).try_into_u16(), Ok(0xFFFF));
+        assert_eq!(
+            Ticks64::from(1u64 << 40).try_into_u16(),
+            Err(ErrorCode::SIZE)
+        );
+    }
+
+    #[test]
+    fn test_wrapping_add_signed() {
+        let t = Ticks24::from(5u32);
+        assert_eq!(t.wrapping_add_signed(-10), Ticks24::from(0x00FF_FFFBu32));
+        assert_eq!(t.wrapping_add_signed(10), Ticks24::from(15u32));
+        assert_eq!(t.wrapping_add_signed(0), t);
+
+        let t = Ticks24::from(0x00FF_FFFAu32);
+        assert_eq!(t.wrapping_add_signed(10), Ticks24::from(4u32));
+        assert_eq!(t.wrapping_add_signed(-10), Ticks24::from(0x00FF_FFF0u32));
+
+        // 2^31 is a multiple of 2^24 and 2^16.
+        assert_eq!(t.wrapping_add_signed(i32::MIN), t);
+        assert_eq!(
+            Ticks16::from(7u16).wrapping_add_signed(i32::MIN),
+            Ticks16::from(7u16)
+        );
+        assert_eq!(
+            Ticks32::from(0u32).wrapping_add_signed(i32::MIN),
+            Ticks32::from(0x8000_0000u32)
+        );
+        assert_eq!(
+            Ticks64::from(0u32).wrapping_add_signed(-1),
+            Ticks64::max_value()
+        );
+    }
+
+    #[test]
+    fn test_wrapping_operators() {
+        let before = Wrapping(Ticks16::from(0xFFF0u16));
+        let after = Wrapping(Ticks16::from(0x0010u16));
+        assert!(after > before);
+        assert!(before < after);
+        assert!(before <= before);
+        assert_eq!(core::cmp::max(before, after), after);
+        // `Ord` on the plain values says the opposite.
+        assert!(after.0 < before.0);
+
+        let now = Wrapping(Ticks16::from(0x0000u16));
+        assert!(Wrapping(Ticks16::from(0x7FFFu16)) > now);
+        assert!(Wrapping(Ticks16::from(0x8000u16)) < now);
+    }
+
+    #[test]
+    fn test_midpoint() {
+        let a = Ticks16::from(0xFFF0u16);
+        let b = Ticks16::from(0x0010u16);
+        assert_eq!(a.midpoint(b), Ticks16::from(0x0000u16));
+        // The arc from `b` to `a` goes the other way around.
+        assert_eq!(b.midpoint(a), Ticks16::from(0x8000u16));
+        assert_eq!(
+            a.midpoint(Ticks16::from(0x0001u16)),
+            Ticks16::from(0xFFF8u16)
+        );
+        assert_eq!(a.midpoint(a), a);
+
+        assert_eq!(
+            Ticks24::from(0x00FF_FF00u32).midpoint(Ticks24::from(0x100u32)),
+            Ticks24::from(0u32)
+        );
+        assert_eq!(
+            Ticks32::from(0u32).midpoint(Ticks32::max_value()),
+            Ticks32::from(0x7FFF_FFFFu32)
+        );
+        assert_eq!(
+            Ticks64::from(u64::MAX - 2).midpoint(Ticks64::from(2u64)),
+            Ticks64::from(u64::MAX)
+        );
+    }
 }